@@ -0,0 +1,124 @@
+/* Benchmarks for the solver's hot paths at a few cast sizes. Every fixture is built from a fixed
+   seed, so each run measures the same game states. To catch a slowdown, save a baseline before a
+   change and compare against it after:
+
+       cargo bench --bench solver -- --save-baseline before
+       cargo bench --bench solver -- --baseline before
+
+   Criterion keeps baselines under target/criterion and reports which benchmarks regressed. No
+   baseline is committed: timings only compare on the machine that took them, so save one locally */
+#![allow(clippy::needless_return)]
+
+use are_you_the_one::bruteforce::BruteForce;
+use are_you_the_one::contestant::ContestantPair;
+use are_you_the_one::deduction::{deduce_from_possibilities, MATCHING_CHECK_LIMIT};
+use are_you_the_one::gamemaster::GameMaster;
+use are_you_the_one::gamestrategy::{BoothRules, GameStrategy};
+use are_you_the_one::registry::{StrategyConfig, StrategyRegistry};
+use are_you_the_one::round::SavedRound;
+use are_you_the_one::simulation::play_seeded;
+use are_you_the_one::solutionspace::SolutionSpace;
+use are_you_the_one::stats::StatsCollector;
+use are_you_the_one::utils::{contestants_to_pairs, pairs_to_contestants};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashSet;
+
+const SEED: u64 = 2022;
+const SIZES: [usize; 3] = [8, 12, 16];
+// Weeks played before measuring, so the solver has saved rounds and fewer possibilities
+const WEEKS: usize = 3;
+
+/* A game a few weeks in, with a brute force solver told everything that happened. The ceremonies
+   and booths are drawn from the seed rather than picked by the solver, so they never change */
+fn fixture(num_contestants: usize) -> (GameMaster, BruteForce, Vec<Vec<ContestantPair>>) {
+    let mut game = GameMaster::initialize_game(num_contestants as i32, 500).with_seed(SEED);
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let mut solver = BruteForce::initialize(game.contestants());
+    solver.game_start(&game.contestants(), &game.rules());
+    let mut ceremonies = vec![];
+    for _week in 0..WEEKS {
+        let mut seating = pairs_to_contestants(&game.matches);
+        seating.shuffle(&mut rng);
+        let ceremony = contestants_to_pairs(&seating);
+        let beams = game.ceremony(&ceremony).unwrap();
+        solver.ceremony_feedback(beams, ceremony.clone());
+        ceremonies.push(ceremony);
+
+        seating.shuffle(&mut rng);
+        for feedback in game.booth_week(vec![ContestantPair::new(seating[0].clone(), seating[1].clone())]) {
+            solver.booth_feedback(feedback);
+        }
+    }
+    assert!(game.in_progress(), "The seed should leave the fixture game unsolved");
+    return (game, solver, ceremonies);
+}
+
+fn bruteforce(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bruteforce");
+    for num_contestants in SIZES {
+        let (_, solver, _) = fixture(num_contestants);
+        group.bench_with_input(BenchmarkId::new("possible_pairing", num_contestants), &solver, |b, solver| b.iter(|| solver.possible_pairing()));
+        group.bench_with_input(BenchmarkId::new("highest_prob_player", num_contestants), &solver, |b, solver| b.iter(|| solver.highest_prob_player(&HashSet::new())));
+    }
+    group.finish();
+}
+
+fn rounds(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rounds");
+    for num_contestants in SIZES {
+        let (_, mut solver, ceremonies) = fixture(num_contestants);
+        assert!(!solver.round_manager.rounds.is_empty(), "Without saved rounds there is nothing to decide");
+        group.bench_function(BenchmarkId::new("should_use_round", num_contestants), |b| {
+            b.iter(|| solver.round_manager.should_use_round(&solver.possibilities))
+        });
+
+        // A quarter of the seats ruled out, as after a run of booths
+        let round = SavedRound::new(ceremonies[0].clone(), 1, 1);
+        let ruled_out: Vec<ContestantPair> = ceremonies[0].iter().step_by(4).cloned().collect();
+        group.bench_function(BenchmarkId::new("eliminate_guesses", num_contestants), |b| {
+            b.iter_batched(|| round.clone(), |mut round| round.eliminate_guesses(&ruled_out), BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+fn solution_counting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solution_space");
+    for num_contestants in SIZES {
+        let (game, _, _) = fixture(num_contestants);
+        group.bench_with_input(BenchmarkId::new("count", num_contestants), game.history(), |b, history| b.iter(|| SolutionSpace::from_history(history).count()));
+    }
+    group.finish();
+}
+
+fn deduction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deduction");
+    // The perfect matching check runs once per pair, so it is also measured at the largest cast it is used for
+    for num_contestants in SIZES.into_iter().chain([MATCHING_CHECK_LIMIT]) {
+        let (_, solver, _) = fixture(num_contestants);
+        group.bench_with_input(BenchmarkId::new("from_possibilities", num_contestants), &solver.possibilities, |b, possibilities| {
+            b.iter(|| deduce_from_possibilities(possibilities))
+        });
+    }
+    group.finish();
+}
+
+fn games(c: &mut Criterion) {
+    let registry = StrategyRegistry::with_defaults();
+    let config = StrategyConfig::new("bruteforce");
+    let mut group = c.benchmark_group("game");
+    // A whole game takes long enough that the default hundred samples would drag on
+    group.sample_size(10);
+    for num_contestants in SIZES {
+        group.bench_function(BenchmarkId::new("bruteforce", num_contestants), |b| {
+            b.iter(|| play_seeded(num_contestants as i32, 500, &BoothRules::default(), &registry, &config, SEED, &mut StatsCollector::new()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bruteforce, rounds, solution_counting, deduction, games);
+criterion_main!(benches);
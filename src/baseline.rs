@@ -0,0 +1,488 @@
+use crate::contestant::{ContestantPair, ContestantPairs, Player};
+use crate::gamestrategy::{Feedback, GameRules, GameStrategy};
+use crate::utils::{contestants_to_pairs, pairs_to_contestants};
+use log::{debug, trace};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+
+/* These are reference strategies used to measure how much BruteForce actually helps.
+   None of them try to be clever, they only differ in how much of the feedback they remember. */
+
+/* Pairs up the given players at random while avoiding the forbidden pairs. If no such pairing
+   exists, the forbidden pairs are ignored rather than failing the ceremony. */
+fn random_pairing<R: Rng>(players: &[Player], forbidden: &HashSet<ContestantPair>, rng: &mut R) -> Vec<ContestantPair> {
+    let mut shuffled = players.to_vec();
+    shuffled.shuffle(rng);
+
+    let mut pairing = vec![];
+    if pair_remaining(&shuffled, forbidden, &mut pairing, rng) {
+        return pairing;
+    }
+    debug!("No pairing avoids all {} forbidden pairs, ignoring them", forbidden.len());
+    return contestants_to_pairs(&shuffled);
+}
+
+/* Depth first search that pairs the first remaining player with a random allowed partner */
+fn pair_remaining<R: Rng>(
+    remaining: &[Player],
+    forbidden: &HashSet<ContestantPair>,
+    pairing: &mut Vec<ContestantPair>,
+    rng: &mut R,
+) -> bool {
+    if remaining.is_empty() {
+        return true;
+    }
+    let player = &remaining[0];
+    let mut partners: Vec<usize> = (1..remaining.len()).collect();
+    partners.shuffle(rng);
+
+    for i in partners {
+        let pair = ContestantPair::new(player.clone(), remaining[i].clone());
+        if forbidden.contains(&pair) {
+            continue;
+        }
+        let rest: Vec<Player> = remaining[1..]
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| j + 1 != i)
+            .map(|(_, p)| p.clone())
+            .collect();
+
+        pairing.push(pair);
+        if pair_remaining(&rest, forbidden, pairing, rng) {
+            return true;
+        }
+        pairing.pop();
+    }
+    return false;
+}
+
+/* Players who are not part of an already confirmed perfect match */
+fn unmatched_players(contestants: &[Player], right_matches: &[ContestantPair]) -> Vec<Player> {
+    let matched = pairs_to_contestants(right_matches);
+    return contestants.iter().filter(|c| !matched.contains(c)).cloned().collect();
+}
+
+/* Every pair of unmatched players that the truth booth hasn't ruled out yet */
+fn untested_pairs(
+    contestants: &[Player],
+    right_matches: &[ContestantPair],
+    wrong_matches: &HashSet<ContestantPair>,
+) -> Vec<ContestantPair> {
+    let unmatched = unmatched_players(contestants, right_matches);
+    let mut candidates = vec![];
+    for (i, a) in unmatched.iter().enumerate() {
+        for b in unmatched[i + 1..].iter() {
+            let pair = ContestantPair::new(a.clone(), b.clone());
+            if !wrong_matches.contains(&pair) {
+                candidates.push(pair);
+            }
+        }
+    }
+    return candidates;
+}
+
+/* Confirmed, or between unmatched players and not ruled out */
+fn is_open_pair(pair: &ContestantPair, right_matches: &[ContestantPair], wrong_matches: &HashSet<ContestantPair>) -> bool {
+    if right_matches.contains(pair) {
+        return true;
+    }
+    let matched = pairs_to_contestants(right_matches);
+    return !wrong_matches.contains(pair) && !matched.contains(pair.get_a()) && !matched.contains(pair.get_b());
+}
+
+/* The confirmed matches plus the last ceremony's other couples, or an arbitrary pairing of
+   the unmatched players when the last ceremony contradicts what has been confirmed since */
+fn guess_from_ceremony(contestants: &[Player], right_matches: &[ContestantPair], ceremony: &[ContestantPair]) -> Vec<ContestantPair> {
+    if !ceremony.is_empty() && right_matches.iter().all(|pair| ceremony.contains(pair)) {
+        return ceremony.to_vec();
+    }
+    let mut guess = right_matches.to_vec();
+    guess.extend(contestants_to_pairs(&unmatched_players(contestants, right_matches)));
+    return guess;
+}
+
+/* Only the truth booth is trusted, so the answer is known once all but one couple is confirmed */
+fn booth_confidence(contestants: &[Player], right_matches: &[ContestantPair]) -> f64 {
+    if right_matches.len() + 1 >= contestants.len() / 2 {
+        return 1.0;
+    }
+    return 0.0;
+}
+
+/* Ignores every piece of feedback. Both the ceremony and the booth are picked completely at random */
+pub struct FullyRandom {
+    contestants: Vec<Player>,
+    last_ceremony: Vec<ContestantPair>,
+    rng: StdRng,
+}
+
+impl FullyRandom {
+    pub fn initialize(contestants: Vec<&Player>) -> Self {
+        Self {
+            contestants: contestants.into_iter().cloned().collect(),
+            last_ceremony: vec![],
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl GameStrategy for FullyRandom {
+    fn game_start(&mut self, _contestants: &[&Player], rules: &GameRules) {
+        if let Some(seed) = rules.seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+    }
+
+    fn send_to_booth(&mut self) -> ContestantPair {
+        let picked: Vec<&Player> = self.contestants.choose_multiple(&mut self.rng, 2).collect();
+        return ContestantPair::new(picked[0].clone(), picked[1].clone());
+    }
+
+    fn booth_feedback(&mut self, _feedback: Feedback) {}
+
+    fn ceremony_pairs(&mut self) -> Vec<ContestantPair> {
+        self.last_ceremony = random_pairing(&self.contestants, &HashSet::new(), &mut self.rng);
+        return self.last_ceremony.clone();
+    }
+
+    fn ceremony_feedback(&mut self, _num_right: usize, _guess: Vec<ContestantPair>) {}
+
+    fn best_guess(&self) -> Vec<ContestantPair> {
+        return guess_from_ceremony(&self.contestants, &[], &self.last_ceremony);
+    }
+}
+
+/* Picks randomly, but never repeats a pair the truth booth said was wrong and
+   keeps every pair the truth booth confirmed */
+pub struct ConsistentRandom {
+    contestants: Vec<Player>,
+    right_matches: Vec<ContestantPair>,
+    wrong_matches: HashSet<ContestantPair>,
+    last_ceremony: Vec<ContestantPair>,
+    rng: StdRng,
+}
+
+impl ConsistentRandom {
+    pub fn initialize(contestants: Vec<&Player>) -> Self {
+        Self {
+            contestants: contestants.into_iter().cloned().collect(),
+            right_matches: vec![],
+            wrong_matches: HashSet::new(),
+            last_ceremony: vec![],
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl GameStrategy for ConsistentRandom {
+    fn game_start(&mut self, _contestants: &[&Player], rules: &GameRules) {
+        if let Some(seed) = rules.seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+    }
+
+    fn send_to_booth(&mut self) -> ContestantPair {
+        let candidates = untested_pairs(&self.contestants, &self.right_matches, &self.wrong_matches);
+        return candidates.choose(&mut self.rng).expect("There should be an untested pair left").clone();
+    }
+
+    fn booth_feedback(&mut self, feedback: Feedback) {
+        match feedback {
+            Feedback::Correct(pair) => self.right_matches.push(pair),
+            Feedback::Wrong(pair) => {
+                self.wrong_matches.insert(pair);
+            }
+            Feedback::Hidden(_) => (),
+        }
+    }
+
+    fn exclude_pairs(&mut self, pairs: &[ContestantPair]) {
+        self.wrong_matches.extend(pairs.iter().cloned());
+    }
+
+    fn ceremony_pairs(&mut self) -> Vec<ContestantPair> {
+        let unmatched = unmatched_players(&self.contestants, &self.right_matches);
+        let mut pairs = self.right_matches.clone();
+        pairs.extend(random_pairing(&unmatched, &self.wrong_matches, &mut self.rng));
+        self.last_ceremony = pairs.clone();
+        return pairs;
+    }
+
+    fn ceremony_feedback(&mut self, _num_right: usize, _guess: Vec<ContestantPair>) {}
+
+    fn best_guess(&self) -> Vec<ContestantPair> {
+        return guess_from_ceremony(&self.contestants, &self.right_matches, &self.last_ceremony);
+    }
+
+    fn confidence(&self) -> f64 {
+        return booth_confidence(&self.contestants, &self.right_matches);
+    }
+
+    fn is_open(&self, pair: &ContestantPair) -> bool {
+        return is_open_pair(pair, &self.right_matches, &self.wrong_matches);
+    }
+
+    fn stats(&self) -> Vec<(String, f64)> {
+        return vec![
+            (String::from("confirmed_matches"), self.right_matches.len() as f64),
+            (String::from("ruled_out_pairs"), self.wrong_matches.len() as f64),
+        ];
+    }
+}
+
+/* Plays the way the cast usually does. Couples from the best scoring ceremony so far stay
+   together (as many as it had beams), everyone else is shuffled, and the truth booth is
+   spent on one of the kept couples. */
+pub struct BeamHeuristic {
+    contestants: Vec<Player>,
+    right_matches: Vec<ContestantPair>,
+    wrong_matches: HashSet<ContestantPair>,
+    best_ceremony: Option<(Vec<ContestantPair>, usize)>,
+    kept_couples: Vec<ContestantPair>,
+    last_ceremony: Vec<ContestantPair>,
+    rng: StdRng,
+}
+
+impl BeamHeuristic {
+    pub fn initialize(contestants: Vec<&Player>) -> Self {
+        Self {
+            contestants: contestants.into_iter().cloned().collect(),
+            right_matches: vec![],
+            wrong_matches: HashSet::new(),
+            best_ceremony: None,
+            kept_couples: vec![],
+            last_ceremony: vec![],
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /* The couples of the best ceremony that are still worth keeping, along with how many
+       beams they are thought to be worth (confirmed matches are not counted) */
+    fn best_couples(&self) -> (Vec<ContestantPair>, usize) {
+        return match &self.best_ceremony {
+            Some((pairs, beams)) => {
+                // Couples that split up a confirmed match can't be kept either
+                let matched = pairs_to_contestants(&self.right_matches);
+                let open: Vec<ContestantPair> = pairs
+                    .iter()
+                    .filter(|p| !matched.contains(p.get_a()) && !matched.contains(p.get_b()))
+                    .filter(|p| !self.wrong_matches.contains(p))
+                    .cloned()
+                    .collect();
+                let confirmed = pairs.iter().filter(|p| self.right_matches.contains(p)).count();
+                (open, beams.saturating_sub(confirmed))
+            }
+            None => (vec![], 0),
+        };
+    }
+}
+
+impl GameStrategy for BeamHeuristic {
+    fn game_start(&mut self, _contestants: &[&Player], rules: &GameRules) {
+        if let Some(seed) = rules.seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+    }
+
+    fn send_to_booth(&mut self) -> ContestantPair {
+        let kept: Vec<&ContestantPair> = self
+            .kept_couples
+            .iter()
+            .filter(|p| !self.right_matches.contains(p) && !self.wrong_matches.contains(p))
+            .collect();
+        if let Some(pair) = kept.choose(&mut self.rng) {
+            return (*pair).clone();
+        }
+
+        // Nobody is worth keeping, so send any untested couple instead
+        let candidates = untested_pairs(&self.contestants, &self.right_matches, &self.wrong_matches);
+        return candidates.choose(&mut self.rng).expect("There should be an untested pair left").clone();
+    }
+
+    fn booth_feedback(&mut self, feedback: Feedback) {
+        match feedback {
+            Feedback::Correct(pair) => self.right_matches.push(pair),
+            Feedback::Wrong(pair) => {
+                self.wrong_matches.insert(pair);
+            }
+            Feedback::Hidden(_) => (),
+        }
+    }
+
+    fn exclude_pairs(&mut self, pairs: &[ContestantPair]) {
+        self.wrong_matches.extend(pairs.iter().cloned());
+    }
+
+    fn ceremony_pairs(&mut self) -> Vec<ContestantPair> {
+        let (open_couples, beams) = self.best_couples();
+        self.kept_couples = open_couples.choose_multiple(&mut self.rng, beams).cloned().collect();
+        trace!("Keeping couples:\n{}", ContestantPairs(&self.kept_couples));
+
+        // Don't put anyone back with the partner they were just split from
+        let mut forbidden = self.wrong_matches.clone();
+        forbidden.extend(open_couples.iter().filter(|p| !self.kept_couples.contains(p)).cloned());
+
+        let mut pairs = self.right_matches.clone();
+        pairs.extend(self.kept_couples.clone());
+        let unmatched = unmatched_players(&self.contestants, &pairs);
+        pairs.extend(random_pairing(&unmatched, &forbidden, &mut self.rng));
+        self.last_ceremony = pairs.clone();
+        return pairs;
+    }
+
+    fn ceremony_feedback(&mut self, num_right: usize, guess: Vec<ContestantPair>) {
+        let is_better = match &self.best_ceremony {
+            Some((_, best_beams)) => num_right >= *best_beams,
+            None => true,
+        };
+        if num_right == self.right_matches.len() {
+            // Only the confirmed matches lit up, so every other couple is wrong
+            for pair in guess.iter().filter(|p| !self.right_matches.contains(p)) {
+                self.wrong_matches.insert(pair.clone());
+            }
+        } else if is_better {
+            self.best_ceremony = Some((guess, num_right));
+        }
+    }
+
+    fn best_guess(&self) -> Vec<ContestantPair> {
+        return match &self.best_ceremony {
+            Some((pairs, _)) => guess_from_ceremony(&self.contestants, &self.right_matches, pairs),
+            None => guess_from_ceremony(&self.contestants, &self.right_matches, &self.last_ceremony),
+        };
+    }
+
+    fn confidence(&self) -> f64 {
+        return booth_confidence(&self.contestants, &self.right_matches);
+    }
+
+    fn is_open(&self, pair: &ContestantPair) -> bool {
+        return is_open_pair(pair, &self.right_matches, &self.wrong_matches);
+    }
+
+    fn stats(&self) -> Vec<(String, f64)> {
+        let best_beams = self.best_ceremony.as_ref().map(|(_, beams)| *beams).unwrap_or(0);
+        return vec![
+            (String::from("confirmed_matches"), self.right_matches.len() as f64),
+            (String::from("ruled_out_pairs"), self.wrong_matches.len() as f64),
+            (String::from("best_ceremony_beams"), best_beams as f64),
+            (String::from("kept_couples"), self.kept_couples.len() as f64),
+        ];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::baseline::{random_pairing, BeamHeuristic, ConsistentRandom, FullyRandom};
+    use crate::contestant::{ContestantPair, Player};
+    use crate::gamestrategy::{BoothRules, Feedback, GameStrategy};
+    use crate::registry::{StrategyConfig, StrategyRegistry};
+    use crate::simulation::play_seeded;
+    use crate::stats::StatsCollector;
+    use crate::utils::{contestants_to_pairs, gen_contestants, pairs_to_contestants};
+    use rand::thread_rng;
+    use std::collections::HashSet;
+
+    fn is_perfect_matching(pairs: &[ContestantPair], contestants: &[Player]) -> bool {
+        let players: HashSet<Player> = pairs_to_contestants(pairs).into_iter().collect();
+        return pairs.len() * 2 == contestants.len() && players.len() == contestants.len();
+    }
+
+    #[test]
+    fn test_random_pairing_avoids_forbidden() {
+        let c = gen_contestants(6);
+        // Only (0, 1) (2, 3) (4, 5) is left once the other pairs with 0 and 2 are forbidden
+        let forbidden: HashSet<ContestantPair> = vec![
+            ContestantPair::new(c[0].clone(), c[2].clone()),
+            ContestantPair::new(c[0].clone(), c[3].clone()),
+            ContestantPair::new(c[0].clone(), c[4].clone()),
+            ContestantPair::new(c[0].clone(), c[5].clone()),
+            ContestantPair::new(c[2].clone(), c[4].clone()),
+            ContestantPair::new(c[2].clone(), c[5].clone()),
+        ]
+        .into_iter()
+        .collect();
+
+        for _ in 0..10 {
+            let pairing = random_pairing(&c, &forbidden, &mut thread_rng());
+            assert!(is_perfect_matching(&pairing, &c));
+            for pair in contestants_to_pairs(&c).iter() {
+                assert!(pairing.contains(pair));
+            }
+        }
+    }
+
+    #[test]
+    fn test_fully_random_valid() {
+        let c = gen_contestants(10);
+        let mut strategy = FullyRandom::initialize(c.iter().collect());
+
+        assert!(is_perfect_matching(&strategy.ceremony_pairs(), &c));
+        let booth = strategy.send_to_booth();
+        assert_ne!(booth.get_a(), booth.get_b());
+    }
+
+    #[test]
+    fn test_consistent_random_respects_booth() {
+        let c = gen_contestants(6);
+        let mut strategy = ConsistentRandom::initialize(c.iter().collect());
+        let right = ContestantPair::new(c[0].clone(), c[1].clone());
+        let wrong = ContestantPair::new(c[2].clone(), c[3].clone());
+
+        strategy.booth_feedback(Feedback::Correct(right.clone()));
+        strategy.booth_feedback(Feedback::Wrong(wrong.clone()));
+
+        for _ in 0..10 {
+            let ceremony = strategy.ceremony_pairs();
+            assert!(is_perfect_matching(&ceremony, &c));
+            assert!(ceremony.contains(&right));
+            assert!(!ceremony.contains(&wrong));
+
+            let booth = strategy.send_to_booth();
+            assert_ne!(booth, wrong);
+            assert!(!booth.has_player(&c[0]) && !booth.has_player(&c[1]));
+        }
+    }
+
+    #[test]
+    fn test_beam_heuristic_keeps_couples() {
+        let c = gen_contestants(8);
+        let mut strategy = BeamHeuristic::initialize(c.iter().collect());
+
+        let first = strategy.ceremony_pairs();
+        strategy.ceremony_feedback(2, first.clone());
+
+        // Exactly as many couples as there were beams are kept together
+        let second = strategy.ceremony_pairs();
+        assert!(is_perfect_matching(&second, &c));
+        assert_eq!(second.iter().filter(|p| first.contains(p)).count(), 2);
+        assert!(first.contains(&strategy.send_to_booth()));
+    }
+
+    #[test]
+    fn test_beam_heuristic_zero_beams() {
+        let c = gen_contestants(8);
+        let mut strategy = BeamHeuristic::initialize(c.iter().collect());
+
+        let first = strategy.ceremony_pairs();
+        strategy.ceremony_feedback(0, first.clone());
+
+        for _ in 0..10 {
+            let ceremony = strategy.ceremony_pairs();
+            assert!(ceremony.iter().all(|p| !first.contains(p)));
+        }
+    }
+
+    #[test]
+    fn test_seeded_game_replays() {
+        // The seed picks the matching, the house's choices and the strategy's own coin flips
+        let registry = StrategyRegistry::with_defaults();
+        for name in ["random", "consistent_random", "beam_heuristic", "bruteforce"] {
+            let play = || play_seeded(10, 30, &BoothRules::default(), &registry, &StrategyConfig::new(name), 11, &mut StatsCollector::new()).unwrap();
+            assert_eq!(play().history(), play().history());
+        }
+    }
+}
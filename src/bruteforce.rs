@@ -1,29 +1,73 @@
 use crate::pretty_string_poss;
 use crate::contestant::ContestantPairs;
-use crate::contestant::{ContestantPair, Player};
-use crate::gamestrategy::{Feedback, GameStrategy};
+use crate::contestant::{Contestant, ContestantPair, Player};
+use crate::ceremony::{design_with_filler, independent_beams, most_likely_ceremony, BeamTarget, CeremonyMode, ModePolicy, PairConstraints};
+use crate::deduction::{deduce, exact_space, Deduction, Reason, EXACT_LIMIT};
+use crate::explanation::{CandidateScore, Constraint, Decision, Explanation};
+use crate::gamestrategy::{Feedback, GameRules, GameStrategy};
+use crate::history::{History, Observation};
+use crate::openingbook::OpeningBook;
+use crate::proof::{Justified, Proof};
 use crate::roundmanager::RoundManager;
-use crate::utils::pairs_to_contestants;
+use crate::utils::{binary_entropy, pairs_to_contestants};
+use crate::whatif::{analyze_action_in, Action};
 use crate::Players;
 use log::{debug, error, info, trace};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::iter::Iterator;
 
-pub struct BruteForce {
-    contestants: HashSet<Player>,
-    right_matches: HashSet<ContestantPair>,
-    pub possibilities: HashMap<Player, HashSet<Player>>,
-    pub round_manager: RoundManager,
+pub struct BruteForce<P: Contestant = Player> {
+    contestants: HashSet<P>,
+    right_matches: HashSet<ContestantPair<P>>,
+    pub possibilities: HashMap<P, HashSet<P>>,
+    pub round_manager: RoundManager<P>,
+    // Whether saved ceremonies may pick the truth booth pair instead of the possibilities
+    pub round_optimization: bool,
+    // Whether pairs decided by the possibilities alone are confirmed or ruled out after each observation
+    pub deduction: bool,
+    // Whether ceremonies seat the most likely complete matching instead of the first one found
+    pub likely_ceremony: bool,
+    // Picks between winning and information ceremonies each week. Without one every ceremony goes for the win
+    pub mode_policy: Option<ModePolicy>,
+    // Overrides the policy for the next ceremony only
+    pub next_mode: Option<CeremonyMode>,
+    pub beam_target: BeamTarget,
+    // Moves to play while the game is still in a state the book covers
+    pub opening_book: Option<OpeningBook>,
+    // Picks between the book's equivalent moves, seeded from the game when it has a seed
+    rng: StdRng,
+    // Everything observed so far, which is how the opening book recognizes the state
+    history: History<P>,
+    // Whether each pair's proof is checked against the history before the pair is decided. Decisions
+    // that don't hold up are refused, so a bookkeeping bug can't rule out the true matching
+    pub sound: bool,
+    // Every pair confirmed or ruled out so far, in order, with what justified it
+    proofs: Vec<Justified<P>>,
+    // The ceremony each saved round was made from, by round id
+    round_observations: HashMap<u32, usize>,
+    weeks_left: usize,
+    // Bits of expected information given up so far to follow seating rules
+    information_lost: f64,
+    // Most possible pairs held while making a decision, a rough measure of how much memory the game took
+    peak_possibilities: usize,
+    last_decision: Option<Explanation<P>>,
 }
 
 #[derive(Debug, PartialEq)]
-pub enum PairPlayerResult {
-    Ok(ContestantPair),
+pub enum PairPlayerResult<P: Contestant = Player> {
+    Ok(ContestantPair<P>),
     Contradiction,
 }
 
-impl BruteForce {
+impl<P: Contestant> BruteForce<P> {
+    // How many of the next best booth pairs are kept in an explanation
+    const NUM_RUNNERS_UP: usize = 5;
+    // Passes of partner swaps when designing an information ceremony
+    const DESIGN_PASSES: usize = 10;
+
     /*
     This algorithm attempts to match people who have never been matched before until
     there is at least 1 success. It then keeps going to the truth booth until that
@@ -32,8 +76,9 @@ impl BruteForce {
 
     */
 
-    pub fn initialize(contestants: Vec<&Player>) -> Self {
-        let cloned_contestants: HashSet<Player> =
+    pub fn initialize(contestants: Vec<&P>) -> Self {
+        let history = History::new(contestants.iter().map(|&player| player.clone()).collect());
+        let cloned_contestants: HashSet<P> =
             HashSet::from_iter(contestants.into_iter().cloned());
         let mut possibilities = HashMap::new();
 
@@ -51,11 +96,27 @@ impl BruteForce {
             contestants: cloned_contestants,
             right_matches: HashSet::new(),
             round_manager: RoundManager::new(),
+            round_optimization: true,
+            deduction: true,
+            likely_ceremony: false,
+            mode_policy: None,
+            next_mode: None,
+            beam_target: BeamTarget::EvenSplit,
+            opening_book: None,
+            rng: StdRng::from_entropy(),
+            history,
+            sound: false,
+            proofs: vec![],
+            round_observations: HashMap::new(),
+            weeks_left: usize::MAX,
+            information_lost: 0.0,
+            last_decision: None,
+            peak_possibilities: 0,
             possibilities,
         }
     }
 
-    fn already_guessed(&self, p1: &Player, p2: &Player) -> bool {
+    fn already_guessed(&self, p1: &P, p2: &P) -> bool {
         if !self.contestants.contains(p1) || !self.contestants.contains(p2) {
             panic!("Player checked is not a part of the game!");
         }
@@ -79,12 +140,12 @@ impl BruteForce {
         This should only be used when eliminating a single possibility and not when a
         correct pair is found.
     */
-    fn remove_guess(&mut self, pair: &ContestantPair) {
+    fn remove_guess(&mut self, pair: &ContestantPair<P>) {
         let p1 = pair.get_a();
         let p2 = pair.get_b();
         if !self.already_guessed(p1, p2) {
             // Throw error is somehow a hashset contains no options left
-            if self.possibilities[p1].len() == 0 || self.possibilities[p2].len() == 0 {
+            if self.possibilities[p1].is_empty() || self.possibilities[p2].is_empty() {
                 panic!("Book keeping error for {}. Not possible to reach point when only <= 1 possibility left and it needs to be removed", p1)
             }
             self.possibilities.get_mut(p1).expect("Attempted to remove p2 from p1 guesses").remove(p2);
@@ -95,7 +156,74 @@ impl BruteForce {
         }
     }
 
-    fn pair_player(&self, player: &Player, off_limits: &HashSet<Player>) -> PairPlayerResult {
+    /* Removes the pair with the proof on record. Returns whether it was removed, which in sound
+    mode it isn't when the proof doesn't hold up */
+    fn rule_out(&mut self, pair: &ContestantPair<P>, proof: Proof<P>) -> bool {
+        if self.already_guessed(pair.get_a(), pair.get_b()) {
+            return false;
+        }
+        let justified = Justified { pair: pair.clone(), is_match: false, proof };
+        if !self.holds_up(&justified) {
+            return false;
+        }
+        self.remove_guess(pair);
+        self.proofs.push(justified);
+        return true;
+    }
+
+    /* Confirms the pair with the proof on record, the same way rule_out removes one */
+    fn confirm(&mut self, pair: ContestantPair<P>, proof: Proof<P>) -> bool {
+        if self.right_matches.contains(&pair) {
+            return false;
+        }
+        let justified = Justified { pair: pair.clone(), is_match: true, proof };
+        if !self.holds_up(&justified) {
+            return false;
+        }
+        self.add_perfect_match(pair);
+        self.proofs.push(justified);
+        return true;
+    }
+
+    /* Always true outside of sound mode */
+    fn holds_up(&self, justified: &Justified<P>) -> bool {
+        if !self.sound {
+            return true;
+        }
+        if !justified.verify(&self.history, &self.proofs) {
+            error!("Refused to decide a pair, the proof doesn't hold up: {}", justified);
+            return false;
+        }
+        return true;
+    }
+
+    /* The proof for a deduction, citing the ceremonies behind the saved rounds it counted beams in.
+    The possibility rules build on every pair decided so far, so they cite what those relied on */
+    fn deduced(&self, reason: Reason<P>) -> Proof<P> {
+        let mut observations: Vec<usize> = match &reason {
+            Reason::OnlyOption(_) | Reason::NoPerfectMatching => self.proofs.iter().flat_map(|justified| justified.proof.observations()).collect(),
+            Reason::CeremonyCount { round_id } => self.ceremonies_of(&[*round_id]),
+            Reason::OverlappingCeremonies { round_id, other_round_id } => self.ceremonies_of(&[*round_id, *other_round_id]),
+            Reason::EveryMatching => self.ceremonies_of(&self.round_manager.rounds.iter().map(|round| round.round_id).collect::<Vec<u32>>()),
+        };
+        observations.sort();
+        observations.dedup();
+        return Proof::Deduced { reason, observations };
+    }
+
+    /* Where the saved rounds' ceremonies are in the history. A round saved without one on record is
+    left out, so a proof resting on it won't hold up */
+    fn ceremonies_of(&self, round_ids: &[u32]) -> Vec<usize> {
+        return round_ids.iter().filter_map(|round_id| self.round_observations.get(round_id).cloned()).collect();
+    }
+
+    /* Index of the observation just recorded */
+    fn last_observation(&self) -> usize {
+        return self.history.observations.len() - 1;
+    }
+
+    #[allow(dead_code)]
+    fn pair_player(&self, player: &P, off_limits: &HashSet<P>) -> PairPlayerResult<P> {
         // Given a player, try to get a player who isn't already picked.
         let player_poss = self
             .possibilities
@@ -114,9 +242,9 @@ impl BruteForce {
     }
 
     /*This finds a player in the current set of possibilities that has the fewest
-    number of possibilities left.*/
-    fn highest_prob_player(&self, filtered: &HashSet<Player>) -> Option<Player> {
-        let mut best_player: Option<(&Player, usize)> = None;
+    number of possibilities left. Ties go to the lowest player, so the pick doesn't depend on hashing*/
+    pub fn highest_prob_player(&self, filtered: &HashSet<P>) -> Option<P> {
+        let mut best_player: Option<(&P, usize)> = None;
         for (player_key, poss_pairs) in self.possibilities.iter() {
             if filtered.contains(player_key) {
                 continue;
@@ -124,7 +252,7 @@ impl BruteForce {
 
             match best_player {
                 Some(best) => {
-                    if poss_pairs.len() < best.1 {
+                    if (poss_pairs.len(), player_key) < (best.1, best.0) {
                         best_player = Some((player_key, poss_pairs.len()))
                     }
                 }
@@ -143,24 +271,24 @@ impl BruteForce {
     }
 
     fn filter_possibilities(
-        possible_pairs: &HashSet<Player>,
-        off_limits: &HashSet<Player>,
-    ) -> HashSet<Player> {
-        let difference: HashSet<&Player> = possible_pairs.difference(&off_limits).collect();
+        possible_pairs: &HashSet<P>,
+        off_limits: &HashSet<P>,
+    ) -> HashSet<P> {
+        let difference: HashSet<&P> = possible_pairs.difference(off_limits).collect();
         return difference.into_iter().map(|item| item.to_owned()).collect();
     }
 
     /* This does an initial depth first search for a pair, and if it is
     not possible to form a pair with the given combination, then it
     works backwards from the contraditing pair to find said pair */
-    fn possible_pairing(&self) -> Vec<ContestantPair> {
+    pub fn possible_pairing(&self) -> Vec<ContestantPair<P>> {
         // Poss stack keeps track of players and their possibilities (which are filtered as you go down the stack)
         // Current pairing is the actual pairing
-        let mut poss_stack: Vec<(Player, HashSet<Player>)> = Vec::new();
-        let mut current_pairing: Vec<ContestantPair> = Vec::new();
+        let mut poss_stack: Vec<(P, HashSet<P>)> = Vec::new();
+        let mut current_pairing: Vec<ContestantPair<P>> = Vec::new();
 
-        if self.right_matches.len() > 0 {
-            current_pairing.extend(self.right_matches.clone());
+        if !self.right_matches.is_empty() {
+            current_pairing.extend(self.sorted_matches());
         }
 
         {
@@ -178,11 +306,11 @@ impl BruteForce {
 
             // from the top of the stack, add the next stack of possibilities
             let (player_a, player_a_poss) = poss_stack.last().unwrap().clone();
-            let player_a_match = player_a_poss.iter().next();
+            let player_a_match = player_a_poss.iter().min();
             
             if let Some(player_b) = player_a_match {
                 // add to the current pairing
-                poss_stack.last_mut().unwrap().1.remove(&player_b);
+                poss_stack.last_mut().unwrap().1.remove(player_b);
                 current_pairing.push(ContestantPair::new(player_a.clone(), player_b.clone()));
 
                 trace!(
@@ -195,7 +323,7 @@ impl BruteForce {
                 );
 
                 // create the next stack of possibilities
-                let off_limits: HashSet<Player> =
+                let off_limits: HashSet<P> =
                     pairs_to_contestants(&current_pairing).into_iter().collect();
                 // trace!("Off limits: {:?}", &off_limits.iter().map(|player| player.name.clone()).collect::<Vec<String>>());
 
@@ -206,7 +334,7 @@ impl BruteForce {
                             self.possibilities.get(&new_player).unwrap().clone();
 
                         player_possibilities =
-                            BruteForce::filter_possibilities(&player_possibilities, &off_limits);
+                            Self::filter_possibilities(&player_possibilities, &off_limits);
                         trace!(
                             "New: {} - Poss [\n{}]",
                             &new_player,
@@ -227,11 +355,12 @@ impl BruteForce {
                 // Keep deleting stacks as long as they are empty (and the current pairing array to remove bad pairs)
                 debug!("Moving back up the stack......");
                 let mut new_stack = poss_stack.last_mut().unwrap();
-                while new_stack.1.len() == 0 {
+                while new_stack.1.is_empty() {
                     // new_stack.1 is the possibilities
                     trace!("stack: {:#?}", &poss_stack);
                     poss_stack.pop().unwrap();
-                    debug!("Popped pair: {:?}", current_pairing.pop());
+                    let popped = current_pairing.pop();
+                    debug!("Popped pair: {:?}", popped);
                     trace!("new stack: {:#?}", &poss_stack);
 
                     new_stack = match poss_stack.last_mut() {
@@ -256,23 +385,150 @@ impl BruteForce {
         return current_pairing;
     }
 
-    fn add_round(&mut self, guess: Vec<ContestantPair>, num_correct: usize) {
+    /* The most likely seating of the undecided players, next to the confirmed matches. Once few
+    enough players are left the pair probabilities come from every matching that is still possible */
+    fn likely_pairing(&self) -> Option<Vec<ContestantPair<P>>> {
+        let mut players: Vec<P> = self.possibilities.keys().cloned().collect();
+        players.sort();
+        let seating = if players.len() <= EXACT_LIMIT {
+            let summary = exact_space(&self.possibilities, &self.round_manager.rounds).summarize();
+            if summary.count == 0 {
+                return None;
+            }
+            most_likely_ceremony(&players, |pair| summary.probability(pair), &PairConstraints::new())
+        } else {
+            let round_probabilities = self.round_probabilities();
+            most_likely_ceremony(&players, |pair| self.pair_probability(pair, &round_probabilities), &PairConstraints::new())
+        };
+        let mut pairing = self.sorted_matches();
+        pairing.extend(seating?);
+        return Some(pairing);
+    }
+
+    /* The chance the whole pairing is right, treating its undecided pairs as independent */
+    fn win_probability(&self, pairing: &[ContestantPair<P>]) -> f64 {
+        let open: Vec<&ContestantPair<P>> = pairing.iter().filter(|pair| !self.right_matches.contains(pair)).collect();
+        if open.is_empty() {
+            return 1.0;
+        }
+        if self.possibilities.len() <= EXACT_LIMIT {
+            let summary = exact_space(&self.possibilities, &self.round_manager.rounds).summarize();
+            if summary.count == 0 {
+                return 0.0;
+            }
+            return open.iter().map(|pair| summary.probability(pair)).product();
+        }
+        let round_probabilities = self.round_probabilities();
+        return open.iter().map(|pair| self.pair_probability(pair, &round_probabilities)).product();
+    }
+
+    /* Reseats the undecided players of the pairing so the beam count is as close to the target as
+    possible. Confirmed matches stay seated together since they always light up anyway. */
+    fn information_pairing(&self, pairing: Vec<ContestantPair<P>>) -> Vec<ContestantPair<P>> {
+        let (mut confirmed, open): (Vec<ContestantPair<P>>, Vec<ContestantPair<P>>) =
+            pairing.into_iter().partition(|pair| self.right_matches.contains(pair));
+        let designed = if self.possibilities.len() <= EXACT_LIMIT {
+            let space = exact_space(&self.possibilities, &self.round_manager.rounds);
+            let summary = space.summarize();
+            let exact = |pairs: &[ContestantPair<P>]| -> Vec<f64> {
+                let analysis = analyze_action_in(&space, &Action::Ceremony(pairs.to_vec()));
+                analysis.scenarios.iter().map(|scenario| scenario.probability).collect()
+            };
+            design_with_filler(open, |pair| summary.probability(pair), &self.beam_target, exact, Self::DESIGN_PASSES)
+        } else {
+            let round_probabilities = self.round_probabilities();
+            let probability = |pair: &ContestantPair<P>| self.pair_probability(pair, &round_probabilities);
+            let independent = |pairs: &[ContestantPair<P>]| independent_beams(pairs, probability);
+            design_with_filler(open, probability, &self.beam_target, independent, Self::DESIGN_PASSES)
+        };
+        confirmed.extend(designed);
+        return confirmed;
+    }
+
+    /* Chance of each beam count among the undecided pairs of a ceremony */
+    fn beam_distribution(&self, ceremony: &[ContestantPair<P>]) -> Vec<f64> {
+        let open: Vec<ContestantPair<P>> = ceremony
+            .iter()
+            .filter(|pair| self.possibilities.contains_key(pair.get_a()) && self.possibilities.contains_key(pair.get_b()))
+            .cloned()
+            .collect();
+        if self.possibilities.len() <= EXACT_LIMIT {
+            let space = exact_space(&self.possibilities, &self.round_manager.rounds);
+            let analysis = analyze_action_in(&space, &Action::Ceremony(open));
+            return analysis.scenarios.iter().map(|scenario| scenario.probability).collect();
+        }
+        let round_probabilities = self.round_probabilities();
+        return independent_beams(&open, |pair| self.pair_probability(pair, &round_probabilities));
+    }
+
+    /* Expected bits the preferred ceremony would have revealed over the constrained one. Negative
+    when the seating rules happen to force a more informative ceremony */
+    pub fn seating_cost(&self, preferred: &[ContestantPair<P>], constrained: &[ContestantPair<P>]) -> f64 {
+        let bits = |ceremony: &[ContestantPair<P>]| BeamTarget::EvenSplit.score(&self.beam_distribution(ceremony));
+        return bits(preferred) - bits(constrained);
+    }
+
+    /* The most likely ceremony that follows the seating rules, confirmed matches included */
+    fn constrained_pairing(&self, seating: &PairConstraints<P>) -> Option<Vec<ContestantPair<P>>> {
+        let mut players: Vec<P> = self.contestants.iter().cloned().collect();
+        players.sort();
+        let summary = if self.possibilities.len() <= EXACT_LIMIT && !self.possibilities.is_empty() {
+            Some(exact_space(&self.possibilities, &self.round_manager.rounds).summarize())
+        } else {
+            None
+        };
+        if let Some(summary) = summary.filter(|summary| summary.count > 0) {
+            let probability = |pair: &ContestantPair<P>| {
+                if self.right_matches.contains(pair) {
+                    return 1.0;
+                }
+                if self.already_guessed(pair.get_a(), pair.get_b()) {
+                    return 0.0;
+                }
+                return summary.probability(pair);
+            };
+            return most_likely_ceremony(&players, probability, seating);
+        }
+        let round_probabilities = self.round_probabilities();
+        return most_likely_ceremony(&players, |pair| self.pair_probability(pair, &round_probabilities), seating);
+    }
+
+    fn add_round(&mut self, guess: Vec<ContestantPair<P>>, num_correct: usize) {
+        // Pairs already known to be wrong can't be where any of the beams came from
+        let ruled_out: Vec<ContestantPair<P>> = guess
+            .iter()
+            .filter(|pair| !self.right_matches.contains(pair) && self.already_guessed(pair.get_a(), pair.get_b()))
+            .cloned()
+            .collect();
         self.round_manager.add_round(guess, num_correct);
         self.round_manager.eliminate_guesses(Vec::from_iter(self.right_matches.clone()));
+        self.round_manager.latest_mut().unwrap().eliminate_guesses(&ruled_out);
     }
 
     pub fn poss_left(&self) -> usize {
         let mut poss_matches_left = 0;
-        for (player, poss_matches) in self.possibilities.iter() {
+        for poss_matches in self.possibilities.values() {
             poss_matches_left += poss_matches.len();
         }
         // Divide by two so you aren't double counting
-        return (poss_matches_left / 2) as usize;
+        return poss_matches_left / 2;
+    }
+
+    /* The confirmed matches in a fixed order, for seating them */
+    fn sorted_matches(&self) -> Vec<ContestantPair<P>> {
+        let mut matches: Vec<ContestantPair<P>> = self.right_matches.iter().cloned().collect();
+        matches.sort();
+        return matches;
     }
 
-    fn add_perfect_match(&mut self, pair: ContestantPair) {
+    /* Pairs confirmed as matches, which have left the possibilities */
+    pub fn confirmed(&self) -> &HashSet<ContestantPair<P>> {
+        return &self.right_matches;
+    }
+
+    fn add_perfect_match(&mut self, pair: ContestantPair<P>) {
         // Remove players in pair from possibilities
-        for (player, player_poss) in self.possibilities.iter_mut() {
+        for player_poss in self.possibilities.values_mut() {
             player_poss.remove(pair.get_a());
             player_poss.remove(pair.get_b());
         }
@@ -287,39 +543,216 @@ impl BruteForce {
         self.round_manager.perfect_match_found(&pair);
     }
 
-    pub fn output_stats(&self) {
-        println!("Rounds were used in {} # of guesses", self.round_manager.times_round_used);
+    /* Applies everything the possibilities decide on their own until nothing new follows.
+    Ruling pairs out can leave a player with one option, which is then confirmed. */
+    fn propagate(&mut self) {
+        if !self.deduction {
+            return;
+        }
+        loop {
+            let deductions = deduce(&self.possibilities, &self.round_manager.rounds);
+            // Refused deductions would only come back again
+            let mut progress = false;
+            for deduction in deductions {
+                match deduction {
+                    Deduction::Confirmed(pair, reason) => {
+                        debug!("Deduced perfect match {} ({:?})", pair, reason);
+                        if !self.already_guessed(pair.get_a(), pair.get_b()) {
+                            let proof = self.deduced(reason);
+                            progress |= self.confirm(pair, proof);
+                        }
+                    }
+                    Deduction::RuledOut(pair, reason) => {
+                        let proof = self.deduced(reason);
+                        progress |= self.rule_out(&pair, proof);
+                    }
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+        self.round_manager.prune_rounds();
+    }
+
+    /* The best chance any saved round gives each pair it still considers */
+    fn round_probabilities(&self) -> HashMap<ContestantPair<P>, f64> {
+        let mut probabilities: HashMap<ContestantPair<P>, f64> = HashMap::new();
+        for round in self.round_manager.rounds.iter() {
+            for pair in round.pick_from_round(round.num_consideration()) {
+                let probability = probabilities.entry(pair).or_insert(0.0);
+                *probability = probability.max(round.probability() as f64);
+            }
+        }
+        return probabilities;
+    }
+
+    /* The chance that a pair is a perfect match as far as this strategy can tell. It is the better
+    of one over the shorter possibility list of the two players and any saved round holding the pair */
+    fn pair_probability(&self, pair: &ContestantPair<P>, round_probabilities: &HashMap<ContestantPair<P>, f64>) -> f64 {
+        if self.right_matches.contains(pair) {
+            return 1.0;
+        }
+        if self.already_guessed(pair.get_a(), pair.get_b()) {
+            return 0.0;
+        }
+        let options = self.possibilities[pair.get_a()].len().min(self.possibilities[pair.get_b()].len());
+        let from_round = round_probabilities.get(pair).cloned().unwrap_or(0.0);
+        return from_round.max(1.0 / options as f64);
+    }
+
+    fn score(&self, pair: &ContestantPair<P>, round_probabilities: &HashMap<ContestantPair<P>, f64>) -> CandidateScore<P> {
+        let probability = self.pair_probability(pair, round_probabilities);
+        return CandidateScore {
+            pair: pair.clone(),
+            probability,
+            expected_information: binary_entropy(probability),
+        };
+    }
+
+    /* What is already known going into any decision */
+    fn known_constraints(&self) -> Vec<Constraint<P>> {
+        let mut constraints: Vec<Constraint<P>> = self.right_matches.iter().cloned().map(Constraint::ConfirmedMatch).collect();
+        let unmatched = self.possibilities.len();
+        let ruled_out = unmatched * unmatched.saturating_sub(1) / 2 - self.poss_left();
+        if ruled_out > 0 {
+            constraints.push(Constraint::RuledOutPairs(ruled_out));
+        }
+        return constraints;
+    }
+
+    /* Scores the chosen booth pair against the best other pairs that could have been sent */
+    fn explain_booth(&self, chosen: &ContestantPair<P>, saved_round: Option<u32>, deciding: Constraint<P>) -> Explanation<P> {
+        let round_probabilities = self.round_probabilities();
+        let mut runners_up = vec![];
+        for (player, player_poss) in self.possibilities.iter() {
+            for other in player_poss.iter().filter(|other| player < other) {
+                let pair = ContestantPair::new(player.clone(), other.clone());
+                if &pair != chosen {
+                    runners_up.push(self.score(&pair, &round_probabilities));
+                }
+            }
+        }
+        runners_up.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+        runners_up.truncate(Self::NUM_RUNNERS_UP);
+
+        let mut explanation = Explanation::new(Decision::Booth, vec![chosen.clone()]);
+        explanation.constraints = self.known_constraints();
+        explanation.constraints.push(deciding);
+        explanation.candidates.push(self.score(chosen, &round_probabilities));
+        explanation.candidates.extend(runners_up);
+        explanation.saved_round = saved_round;
+        return explanation;
+    }
+
+    fn explain_ceremony(&self, chosen: &[ContestantPair<P>]) -> Explanation<P> {
+        let mut explanation = Explanation::new(Decision::Ceremony, chosen.to_vec());
+        explanation.constraints = self.known_constraints();
+        if let Some(player) = self.highest_prob_player(&HashSet::new()) {
+            let possibilities_left = self.possibilities[&player].len();
+            explanation.constraints.push(Constraint::FewestPossibilities { player, possibilities_left });
+        }
+        let round_probabilities = self.round_probabilities();
+        explanation.candidates = chosen.iter().map(|pair| self.score(pair, &round_probabilities)).collect();
+        return explanation;
     }
 }
 
-impl GameStrategy for BruteForce {
-    fn ceremony_pairs(&mut self) -> Vec<ContestantPair> {
-        return self.possible_pairing();
+impl<P: Contestant> GameStrategy<P> for BruteForce<P> {
+    fn ceremony_pairs(&mut self) -> Vec<ContestantPair<P>> {
+        info!("Ceremony guess --- rounds used: {} -----------------------------------------------", self.round_manager.times_round_used);
+        if let Some((pairing, information)) = self.opening_book.as_ref().and_then(|book| book.ceremony(&self.history, &mut self.rng)) {
+            let mut explanation = self.explain_ceremony(&pairing);
+            explanation.constraints.push(Constraint::OpeningBook { information });
+            self.last_decision = Some(explanation);
+            return pairing;
+        }
+        let likely = if self.likely_ceremony && !self.possibilities.is_empty() { self.likely_pairing() } else { None };
+        let mut pairing = likely.unwrap_or_else(|| self.best_guess());
+
+        let mode = match (self.next_mode.take(), &self.mode_policy) {
+            (Some(mode), _) => mode,
+            (None, Some(policy)) => policy.choose(self.weeks_left, self.win_probability(&pairing)),
+            (None, None) => CeremonyMode::Win,
+        };
+        if mode == CeremonyMode::Information && !self.possibilities.is_empty() {
+            info!("Designing the ceremony for information, {} weeks left", self.weeks_left);
+            pairing = self.information_pairing(pairing);
+        }
+        self.last_decision = Some(self.explain_ceremony(&pairing));
+        return pairing;
+    }
+
+    /* Starts from the usual ceremony and, when it breaks the seating rules, seats the most likely
+    ceremony that doesn't. Pairs with no chance are still seated when the rules leave nothing else */
+    fn ceremony_pairs_within(&mut self, seating: &PairConstraints<P>) -> Vec<ContestantPair<P>> {
+        let preferred = self.ceremony_pairs();
+        if seating.violations(&preferred).is_empty() {
+            return preferred;
+        }
+        let Some(constrained) = self.constrained_pairing(seating).or_else(|| seating.enforce(preferred.clone())) else {
+            error!("No ceremony keeps to this week's seating rules");
+            return preferred;
+        };
+        let cost = self.seating_cost(&preferred, &constrained);
+        info!("Seating rules cost {:.3} bits this week", cost);
+        self.information_lost += cost;
+        self.last_decision = Some(self.explain_ceremony(&constrained));
+        return constrained;
     }
 
-    fn ceremony_feedback(&mut self, num_right: usize, guess: Vec<ContestantPair>) {
-        let num_new_correct = num_right - self.right_matches.len(); // only care about number correct of ones we don't care about
+    fn ceremony_feedback(&mut self, num_right: usize, guess: Vec<ContestantPair<P>>) {
+        self.peak_possibilities = self.peak_possibilities.max(self.poss_left());
+        // Only the beams of undecided pairs are news. Seating rules can split up confirmed matches
+        let num_new_correct = num_right - guess.iter().filter(|pair| self.right_matches.contains(pair)).count();
+        self.history.record(Observation::Ceremony { pairs: guess.clone(), beams: num_right });
         
         if num_new_correct == 0 {
             debug!("Rounds pre remove------\n{}", self.round_manager.pretty_string());
 
+            let observation = self.last_observation();
             for pair in guess.iter() {
-                self.remove_guess(pair);
+                self.rule_out(pair, Proof::NoBeams { observation });
             }
             self.round_manager.prune_rounds();
             debug!("Rounds post remove------\n{}", self.round_manager.pretty_string());
 
         } else {
             self.add_round(guess, num_new_correct);
+            let round_id = self.round_manager.latest().unwrap().round_id;
+            self.round_observations.insert(round_id, self.last_observation());
         }
+        self.propagate();
+        self.weeks_left = self.weeks_left.saturating_sub(1);
+        trace!("{}", pretty_string_poss(&self.possibilities));
+        info!("Poss remaining: {}", self.poss_left());
     }
 
-    fn send_to_booth(&mut self) -> ContestantPair {
-        if self.round_manager.should_use_round(&self.possibilities) {
+    fn send_to_booth(&mut self) -> ContestantPair<P> {
+        if self.possibilities.is_empty() {
+            // Everything was deduced, so the booth can only confirm a known match
+            let guess = self.right_matches.iter().min().unwrap().clone();
+            self.last_decision = Some(self.explain_booth(&guess, None, Constraint::ConfirmedMatch(guess.clone())));
+            return guess;
+        }
+        if let Some((guess, information)) = self.opening_book.as_ref().and_then(|book| book.booth(&self.history, &mut self.rng)) {
+            self.last_decision = Some(self.explain_booth(&guess, None, Constraint::OpeningBook { information }));
+            return guess;
+        }
+        if self.round_optimization && self.round_manager.should_use_round(&self.possibilities) {
             // self.round_manager.rounds.len() > 5 {
             info!("*******using round best guess*******");
             // debug!("{}", self.round_manager.pretty_string());
-            return self.round_manager.best_guess().unwrap();
+            let round = self.round_manager.most_eff().unwrap();
+            let round_id = round.round_id;
+            let deciding = Constraint::SavedRound {
+                round_id,
+                num_correct: round.num_correct,
+                pairs_left: round.num_consideration(),
+            };
+            let guess = self.round_manager.best_guess().unwrap();
+            self.last_decision = Some(self.explain_booth(&guess, Some(round_id), deciding));
+            return guess;
             
         } else {
             info!("*******using possibilities best guess*******");
@@ -330,38 +763,151 @@ impl GameStrategy for BruteForce {
                 .get(&player)
                 .unwrap()
                 .iter()
-                .next()
+                .min()
                 .unwrap();
-            return ContestantPair::new(player, to_pair.clone());
+            let guess = ContestantPair::new(player.clone(), to_pair.clone());
+            let deciding = Constraint::FewestPossibilities {
+                possibilities_left: self.possibilities[&player].len(),
+                player,
+            };
+            self.last_decision = Some(self.explain_booth(&guess, None, deciding));
+            return guess;
         }
     }
 
-    fn booth_feedback(&mut self, feedback: Feedback) {
+    /* The first booth is picked as usual. The rest go to the most uncertain undecided pairs that
+    share nobody with an earlier booth, so each result is worth as much as it can be on its own */
+    fn send_to_booths(&mut self, count: usize) -> Vec<ContestantPair<P>> {
+        if count == 0 {
+            return vec![];
+        }
+        let mut booths = vec![self.send_to_booth()];
+        let round_probabilities = self.round_probabilities();
+        let mut candidates: Vec<CandidateScore<P>> = self
+            .possibilities
+            .iter()
+            .flat_map(|(player, partners)| partners.iter().map(move |partner| ContestantPair::new(player.clone(), partner.clone())))
+            .filter(|pair| pair.get_a() < pair.get_b())
+            .map(|pair| self.score(&pair, &round_probabilities))
+            .collect();
+        candidates.sort_by(|a, b| b.expected_information.total_cmp(&a.expected_information).then_with(|| a.pair.cmp(&b.pair)));
+        for candidate in candidates {
+            if booths.len() == count {
+                break;
+            }
+            let seated = pairs_to_contestants(&booths);
+            if !seated.contains(candidate.pair.get_a()) && !seated.contains(candidate.pair.get_b()) {
+                booths.push(candidate.pair);
+            }
+        }
+        return booths;
+    }
+
+    fn booth_feedback(&mut self, feedback: Feedback<P>) {
+        self.peak_possibilities = self.peak_possibilities.max(self.poss_left());
         debug!("Rounds pre remove------\n{}", self.round_manager.pretty_string());
 
         match feedback {
             Feedback::Correct(pair) => {
-                self.add_perfect_match(pair);
+                self.history.record(Observation::Booth { pair: pair.clone(), is_match: true });
+                let observation = self.last_observation();
+                self.confirm(pair, Proof::Booth { observation });
             }
             Feedback::Wrong(pair) => {
-                self.remove_guess(&pair);
+                self.history.record(Observation::Booth { pair: pair.clone(), is_match: false });
+                let observation = self.last_observation();
+                self.rule_out(&pair, Proof::Booth { observation });
             }
+            Feedback::Hidden(_) => (),
         }
+        self.propagate();
         self.round_manager.prune_rounds();
         debug!("Rounds post remove------\n{}", self.round_manager.pretty_string());
 
     }
+
+    fn exclude_pairs(&mut self, pairs: &[ContestantPair<P>]) {
+        self.history.excluded.extend(pairs.iter().cloned());
+        for pair in pairs.iter() {
+            self.rule_out(pair, Proof::Excluded);
+        }
+        self.propagate();
+        self.round_manager.prune_rounds();
+    }
+
+    fn game_start(&mut self, _contestants: &[&P], rules: &GameRules) {
+        self.weeks_left = rules.max_iterations;
+        if let Some(seed) = rules.seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+    }
+
+    fn best_guess(&self) -> Vec<ContestantPair<P>> {
+        if self.possibilities.is_empty() {
+            return self.sorted_matches();
+        }
+        return self.possible_pairing();
+    }
+
+    /* Estimated by treating each unconfirmed pair of the best guess as independent, with
+    the chance of the pair being right as one over the shortest possibility list of the two */
+    fn confidence(&self) -> f64 {
+        let mut confidence = 1.0;
+        for pair in self.best_guess().iter() {
+            if self.right_matches.contains(pair) {
+                continue;
+            }
+            let options = self.possibilities[pair.get_a()].len().min(self.possibilities[pair.get_b()].len());
+            confidence /= options.max(1) as f64;
+        }
+        return confidence;
+    }
+
+    fn stats(&self) -> Vec<(String, f64)> {
+        return vec![
+            (String::from("rounds_used"), self.round_manager.times_round_used as f64),
+            (String::from("saved_rounds"), self.round_manager.rounds.len() as f64),
+            (String::from("possibilities_left"), self.poss_left() as f64),
+            (String::from("confirmed_matches"), self.right_matches.len() as f64),
+            (String::from("information_lost_to_seating"), self.information_lost),
+            (String::from("peak_possibilities"), self.peak_possibilities.max(self.poss_left()) as f64),
+        ];
+    }
+
+    fn explain_last_decision(&self) -> Option<Explanation<P>> {
+        return self.last_decision.clone();
+    }
+
+    fn proofs(&self) -> &[Justified<P>] {
+        return &self.proofs;
+    }
+
+    fn is_open(&self, pair: &ContestantPair<P>) -> bool {
+        return self.right_matches.contains(pair) || !self.already_guessed(pair.get_a(), pair.get_b());
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::bruteforce::Feedback;
     use crate::bruteforce::{BruteForce, PairPlayerResult};
+    use crate::ceremony::{CeremonyMode, ModePolicy, PairConstraints};
+    use crate::explanation::{Constraint, Decision};
+    use crate::gamemaster::GameMaster;
+    use crate::gamestrategy::{BoothRules, GameOutcome, GameRules};
+    use crate::openingbook::OpeningBook;
+    use crate::proof::{Justified, Proof};
+    use crate::properties::play_against;
+    use crate::season::{replay_week, report, Season};
+    use crate::voting::VotePolicy;
     use crate::contestant::{ContestantPair, Player};
     use crate::gamestrategy::GameStrategy;
     use crate::utils::contestants_to_pairs;
     use crate::utils::gen_contestants;
     use crate::utils::get_matches;
+    use crate::utils::pairs_to_contestants;
+    use proptest::prop_assert;
+    use rand::Rng;
     use std::collections::HashMap;
     use std::collections::HashSet;
     #[test]
@@ -470,7 +1016,7 @@ mod tests {
                 already_taken_pair.get_a().clone(),
                 already_taken_pair.get_b().clone(),
             ]);
-            let paired_with = strategy.pair_player(&expected_pair.get_a(), &off_limits); // This should output the other only pair possible
+            let paired_with = strategy.pair_player(expected_pair.get_a(), &off_limits); // This should output the other only pair possible
             assert_eq!(PairPlayerResult::Ok(expected_pair.clone()), paired_with);
         }
     }
@@ -594,7 +1140,27 @@ mod tests {
         );
 
         let expected_pair = ContestantPair::new(c[2].clone(), c[0].clone());
-        assert_eq!(&expected_pair, strategy.possible_pairing().get(0).unwrap());
+        assert_eq!(&expected_pair, strategy.possible_pairing().first().unwrap());
+    }
+
+    #[test]
+    fn test_possible_pairing_is_a_matching() {
+        // Sparse possibilities force the search to back up, which used to leave stale pairs behind
+        let c = gen_contestants(8);
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let mut strategy = BruteForce::initialize(c.iter().collect());
+            for a in 0..8 {
+                for b in a + 1..8 {
+                    if a / 2 != b / 2 && rng.gen_bool(0.5) {
+                        strategy.remove_guess(&ContestantPair::new(c[a].clone(), c[b].clone()));
+                    }
+                }
+            }
+            let pairing = strategy.possible_pairing();
+            let seated: HashSet<Player> = pairs_to_contestants(&pairing).into_iter().collect();
+            assert_eq!(seated.len(), 8);
+        }
     }
 
     #[test]
@@ -614,10 +1180,11 @@ mod tests {
         // test when 0 matches found
         strategy
             .right_matches
-            .insert(perfect_matches.get(0).unwrap().clone());
+            .insert(perfect_matches.first().unwrap().clone());
 
+        // The confirmed match isn't seated, so it doesn't light a beam either
         let guess2 = get_matches(&perfect_matches, 0, 6);
-        strategy.ceremony_feedback(1, guess2.clone());
+        strategy.ceremony_feedback(0, guess2.clone());
 
         for g in guess2.iter() {
             assert_eq!(strategy.already_guessed(g.get_a(), g.get_b()), true);
@@ -632,7 +1199,7 @@ mod tests {
 
         // remove players in couple from possible keys
         // also remove each player from couple as possible options for the remaining players
-        let pm_match = perfect_matches.get(0).unwrap();
+        let pm_match = perfect_matches.first().unwrap();
         assert_eq!(
             strategy.already_guessed(pm_match.get_a(), pm_match.get_b()),
             false
@@ -650,7 +1217,7 @@ mod tests {
 
         // Test that the round probabilities chance since it contained both
         assert_eq!(
-            strategy.round_manager.rounds.get(0).unwrap().probability(),
+            strategy.round_manager.rounds.first().unwrap().probability(),
             1.0 / 5.0
         ); // now 1/5 chance of guessing from round
         assert_eq!(strategy.round_manager.rounds.len(), 1); // round with only one option left before should be removed
@@ -661,7 +1228,7 @@ mod tests {
         let contestants = gen_contestants(12);
         let mut strategy = BruteForce::initialize(contestants.iter().collect());
         let matches = contestants_to_pairs(&contestants);
-        let wrong_guess = matches.get(0).unwrap().clone();
+        let wrong_guess = matches.first().unwrap().clone();
 
         assert_eq!(
             strategy.already_guessed(wrong_guess.get_a(), wrong_guess.get_b()),
@@ -680,7 +1247,7 @@ mod tests {
 
         // Test that the round probabilities change
         assert_eq!(
-            strategy.round_manager.rounds.get(0).unwrap().probability(),
+            strategy.round_manager.rounds.first().unwrap().probability(),
             2.0 / 5.0
         );
         assert_eq!(
@@ -689,11 +1256,392 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_introspection() {
+        let c = gen_contestants(4);
+        let mut strategy = BruteForce::initialize(c.iter().collect());
+        strategy.deduction = false;
+        assert_eq!(strategy.best_guess().len(), 2);
+        assert_eq!(strategy.is_solved(), false);
+
+        // With (0, 1) confirmed, (2, 3) is the only pair left
+        strategy.booth_feedback(Feedback::Correct(ContestantPair::new(c[0].clone(), c[1].clone())));
+        let guess = strategy.best_guess();
+        assert!(guess.contains(&ContestantPair::new(c[2].clone(), c[3].clone())));
+        assert_eq!(strategy.confidence(), 1.0);
+        assert_eq!(strategy.is_solved(), true);
+
+        let stats = strategy.stats();
+        assert!(stats.contains(&(String::from("confirmed_matches"), 1.0)));
+        assert!(stats.contains(&(String::from("possibilities_left"), 1.0)));
+        assert!(stats.contains(&(String::from("peak_possibilities"), 6.0)));
+
+        assert_eq!(strategy.explain_last_decision(), None);
+        strategy.send_to_booth();
+        assert!(strategy.explain_last_decision().is_some());
+    }
+
+    #[test]
+    fn test_deduction() {
+        let c = gen_contestants(6);
+        let mut strategy = BruteForce::initialize(c.iter().collect());
+        let pair = |a: usize, b: usize| ContestantPair::new(c[a].clone(), c[b].clone());
+
+        // Once 0 can only go with 1, the pair is confirmed without a booth
+        for other in 2..6 {
+            strategy.booth_feedback(Feedback::Wrong(pair(0, other)));
+        }
+        assert!(strategy.right_matches.contains(&pair(0, 1)));
+        assert_eq!(strategy.already_guessed(&c[2], &c[3]), false);
+
+        // With (2, 3) ruled out, 2 and 3 need 4 and 5 so those two can't be together
+        strategy.booth_feedback(Feedback::Wrong(pair(2, 3)));
+        assert_eq!(strategy.already_guessed(&c[4], &c[5]), true);
+        strategy.booth_feedback(Feedback::Wrong(pair(2, 4)));
+        assert!(strategy.right_matches.contains(&pair(2, 5)));
+        assert!(strategy.right_matches.contains(&pair(3, 4)));
+        assert!(strategy.possibilities.is_empty());
+        assert_eq!(strategy.is_solved(), true);
+
+        // The booth falls back on a known match when nothing is left to ask about
+        let guess = strategy.send_to_booth();
+        assert!(strategy.right_matches.contains(&guess));
+        assert_eq!(strategy.ceremony_pairs().len(), 3);
+    }
+
+    #[test]
+    fn test_deductions_are_sound() {
+        // Show sized casts, played to the end. Nothing deduced may contradict the hidden matching
+        for seed in 0..5 {
+            let mut game = GameMaster::initialize_game(20, 100).with_seed(seed);
+            let mut strategy = BruteForce::initialize(game.contestants());
+            let check = |strategy: &BruteForce, game: &GameMaster| {
+                for pair in strategy.right_matches.iter() {
+                    assert!(game.matches.contains(pair), "{} was deduced but isn't a match", pair);
+                }
+                for pair in game.matches.iter().filter(|pair| !strategy.right_matches.contains(pair)) {
+                    assert_eq!(strategy.already_guessed(pair.get_a(), pair.get_b()), false, "{} was ruled out", pair);
+                }
+            };
+            while game.in_progress() {
+                let guess = strategy.ceremony_pairs();
+                let num_correct = game.ceremony(&guess).unwrap();
+                if !game.in_progress() {
+                    break;
+                }
+                strategy.ceremony_feedback(num_correct, guess);
+                check(&strategy, &game);
+                let booth_result = game.truth_booth(strategy.send_to_booth());
+                strategy.booth_feedback(booth_result);
+                check(&strategy, &game);
+            }
+        }
+    }
+
+    #[test]
+    fn test_season_fixture() {
+        // A recorded ten couple season. The exact space settles it a week before BruteForce does.
+        // Propagation isn't tested on the public ceremony data of aired seasons yet: none of them
+        // has been transcribed from a source that can be cited, so this game stands in for them
+        let season = Season::parse(include_str!("../seasons/seeded_10_couples.txt")).unwrap();
+        let name = |pair: &ContestantPair| format!("{}+{}", pair.get_a().name, pair.get_b().name);
+        let mut strategy = BruteForce::initialize(season.contestants.iter().collect());
+        strategy.exclude_pairs(&season.same_side_pairs());
+        assert!(strategy.proofs().iter().all(|justified| justified.proof == Proof::Excluded));
+        let mut possible = vec![];
+        for week in season.weeks.iter() {
+            replay_week(&mut strategy, week);
+            possible.push(strategy.poss_left());
+        }
+        assert_eq!(possible, vec![89, 88, 87, 71, 70, 55, 46, 35, 0]);
+        let mut confirmed: Vec<String> = strategy.right_matches.iter().map(name).collect();
+        confirmed.sort();
+        assert_eq!(confirmed[..3], ["Aaron+Quinn", "Brandon+Maya", "Cody+Tessa"]);
+
+        let report = report(&season);
+        let left: Vec<Option<u64>> = report.weeks.iter().map(|week| week.matchings_left).collect();
+        assert_eq!(left[3..], [Some(1551), Some(106), Some(14), Some(7), Some(1), Some(1)]);
+        assert_eq!((report.determined_week, report.solver_week, report.cast_week), (Some(8), Some(9), Some(9)));
+    }
+
+    #[test]
+    fn test_custom_contestants() {
+        // Contestants as a service would know them, identified by a UUID and carrying metadata
+        #[derive(Debug, Clone)]
+        struct Guest {
+            uuid: u128,
+            handle: String,
+        }
+        impl PartialEq for Guest {
+            fn eq(&self, other: &Self) -> bool {
+                return self.uuid == other.uuid;
+            }
+        }
+        impl Eq for Guest {}
+        impl std::hash::Hash for Guest {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.uuid.hash(state);
+            }
+        }
+        impl PartialOrd for Guest {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                return Some(self.cmp(other));
+            }
+        }
+        impl Ord for Guest {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                return self.uuid.cmp(&other.uuid);
+            }
+        }
+        impl std::fmt::Display for Guest {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "@{}", self.handle)
+            }
+        }
+
+        let guests: Vec<Guest> = (0..10u128).map(|i| Guest { uuid: i * 0x9e3779b97f4a7c15, handle: format!("guest{}", i) }).collect();
+        let truth = contestants_to_pairs(&[0, 7, 1, 4, 2, 9, 3, 8, 5, 6].map(|i| guests[i].clone()));
+        let mut strategy = BruteForce::initialize(guests.iter().collect());
+        strategy.sound = true;
+        play_against(&mut strategy, &truth, 20, |strategy| {
+            prop_assert!(strategy.proofs().iter().all(|justified| truth.contains(&justified.pair) == justified.is_match));
+            return Ok(());
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_sound_mode() {
+        let mut game = GameMaster::initialize_game(10, 100).with_seed(1);
+        let mut strategy = BruteForce::initialize(game.contestants());
+        strategy.sound = true;
+        while game.in_progress() {
+            let guess = strategy.ceremony_pairs();
+            let num_correct = game.ceremony(&guess).unwrap();
+            if !game.in_progress() {
+                break;
+            }
+            strategy.ceremony_feedback(num_correct, guess);
+            let booth_result = game.truth_booth(strategy.send_to_booth());
+            strategy.booth_feedback(booth_result);
+            assert_eq!(game.audit(strategy.proofs()), None);
+            assert_eq!(game.discarded(&strategy), None);
+        }
+        assert!(strategy.proofs().iter().any(|justified| matches!(justified.proof, Proof::Deduced { .. })));
+
+        // A booth that never happened proves nothing
+        let mut strategy = BruteForce::initialize(game.contestants());
+        strategy.sound = true;
+        let pair = game.matches[0].clone();
+        assert_eq!(strategy.rule_out(&pair, Proof::Booth { observation: 0 }), false);
+        assert_eq!(strategy.already_guessed(pair.get_a(), pair.get_b()), false);
+        strategy.booth_feedback(Feedback::Correct(pair.clone()));
+        assert_eq!(strategy.proofs(), &[Justified { pair, is_match: true, proof: Proof::Booth { observation: 0 } }]);
+    }
+
+    #[test]
+    fn test_seating_constraints() {
+        // Couples who won't sit together and friends who insist on it, all through the season
+        for _ in 0..3 {
+            let game = GameMaster::initialize_game(12, 100);
+            let truth = game.matches.clone();
+            let friends = ContestantPair::new(truth[1].get_a().clone(), truth[2].get_a().clone());
+            let mut game = game
+                .with_seating(0, PairConstraints::new().with_excluded(truth[0].clone()).with_forced(friends.clone()))
+                .and_then(|game| game.with_seating(3, PairConstraints::new().with_forced(friends)))
+                .unwrap();
+            for week in 4..8 {
+                game = game.with_seating(week, PairConstraints::new().with_excluded(truth[0].clone())).unwrap();
+            }
+            let mut strategy = BruteForce::initialize(game.contestants());
+            while game.in_progress() {
+                let guess = strategy.ceremony_pairs_within(&game.seating_constraints());
+                let num_correct = game.ceremony(&guess).unwrap();
+                if !game.in_progress() {
+                    break;
+                }
+                strategy.ceremony_feedback(num_correct, guess);
+                let booth_result = game.truth_booth(strategy.send_to_booth());
+                strategy.booth_feedback(booth_result);
+                for pair in strategy.right_matches.iter() {
+                    assert!(truth.contains(pair));
+                }
+            }
+            assert!(matches!(game.outcome(), Some(GameOutcome::Won { .. })));
+        }
+
+        // Having to sit exactly as last week repeats a sure two beams, which teaches nothing
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut strategy = BruteForce::initialize(c.iter().collect());
+        strategy.ceremony_feedback(2, truth.clone());
+        let mut seating = PairConstraints::new();
+        for pair in truth.iter() {
+            seating = seating.with_forced(pair.clone());
+        }
+        let constrained = strategy.ceremony_pairs_within(&seating);
+        assert_eq!(seating.violations(&constrained), vec![]);
+        let shuffled = get_matches(&truth, 0, 4);
+        assert!(strategy.seating_cost(&shuffled, &truth) > 0.0);
+        assert_eq!(strategy.seating_cost(&truth, &truth), 0.0);
+        let lost = strategy.stats().into_iter().find(|(name, _)| name == "information_lost_to_seating").unwrap().1;
+        assert!(lost >= 0.0);
+    }
+
+    #[test]
+    fn test_voted_booths() {
+        // The solver still finishes when the house picks every booth, just not as fast
+        for policy in [VotePolicy::Random, VotePolicy::MostBeams, VotePolicy::Couples { accuracy: 0.3 }] {
+            let mut game = GameMaster::initialize_game(12, 100).with_vote_policy(policy);
+            let mut strategy = BruteForce::initialize(game.contestants());
+            while game.in_progress() {
+                let guess = strategy.ceremony_pairs();
+                let num_correct = game.ceremony(&guess).unwrap();
+                if !game.in_progress() {
+                    break;
+                }
+                strategy.ceremony_feedback(num_correct, guess);
+                for booth_result in game.booth_week(game.voted_booths().unwrap()) {
+                    strategy.observe_booth(booth_result);
+                }
+            }
+            assert!(matches!(game.outcome(), Some(GameOutcome::Won { .. })));
+        }
+    }
+
+    #[test]
+    fn test_likely_ceremony() {
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut strategy = BruteForce::initialize(c.iter().collect());
+        strategy.likely_ceremony = true;
+
+        // Each pair of a ceremony with two beams out of four is a match half the time,
+        // far more often than any other pair, so the likeliest seating repeats it
+        strategy.ceremony_feedback(2, truth.clone());
+        let pairing = strategy.ceremony_pairs();
+        assert_eq!(pairing.len(), 4);
+        assert!(pairing.iter().all(|pair| truth.contains(pair)));
+    }
+
+    #[test]
+    fn test_send_to_booths() {
+        let c = gen_contestants(8);
+        let mut strategy = BruteForce::initialize(c.iter().collect());
+        assert_eq!(strategy.send_to_booths(0), vec![]);
+        let booths = strategy.send_to_booths(3);
+        assert_eq!(booths.len(), 3);
+        assert_eq!(pairs_to_contestants(&booths).into_iter().collect::<HashSet<_>>().len(), 6);
+    }
+
+    #[test]
+    fn test_opening_book() {
+        let c = gen_contestants(8);
+        let mut strategy = BruteForce::initialize(c.iter().collect());
+        strategy.opening_book = Some(OpeningBook::generate(8));
+
+        let ceremony = strategy.ceremony_pairs();
+        assert_eq!(pairs_to_contestants(&ceremony).into_iter().collect::<HashSet<_>>().len(), 8);
+        assert!(matches!(strategy.explain_last_decision().unwrap().constraints.last(), Some(Constraint::OpeningBook { .. })));
+
+        // No beams, so the book sends a couple that wasn't seated together
+        strategy.ceremony_feedback(0, ceremony.clone());
+        let booth = strategy.send_to_booth();
+        assert!(!ceremony.contains(&booth));
+        assert!(matches!(strategy.explain_last_decision().unwrap().constraints.last(), Some(Constraint::OpeningBook { .. })));
+
+        // The book still has the second ceremony, and the game leaves it after that
+        strategy.booth_feedback(Feedback::Wrong(booth));
+        let second = strategy.ceremony_pairs();
+        assert!(matches!(strategy.explain_last_decision().unwrap().constraints.last(), Some(Constraint::OpeningBook { .. })));
+        strategy.ceremony_feedback(1, second);
+        strategy.send_to_booth();
+        assert!(!matches!(strategy.explain_last_decision().unwrap().constraints.last(), Some(Constraint::OpeningBook { .. })));
+
+        // The book picks between equivalent seatings with the game's seed
+        let book = OpeningBook::generate(8);
+        let opening = |seed: u64| {
+            let mut strategy = BruteForce::initialize(c.iter().collect());
+            strategy.opening_book = Some(book.clone());
+            strategy.game_start(&c.iter().collect::<Vec<_>>(), &GameRules { num_contestants: 8, max_iterations: 10, booths: BoothRules::default(), seed: Some(seed) });
+            return strategy.ceremony_pairs();
+        };
+        assert_eq!(opening(5), opening(5));
+    }
+
+    #[test]
+    fn test_information_ceremony() {
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut strategy = BruteForce::initialize(c.iter().collect());
+        strategy.booth_feedback(Feedback::Correct(truth[0].clone()));
+
+        // Straight after a two beam ceremony, one of them the confirmed match, repeating it would be a sure two beams
+        strategy.ceremony_feedback(2, truth.clone());
+        strategy.next_mode = Some(CeremonyMode::Information);
+        let pairing = strategy.ceremony_pairs();
+        assert_eq!(pairing.len(), 4);
+        assert!(pairing.contains(&truth[0]));
+        assert_ne!(pairing.iter().filter(|pair| truth.contains(pair)).count(), 4);
+        assert_eq!(strategy.next_mode, None);
+
+        // With a week left the policy goes for the win, seating the most likely pairing untouched
+        strategy.mode_policy = Some(ModePolicy::default());
+        strategy.likely_ceremony = true;
+        strategy.game_start(&c.iter().collect::<Vec<_>>(), &GameRules { num_contestants: 8, max_iterations: 1, booths: BoothRules::default(), seed: None });
+        let pairing = strategy.ceremony_pairs();
+        assert_eq!(pairing, strategy.likely_pairing().unwrap());
+    }
+
+    #[test]
+    fn test_explain_booth() {
+        let contestants = gen_contestants(8);
+        let perfect_matches = contestants_to_pairs(&contestants);
+        let mut strategy = BruteForce::initialize(contestants.iter().collect());
+
+        // A round with 3 of 4 right beats the 1 in 7 chance of any single pair
+        strategy.ceremony_feedback(3, perfect_matches.clone());
+        let guess = strategy.send_to_booth();
+        let explanation = strategy.explain_last_decision().unwrap();
+
+        assert_eq!(explanation.decision, Decision::Booth);
+        assert_eq!(explanation.chosen, vec![guess.clone()]);
+        assert_eq!(explanation.saved_round, Some(1));
+        assert!(explanation.constraints.contains(&Constraint::SavedRound { round_id: 1, num_correct: 3, pairs_left: 4 }));
+        assert_eq!(explanation.candidates[0].pair, guess);
+        assert_eq!(explanation.candidates[0].probability, 0.75);
+        assert_eq!(explanation.candidates.len(), 1 + BruteForce::<Player>::NUM_RUNNERS_UP);
+        for runner_up in explanation.candidates[1..].iter() {
+            assert!(runner_up.probability <= 0.75);
+        }
+
+        let pairing = strategy.ceremony_pairs();
+        let explanation = strategy.explain_last_decision().unwrap();
+        assert_eq!(explanation.decision, Decision::Ceremony);
+        assert_eq!(explanation.candidates.len(), pairing.len());
+    }
+
     #[test]
     fn test_get_ceremony_pairs() {
-        // If there are no rounds in play yet, just pick randomly
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let seats_everyone = |pairs: &[ContestantPair]| pairs.len() == 4 && pairs_to_contestants(pairs).into_iter().collect::<HashSet<Player>>().len() == 8;
 
-        // If there are are any rounds in play, take the most likely one reduce num possibilities by half
-        // By including half of the remaining options in the next round
+        // With nothing known yet any full seating will do
+        let mut strategy = BruteForce::initialize(c.iter().collect());
+        assert!(seats_everyone(&strategy.ceremony_pairs()));
+
+        // No beams rules every pair of the seating out, so none of them sit together again
+        let wrong = get_matches(&truth, 0, 4);
+        strategy.ceremony_feedback(0, wrong.clone());
+        let guess = strategy.ceremony_pairs();
+        assert!(seats_everyone(&guess));
+        assert!(guess.iter().all(|pair| !wrong.contains(pair)));
+
+        // A confirmed match keeps its seat
+        strategy.booth_feedback(Feedback::Correct(truth[0].clone()));
+        let guess = strategy.ceremony_pairs();
+        assert!(seats_everyone(&guess));
+        assert!(guess.contains(&truth[0]));
     }
 }
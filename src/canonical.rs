@@ -0,0 +1,318 @@
+use crate::contestant::{Contestant, ContestantPair};
+use crate::history::{History, Observation};
+use std::collections::{BTreeSet, HashMap};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/* An observation with contestants replaced by their canonical labels. Pairs are (low, high) */
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CanonicalObservation {
+    Booth { pair: (usize, usize), is_match: bool },
+    Ceremony { pairs: Vec<(usize, usize)>, beams: usize },
+}
+
+/* What a history says, up to renaming the contestants. Two histories get the same form exactly when
+   some relabeling of one cast turns one into the other. The order things were observed in doesn't
+   change what they imply, so the observations are kept sorted */
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CanonicalForm {
+    pub num_contestants: usize,
+    pub observations: Vec<CanonicalObservation>,
+}
+
+/* A contestant's canonical label along with the form itself */
+#[derive(Debug, Clone)]
+pub struct Canonicalization {
+    pub form: CanonicalForm,
+    // labels[i] is the canonical label of history.contestants[i]
+    pub labels: Vec<usize>,
+}
+
+/* One observation as a contestant sees it: what kind it was (booth, or ceremony by beams), the booth
+   result, the color of their partner in it and, for ceremonies, the colors of every seated pair */
+type Sighting = (usize, bool, usize, Vec<(usize, usize)>);
+
+/* The history with contestants as indices into the cast, which is all the search needs */
+struct Indexed {
+    num_contestants: usize,
+    booths: Vec<((usize, usize), bool)>,
+    ceremonies: Vec<(Vec<(usize, usize)>, usize)>,
+}
+
+impl Indexed {
+    fn new<P: Contestant>(history: &History<P>) -> Indexed {
+        let index: HashMap<&P, usize> = history.contestants.iter().enumerate().map(|(i, player)| (player, i)).collect();
+        let indices = |pair: &ContestantPair<P>| {
+            let (a, b) = (index[pair.get_a()], index[pair.get_b()]);
+            (a.min(b), a.max(b))
+        };
+        let mut booths = vec![];
+        let mut ceremonies = vec![];
+        for observation in history.observations.iter() {
+            match observation {
+                Observation::Booth { pair, is_match } => booths.push((indices(pair), *is_match)),
+                Observation::Ceremony { pairs, beams } => ceremonies.push((pairs.iter().map(indices).collect(), *beams)),
+            }
+        }
+        return Indexed { num_contestants: history.contestants.len(), booths, ceremonies };
+    }
+
+    fn encode(&self, labels: &[usize]) -> CanonicalForm {
+        let relabel = |&(a, b): &(usize, usize)| (labels[a].min(labels[b]), labels[a].max(labels[b]));
+        let mut observations: Vec<CanonicalObservation> = self
+            .booths
+            .iter()
+            .map(|(pair, is_match)| CanonicalObservation::Booth { pair: relabel(pair), is_match: *is_match })
+            .collect();
+        for (pairs, beams) in self.ceremonies.iter() {
+            let mut pairs: Vec<(usize, usize)> = pairs.iter().map(relabel).collect();
+            pairs.sort();
+            observations.push(CanonicalObservation::Ceremony { pairs, beams: *beams });
+        }
+        observations.sort();
+        return CanonicalForm { num_contestants: self.num_contestants, observations };
+    }
+
+    /* Splits contestants by how they take part in the observations until that stops telling any more
+    of them apart. Colors only ever depend on other colors, never on the input labels, so relabeling
+    the cast can't change which color a contestant ends up with */
+    fn refine(&self, mut colors: Vec<usize>) -> Vec<usize> {
+        loop {
+            let mut signatures: Vec<(usize, Vec<Sighting>)> = vec![];
+            let ceremony_colors: Vec<Vec<(usize, usize)>> = self
+                .ceremonies
+                .iter()
+                .map(|(pairs, _)| {
+                    let mut colored: Vec<(usize, usize)> = pairs.iter().map(|&(a, b)| (colors[a].min(colors[b]), colors[a].max(colors[b]))).collect();
+                    colored.sort();
+                    colored
+                })
+                .collect();
+            for contestant in 0..self.num_contestants {
+                let mut seen = vec![];
+                for &((a, b), is_match) in self.booths.iter() {
+                    if a == contestant || b == contestant {
+                        seen.push((0, is_match, colors[a + b - contestant], vec![]));
+                    }
+                }
+                for ((pairs, beams), colored) in self.ceremonies.iter().zip(ceremony_colors.iter()) {
+                    if let Some(&(a, b)) = pairs.iter().find(|&&(a, b)| a == contestant || b == contestant) {
+                        seen.push((1 + beams, false, colors[a + b - contestant], colored.clone()));
+                    }
+                }
+                seen.sort();
+                signatures.push((colors[contestant], seen));
+            }
+            let distinct: BTreeSet<_> = signatures.iter().collect();
+            let rank: HashMap<_, usize> = distinct.into_iter().enumerate().map(|(i, signature)| (signature, i)).collect();
+            let refined: Vec<usize> = signatures.iter().map(|signature| rank[signature]).collect();
+            let classes = |colors: &[usize]| colors.iter().collect::<BTreeSet<_>>().len();
+            if classes(&refined) == classes(&colors) {
+                return refined;
+            }
+            colors = refined;
+        }
+    }
+
+    /* Individualization and refinement: while contestants share a color, try singling out each one in
+    the first shared color and keep the smallest encoding among the leaves. Two leaves with the same
+    encoding reveal a relabeling that changes nothing, and contestants such relabelings move onto ones
+    already tried lead to the same leaves, so they are skipped */
+    fn search(&self, colors: Vec<usize>, path: &mut Vec<usize>, found: &mut Found) {
+        let colors = self.refine(colors);
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for &color in colors.iter() {
+            *counts.entry(color).or_insert(0) += 1;
+        }
+        let shared = match counts.iter().filter(|(_, &count)| count > 1).map(|(&color, _)| color).min() {
+            Some(color) => color,
+            None => return found.leaf(self.encode(&colors), colors),
+        };
+        let mut tried: Vec<usize> = vec![];
+        for contestant in (0..self.num_contestants).filter(|&c| colors[c] == shared) {
+            let orbits = found.orbits(self.num_contestants, path);
+            if tried.iter().any(|&other| orbits[other] == orbits[contestant]) {
+                continue;
+            }
+            tried.push(contestant);
+            // Singling out keeps the color order: the chosen one goes just ahead of the rest of its color
+            let individualized = colors.iter().enumerate().map(|(c, &color)| 2 * color + usize::from(color == shared && c != contestant)).collect();
+            path.push(contestant);
+            self.search(individualized, path, found);
+            path.pop();
+        }
+    }
+}
+
+/* Leaves of the search worth comparing against, and the symmetries found along the way */
+#[derive(Default)]
+struct Found {
+    first: Option<(CanonicalForm, Vec<usize>)>,
+    best: Option<(CanonicalForm, Vec<usize>)>,
+    // Each maps a contestant to where the symmetry sends it
+    automorphisms: Vec<Vec<usize>>,
+}
+
+impl Found {
+    fn leaf(&mut self, form: CanonicalForm, labels: Vec<usize>) {
+        for (known, known_labels) in self.first.iter().chain(self.best.iter()) {
+            if *known == form {
+                let mut holder = vec![0; labels.len()];
+                for (contestant, &label) in known_labels.iter().enumerate() {
+                    holder[label] = contestant;
+                }
+                let automorphism: Vec<usize> = labels.iter().map(|&label| holder[label]).collect();
+                if automorphism.iter().enumerate().any(|(c, &image)| c != image) {
+                    self.automorphisms.push(automorphism);
+                }
+                return;
+            }
+        }
+        if self.first.is_none() {
+            self.first = Some((form.clone(), labels.clone()));
+        }
+        if self.best.as_ref().is_none_or(|(best, _)| form < *best) {
+            self.best = Some((form, labels));
+        }
+    }
+
+    /* Orbit representatives under the symmetries that leave the singled out contestants in place */
+    fn orbits(&self, num_contestants: usize, fixed: &[usize]) -> Vec<usize> {
+        let mut root: Vec<usize> = (0..num_contestants).collect();
+        fn find(root: &mut [usize], mut c: usize) -> usize {
+            while root[c] != c {
+                root[c] = root[root[c]];
+                c = root[c];
+            }
+            return c;
+        }
+        for automorphism in self.automorphisms.iter().filter(|automorphism| fixed.iter().all(|&c| automorphism[c] == c)) {
+            for (c, &image) in automorphism.iter().enumerate() {
+                let (a, b) = (find(&mut root, c), find(&mut root, image));
+                root[a.max(b)] = a.min(b);
+            }
+        }
+        return (0..num_contestants).map(|c| find(&mut root, c)).collect();
+    }
+}
+
+pub fn canonicalize<P: Contestant>(history: &History<P>) -> Canonicalization {
+    let indexed = Indexed::new(history);
+    let mut found = Found::default();
+    indexed.search(vec![0; indexed.num_contestants], &mut vec![], &mut found);
+    let (form, labels) = found.best.unwrap_or((CanonicalForm { num_contestants: 0, observations: vec![] }, vec![]));
+    return Canonicalization { form, labels };
+}
+
+impl CanonicalForm {
+    pub fn of<P: Contestant>(history: &History<P>) -> CanonicalForm {
+        return canonicalize(history).form;
+    }
+
+    /* 64-bit FNV-1a over the form. Unlike the std hasher it is fixed across builds and platforms, so it
+    can name results saved to disk */
+    pub fn stable_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut feed = |value: usize| {
+            for byte in (value as u64).to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        feed(self.num_contestants);
+        feed(self.observations.len());
+        for observation in self.observations.iter() {
+            match observation {
+                CanonicalObservation::Booth { pair, is_match } => {
+                    feed(0);
+                    feed(pair.0);
+                    feed(pair.1);
+                    feed(usize::from(*is_match));
+                }
+                CanonicalObservation::Ceremony { pairs, beams } => {
+                    feed(1);
+                    feed(*beams);
+                    feed(pairs.len());
+                    for pair in pairs.iter() {
+                        feed(pair.0);
+                        feed(pair.1);
+                    }
+                }
+            }
+        }
+        return hash;
+    }
+}
+
+impl fmt::Display for CanonicalForm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} contestants", self.num_contestants)?;
+        for observation in self.observations.iter() {
+            match observation {
+                CanonicalObservation::Booth { pair, is_match } => {
+                    write!(f, "; booth {}+{} {}", pair.0, pair.1, if *is_match { "match" } else { "no match" })?
+                }
+                CanonicalObservation::Ceremony { pairs, beams } => {
+                    let seated: Vec<String> = pairs.iter().map(|(a, b)| format!("{}+{}", a, b)).collect();
+                    write!(f, "; ceremony {} = {}", seated.join(", "), beams)?
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::canonical::{canonicalize, CanonicalForm};
+    use crate::contestant::{ContestantPair, Player};
+    use crate::history::{History, Observation};
+    use crate::utils::gen_contestants;
+
+    fn pair(contestants: &[Player], a: usize, b: usize) -> ContestantPair {
+        return ContestantPair::new(contestants[a].clone(), contestants[b].clone());
+    }
+
+    fn history(contestants: &[Player], ceremonies: &[(&[(usize, usize)], usize)], booths: &[(usize, usize, bool)]) -> History {
+        let mut history = History::new(contestants.to_vec());
+        for (pairs, beams) in ceremonies.iter() {
+            history.record(Observation::Ceremony { pairs: pairs.iter().map(|&(a, b)| pair(contestants, a, b)).collect(), beams: *beams });
+        }
+        for &(a, b, is_match) in booths.iter() {
+            history.record(Observation::Booth { pair: pair(contestants, a, b), is_match });
+        }
+        return history;
+    }
+
+    #[test]
+    fn test_relabeling_invariance() {
+        let c = gen_contestants(6);
+        let original = history(&c, &[(&[(0, 1), (2, 3), (4, 5)], 1), (&[(0, 2), (1, 4), (3, 5)], 0)], &[(0, 1, false), (2, 3, true)]);
+        // Contestants 0..6 renamed to 5, 3, 1, 0, 4, 2, with the observations in another order
+        let renamed = history(&c, &[(&[(1, 5), (3, 4), (0, 2)], 0), (&[(3, 5), (0, 1), (4, 2)], 1)], &[(0, 1, true), (3, 5, false)]);
+        assert_eq!(CanonicalForm::of(&original), CanonicalForm::of(&renamed));
+        assert_eq!(CanonicalForm::of(&original).stable_hash(), CanonicalForm::of(&renamed).stable_hash());
+
+        // Same ceremonies but the booth confirms a different kind of pair
+        let different = history(&c, &[(&[(0, 1), (2, 3), (4, 5)], 1), (&[(0, 2), (1, 4), (3, 5)], 0)], &[(0, 1, false), (0, 3, true)]);
+        assert_ne!(CanonicalForm::of(&original), CanonicalForm::of(&different));
+        assert_ne!(CanonicalForm::of(&original).stable_hash(), CanonicalForm::of(&different).stable_hash());
+    }
+
+    #[test]
+    fn test_labels_map_the_history() {
+        let c = gen_contestants(8);
+        let empty = canonicalize(&History::new(c.clone()));
+        assert_eq!(empty.form.to_string(), "8 contestants");
+
+        let game = history(&c, &[(&[(0, 7), (1, 6), (2, 5), (3, 4)], 2)], &[(1, 6, false)]);
+        let canonical = canonicalize(&game);
+        let mut labels = canonical.labels.clone();
+        labels.sort();
+        assert_eq!(labels, (0..8).collect::<Vec<_>>());
+        // The booth pair keeps its place in the ceremony under the new labels
+        let booth = (canonical.labels[1].min(canonical.labels[6]), canonical.labels[1].max(canonical.labels[6]));
+        assert!(canonical.form.to_string().contains(&format!("booth {}+{} no match", booth.0, booth.1)));
+        assert_eq!(canonical.form.to_string(), "8 contestants; booth 0+1 no match; ceremony 0+1, 2+3, 4+5, 6+7 = 2");
+    }
+}
@@ -0,0 +1,517 @@
+use crate::contestant::{Contestant, ContestantPair, ContestantPairs, Player};
+use crate::matching::max_weight_matching;
+use crate::utils::pairs_to_contestants;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/* Pairs a ceremony has to seat together, and pairs it must keep apart */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairConstraints<P: Contestant = Player> {
+    pub forced: Vec<ContestantPair<P>>,
+    pub excluded: Vec<ContestantPair<P>>,
+}
+
+impl<P: Contestant> Default for PairConstraints<P> {
+    fn default() -> Self {
+        return PairConstraints { forced: vec![], excluded: vec![] };
+    }
+}
+
+impl<P: Contestant> PairConstraints<P> {
+    pub fn new() -> PairConstraints<P> {
+        return PairConstraints::default();
+    }
+
+    pub fn with_forced(mut self, pair: ContestantPair<P>) -> PairConstraints<P> {
+        self.forced.push(pair);
+        return self;
+    }
+
+    pub fn with_excluded(mut self, pair: ContestantPair<P>) -> PairConstraints<P> {
+        self.excluded.push(pair);
+        return self;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.forced.is_empty() && self.excluded.is_empty();
+    }
+
+    /* Forced pairs the ceremony splits up and excluded pairs it seats */
+    pub fn violations(&self, ceremony: &[ContestantPair<P>]) -> Vec<ContestantPair<P>> {
+        let missing = self.forced.iter().filter(|pair| !ceremony.contains(pair));
+        let seated = self.excluded.iter().filter(|pair| ceremony.contains(pair));
+        return missing.chain(seated).cloned().collect();
+    }
+
+    /* Whether a ceremony of these players can keep to the constraints at all */
+    pub fn check(&self, players: &[P]) -> Result<(), SeatingError<P>> {
+        let mut seated: HashSet<&P> = HashSet::new();
+        for pair in self.forced.iter() {
+            for player in [pair.get_a(), pair.get_b()] {
+                if !players.contains(player) {
+                    return Err(SeatingError::UnknownPlayer(player.clone()));
+                }
+                if !seated.insert(player) {
+                    return Err(SeatingError::SeatedTwice(player.clone()));
+                }
+            }
+            if self.excluded.contains(pair) {
+                return Err(SeatingError::ForcedAndExcluded(pair.clone()));
+            }
+        }
+        if most_likely_ceremony(players, |_| 1.0, self).is_none() {
+            return Err(SeatingError::NoSeating);
+        }
+        return Ok(());
+    }
+
+    /* The seating of the ceremony's players that keeps to the constraints and keeps as many of the
+    ceremony's pairs as it can, or None when the constraints can't be kept */
+    pub fn enforce(&self, ceremony: Vec<ContestantPair<P>>) -> Option<Vec<ContestantPair<P>>> {
+        if self.violations(&ceremony).is_empty() {
+            return Some(ceremony);
+        }
+        let players: Vec<P> = ceremony.iter().flat_map(|pair| [pair.get_a().clone(), pair.get_b().clone()]).collect();
+        return most_likely_ceremony(&players, |pair| if ceremony.contains(pair) { 1.0 } else { 0.5 }, self);
+    }
+}
+
+/* Why a ceremony can't be seated the way the house wants */
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeatingError<P: Contestant = Player> {
+    UnknownPlayer(P),
+    // Forced into two different pairs
+    SeatedTwice(P),
+    ForcedAndExcluded(ContestantPair<P>),
+    // Every complete seating breaks one of the constraints
+    NoSeating,
+    // The ceremony as seated splits up these forced pairs or seats these excluded ones
+    Ignored(Vec<ContestantPair<P>>),
+}
+
+impl<P: Contestant> fmt::Display for SeatingError<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SeatingError::UnknownPlayer(player) => write!(f, "{} is not in the cast", player),
+            SeatingError::SeatedTwice(player) => write!(f, "{} is forced into more than one pair", player),
+            SeatingError::ForcedAndExcluded(pair) => write!(f, "{} is both forced and excluded", pair),
+            SeatingError::NoSeating => write!(f, "no seating keeps to the constraints"),
+            SeatingError::Ignored(pairs) => write!(f, "the ceremony ignores this week's seating for {}", ContestantPairs(pairs)),
+        }
+    }
+}
+
+impl<P: Contestant> std::error::Error for SeatingError<P> {}
+
+// Log probabilities are rounded to integers at this resolution for the matching
+const WEIGHT_SCALE: f64 = 1_000_000.0;
+
+/* The complete seating with the highest product of pair probabilities, found as a maximum
+   weight perfect matching over log probabilities. Pairs with no chance and excluded pairs
+   are never seated. Returns None when no seating satisfies the constraints. */
+pub fn most_likely_ceremony<P: Contestant, F: Fn(&ContestantPair<P>) -> f64>(
+    players: &[P],
+    probability: F,
+    constraints: &PairConstraints<P>,
+) -> Option<Vec<ContestantPair<P>>> {
+    let mut seated: HashSet<&P> = HashSet::new();
+    for pair in constraints.forced.iter() {
+        let known = players.contains(pair.get_a()) && players.contains(pair.get_b());
+        if !known || !seated.insert(pair.get_a()) || !seated.insert(pair.get_b()) || constraints.excluded.contains(pair) {
+            return None;
+        }
+    }
+    let open: Vec<&P> = players.iter().filter(|p| !seated.contains(p)).collect();
+    let index: HashMap<&P, usize> = open.iter().enumerate().map(|(i, p)| (*p, i)).collect();
+
+    let mut edges = vec![];
+    for (i, a) in open.iter().enumerate() {
+        for b in open[i + 1..].iter() {
+            let pair = ContestantPair::new((*a).clone(), (*b).clone());
+            let p = probability(&pair);
+            if p > 0.0 && !constraints.excluded.contains(&pair) {
+                edges.push((index[a], index[b], (p.min(1.0).ln() * WEIGHT_SCALE).round() as i64));
+            }
+        }
+    }
+    // Every perfect matching has the same number of pairs, so shifting the weights to be
+    // positive doesn't change which one is heaviest
+    let lightest = edges.iter().map(|e| e.2).min().unwrap_or(0);
+    for edge in edges.iter_mut() {
+        edge.2 += 1 - lightest;
+    }
+
+    let mate = max_weight_matching(open.len(), &edges, true);
+    let mut ceremony = constraints.forced.clone();
+    for (i, partner) in mate.iter().enumerate() {
+        match partner {
+            Some(j) if i < *j => ceremony.push(ContestantPair::new(open[i].clone(), open[*j].clone())),
+            Some(_) => (),
+            None => return None,
+        }
+    }
+    return Some(ceremony);
+}
+
+/* What a week's ceremony is for */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CeremonyMode {
+    // Seat the best guess, which could end the game
+    Win,
+    // Seat whatever splits the remaining solutions best, usually with known-wrong filler pairs
+    Information,
+}
+
+/* Goes for the win when time is running out or the best guess is likely enough, and
+   designs ceremonies for information otherwise */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModePolicy {
+    pub win_weeks_left: usize,
+    pub win_probability: f64,
+}
+
+impl Default for ModePolicy {
+    fn default() -> Self {
+        return ModePolicy { win_weeks_left: 1, win_probability: 0.5 };
+    }
+}
+
+impl ModePolicy {
+    pub fn choose(&self, weeks_left: usize, win_probability: f64) -> CeremonyMode {
+        if weeks_left <= self.win_weeks_left || win_probability >= self.win_probability {
+            return CeremonyMode::Win;
+        }
+        return CeremonyMode::Information;
+    }
+}
+
+/* The spread of beam counts an information ceremony aims for */
+#[derive(Debug, Clone, PartialEq)]
+pub enum BeamTarget {
+    // As close to every beam count being equally likely as possible, i.e. the most bits
+    EvenSplit,
+    // Chance of each beam count, starting from zero beams
+    Distribution(Vec<f64>),
+}
+
+impl BeamTarget {
+    /* Higher is better. Entropy in bits for an even split, minus the total variation distance otherwise */
+    pub fn score(&self, beams: &[f64]) -> f64 {
+        return match self {
+            BeamTarget::EvenSplit => beams.iter().filter(|&&p| p > 0.0).map(|p| -p * p.log2()).sum(),
+            BeamTarget::Distribution(target) => {
+                let len = beams.len().max(target.len());
+                let at = |dist: &[f64], i: usize| dist.get(i).cloned().unwrap_or(0.0);
+                -(0..len).map(|i| (at(beams, i) - at(target, i)).abs()).sum::<f64>() / 2.0
+            }
+        };
+    }
+}
+
+impl std::str::FromStr for BeamTarget {
+    type Err = String;
+
+    /* "even" for an even split, or the chance of each beam count from zero up separated by slashes,
+       e.g. "0.2/0.5/0.3". The chances have to add up to one */
+    fn from_str(target: &str) -> Result<BeamTarget, String> {
+        if target == "even" {
+            return Ok(BeamTarget::EvenSplit);
+        }
+        let chances = target.split('/').map(|chance| chance.trim().parse::<f64>()).collect::<Result<Vec<f64>, _>>().map_err(|_| target.to_string())?;
+        if chances.iter().any(|&chance| !(0.0..=1.0).contains(&chance)) || (chances.iter().sum::<f64>() - 1.0).abs() > 1e-6 {
+            return Err(target.to_string());
+        }
+        return Ok(BeamTarget::Distribution(chances));
+    }
+}
+
+/* Beam count distribution when every pair lights up independently with its own probability */
+pub fn independent_beams<P: Contestant, F: Fn(&ContestantPair<P>) -> f64>(ceremony: &[ContestantPair<P>], probability: F) -> Vec<f64> {
+    let mut beams = vec![1.0];
+    for pair in ceremony.iter() {
+        let p = probability(pair);
+        let mut next = vec![0.0; beams.len() + 1];
+        for (count, chance) in beams.iter().enumerate() {
+            next[count] += chance * (1.0 - p);
+            next[count + 1] += chance * p;
+        }
+        beams = next;
+    }
+    return beams;
+}
+
+/* Hill climbs from the starting seating by swapping partners between two seated pairs, keeping
+   any swap that moves the beam distribution closer to the target. Stops after max_passes passes
+   or once no swap helps. */
+pub fn design_ceremony<P: Contestant, E: FnMut(&[ContestantPair<P>]) -> Vec<f64>>(
+    start: Vec<ContestantPair<P>>,
+    target: &BeamTarget,
+    mut beams: E,
+    max_passes: usize,
+) -> Vec<ContestantPair<P>> {
+    let mut ceremony = start;
+    let mut best = target.score(&beams(&ceremony));
+    for _ in 0..max_passes {
+        let mut improved = false;
+        for i in 0..ceremony.len() {
+            for j in i + 1..ceremony.len() {
+                let (a, b) = (ceremony[i].get_a().clone(), ceremony[i].get_b().clone());
+                let (c, d) = (ceremony[j].get_a().clone(), ceremony[j].get_b().clone());
+                for (first, second) in [
+                    (ContestantPair::new(a.clone(), c.clone()), ContestantPair::new(b.clone(), d.clone())),
+                    (ContestantPair::new(a.clone(), d.clone()), ContestantPair::new(b.clone(), c.clone())),
+                ] {
+                    let mut candidate = ceremony.clone();
+                    candidate[i] = first;
+                    candidate[j] = second;
+                    let score = target.score(&beams(&candidate));
+                    if score > best + 1e-12 {
+                        best = score;
+                        ceremony = candidate;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+    return ceremony;
+}
+
+/* Seats a few uncertain pairs and fills the rest of the ceremony with the least likely pairs
+   there are, ideally known-wrong ones, so every beam has to come from the chosen pairs. Pairs are
+   added most uncertain first whenever that moves the beam distribution closer to the target.
+   Partner swaps rarely get here on their own, since the filler only pays off once it is all
+   in place. */
+pub fn filler_ceremony<P: Contestant, F: Fn(&ContestantPair<P>) -> f64, E: FnMut(&[ContestantPair<P>]) -> Vec<f64>>(
+    players: &[P],
+    probability: F,
+    target: &BeamTarget,
+    mut beams: E,
+) -> Option<Vec<ContestantPair<P>>> {
+    let mut candidates = vec![];
+    for (i, a) in players.iter().enumerate() {
+        for b in players[i + 1..].iter() {
+            let pair = ContestantPair::new(a.clone(), b.clone());
+            let p = probability(&pair);
+            if p > 0.0 && p < 1.0 {
+                candidates.push(((p - 0.5).abs(), pair));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    let mut chosen: Vec<ContestantPair<P>> = vec![];
+    let mut best: Option<(f64, Vec<ContestantPair<P>>)> = None;
+    for (_, pair) in candidates.into_iter() {
+        if chosen.iter().any(|seated| seated.get_a() == pair.get_a() || seated.get_b() == pair.get_b()
+            || seated.get_a() == pair.get_b() || seated.get_b() == pair.get_a())
+        {
+            continue;
+        }
+        chosen.push(pair);
+        let Some(seating) = least_likely_filler(players, &probability, &chosen) else {
+            chosen.pop();
+            continue;
+        };
+        let score = target.score(&beams(&seating));
+        if best.as_ref().is_none_or(|(best_score, _)| score > best_score + 1e-12) {
+            best = Some((score, seating));
+        } else {
+            chosen.pop();
+        }
+    }
+    return best.map(|(_, seating)| seating);
+}
+
+/* Hill climbs both from the starting seating and from the filler seating, and keeps whichever
+   ends up closer to the target */
+pub fn design_with_filler<P: Contestant, F: Fn(&ContestantPair<P>) -> f64, E: Fn(&[ContestantPair<P>]) -> Vec<f64>>(
+    start: Vec<ContestantPair<P>>,
+    probability: F,
+    target: &BeamTarget,
+    beams: E,
+    max_passes: usize,
+) -> Vec<ContestantPair<P>> {
+    let filler = filler_ceremony(&pairs_to_contestants(&start), probability, target, &beams);
+    let mut designed = design_ceremony(start, target, &beams, max_passes);
+    if let Some(filler) = filler {
+        let from_filler = design_ceremony(filler, target, &beams, max_passes);
+        if target.score(&beams(&from_filler)) > target.score(&beams(&designed)) + 1e-12 {
+            designed = from_filler;
+        }
+    }
+    return designed;
+}
+
+/* The chosen pairs plus the seating of everybody else with the lowest product of probabilities */
+fn least_likely_filler<P: Contestant, F: Fn(&ContestantPair<P>) -> f64>(
+    players: &[P],
+    probability: F,
+    chosen: &[ContestantPair<P>],
+) -> Option<Vec<ContestantPair<P>>> {
+    let open: Vec<&P> = players.iter().filter(|p| !chosen.iter().any(|pair| pair.get_a() == *p || pair.get_b() == *p)).collect();
+    let mut edges = vec![];
+    for (i, a) in open.iter().enumerate() {
+        for (j, b) in open.iter().enumerate().skip(i + 1) {
+            let p = probability(&ContestantPair::new((*a).clone(), (*b).clone()));
+            // Known-wrong pairs weigh the most, and more likely pairs less and less
+            edges.push((i, j, ((1.0 - p.clamp(0.0, 1.0)) * WEIGHT_SCALE).round() as i64 + 1));
+        }
+    }
+    let mate = max_weight_matching(open.len(), &edges, true);
+    let mut seating = chosen.to_vec();
+    for (i, partner) in mate.iter().enumerate() {
+        match partner {
+            Some(j) if i < *j => seating.push(ContestantPair::new(open[i].clone(), open[*j].clone())),
+            Some(_) => (),
+            None => return None,
+        }
+    }
+    return Some(seating);
+}
+
+/* The product of the pair probabilities, in natural log */
+pub fn log_likelihood<P: Contestant, F: Fn(&ContestantPair<P>) -> f64>(ceremony: &[ContestantPair<P>], probability: F) -> f64 {
+    return ceremony.iter().map(|pair| probability(pair).ln()).sum();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ceremony::{
+        design_ceremony, filler_ceremony, independent_beams, log_likelihood, most_likely_ceremony, BeamTarget, CeremonyMode, ModePolicy,
+        PairConstraints, SeatingError,
+    };
+    use crate::history::Observation;
+    use crate::solutionspace::SolutionSpace;
+    use std::collections::HashSet;
+    use crate::whatif::{analyze_action_in, Action};
+    use crate::contestant::{ContestantPair, Player};
+    use crate::utils::{contestants_to_pairs, gen_contestants, pairs_to_contestants};
+
+    #[test]
+    fn test_most_likely_ceremony() {
+        let c = gen_contestants(6);
+        let truth = contestants_to_pairs(&c);
+        let likely = |pair: &ContestantPair| if truth.contains(pair) { 0.6 } else { 0.1 };
+
+        let ceremony = most_likely_ceremony(&c, likely, &PairConstraints::new()).unwrap();
+        assert_eq!(ceremony.len(), 3);
+        assert!(ceremony.iter().all(|pair| truth.contains(pair)));
+        assert!((log_likelihood(&ceremony, likely) - 3.0 * 0.6f64.ln()).abs() < 1e-9);
+
+        // Forcing (0, 2) rules out (0, 1) and (2, 3), and (4, 5) is still the best of the rest
+        let forced = ContestantPair::new(c[0].clone(), c[2].clone());
+        let constraints = PairConstraints::new().with_forced(forced.clone());
+        let ceremony = most_likely_ceremony(&c, likely, &constraints).unwrap();
+        assert_eq!(ceremony[0], forced);
+        assert!(ceremony.contains(&truth[2]));
+
+        // Excluding a pair moves it out, and excluding every partner of a player makes it impossible
+        let constraints = PairConstraints::new().with_excluded(truth[2].clone());
+        let ceremony = most_likely_ceremony(&c, likely, &constraints).unwrap();
+        assert!(!ceremony.contains(&truth[2]));
+        let mut constraints = PairConstraints::new();
+        for other in c[1..].iter() {
+            constraints = constraints.with_excluded(ContestantPair::new(c[0].clone(), other.clone()));
+        }
+        assert_eq!(most_likely_ceremony(&c, likely, &constraints), None);
+    }
+
+    #[test]
+    fn test_enforce_constraints() {
+        let c = gen_contestants(6);
+        let truth = contestants_to_pairs(&c);
+        let forced = ContestantPair::new(c[0].clone(), c[2].clone());
+        let excluded = truth[2].clone();
+        let constraints = PairConstraints::new().with_forced(forced.clone()).with_excluded(excluded.clone());
+        assert_eq!(constraints.violations(&truth), vec![forced.clone(), excluded.clone()]);
+
+        let seating = constraints.enforce(truth.clone()).unwrap();
+        assert_eq!(constraints.violations(&seating), vec![]);
+        assert_eq!(pairs_to_contestants(&seating).into_iter().collect::<HashSet<_>>().len(), 6);
+        assert_eq!(PairConstraints::new().enforce(truth.clone()), Some(truth.clone()));
+
+        // Splitting up a couple that has no one else left to sit with
+        let constraints = PairConstraints::new().with_excluded(truth[0].clone());
+        let seating = constraints.enforce(vec![truth[1].clone(), truth[2].clone(), truth[0].clone()]).unwrap();
+        assert_eq!(constraints.violations(&seating), vec![]);
+        assert_eq!(seating.len(), 3);
+        // One of the other two couples can stay where it was
+        assert_eq!(seating.iter().filter(|pair| truth.contains(pair)).count(), 1);
+
+        // Nobody left for c[0] to sit with, or c[0] forced into two pairs
+        let mut constraints = PairConstraints::new();
+        for other in c[1..].iter() {
+            constraints = constraints.with_excluded(ContestantPair::new(c[0].clone(), other.clone()));
+        }
+        assert_eq!(constraints.enforce(truth.clone()), None);
+        assert_eq!(constraints.check(&c), Err(SeatingError::NoSeating));
+        let twice = PairConstraints::new().with_forced(forced.clone()).with_forced(truth[0].clone());
+        assert_eq!(twice.enforce(truth.clone()), None);
+        assert_eq!(twice.check(&c), Err(SeatingError::SeatedTwice(c[0].clone())));
+        assert_eq!(PairConstraints::new().with_forced(forced).check(&c), Ok(()));
+    }
+
+    #[test]
+    fn test_mode_policy() {
+        let policy = ModePolicy::default();
+        assert_eq!(policy.choose(5, 0.1), CeremonyMode::Information);
+        assert_eq!(policy.choose(1, 0.1), CeremonyMode::Win);
+        assert_eq!(policy.choose(5, 0.9), CeremonyMode::Win);
+    }
+
+    #[test]
+    fn test_beam_target() {
+        assert_eq!(independent_beams::<Player, _>(&[], |_| 0.5), vec![1.0]);
+        let c = gen_contestants(4);
+        let beams = independent_beams(&contestants_to_pairs(&c), |_| 0.5);
+        assert_eq!(beams, vec![0.25, 0.5, 0.25]);
+        assert_eq!(BeamTarget::EvenSplit.score(&beams), 1.5);
+        assert_eq!(BeamTarget::Distribution(vec![0.5, 0.5]).score(&beams), -0.25);
+    }
+
+    #[test]
+    fn test_design_ceremony() {
+        // After a two beam ceremony, seating it again is certain to light two beams and teaches nothing
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut space = SolutionSpace::new(&c);
+        space.observe(&Observation::Ceremony { pairs: truth.clone(), beams: 2 });
+        let exact = |pairs: &[ContestantPair]| -> Vec<f64> {
+            analyze_action_in(&space, &Action::Ceremony(pairs.to_vec())).scenarios.iter().map(|s| s.probability).collect()
+        };
+        assert_eq!(BeamTarget::EvenSplit.score(&exact(&truth)), 0.0);
+
+        let designed = design_ceremony(truth.clone(), &BeamTarget::EvenSplit, exact, 10);
+        assert_eq!(pairs_to_contestants(&designed).into_iter().collect::<HashSet<_>>().len(), 8);
+        assert!(BeamTarget::EvenSplit.score(&exact(&designed)) > 1.0);
+
+        // Aiming for a blackout moves the ceremony towards pairs that rarely light up
+        let blackout = BeamTarget::Distribution(vec![1.0]);
+        let designed = design_ceremony(truth.clone(), &blackout, exact, 10);
+        assert!(exact(&designed)[0] > 0.5);
+    }
+    #[test]
+    fn test_filler_ceremony() {
+        // Two coin flip pairs and a long shot, everything else known to be wrong
+        let c = gen_contestants(6);
+        let truth = contestants_to_pairs(&c);
+        let probability = |pair: &ContestantPair| match truth.iter().position(|t| t == pair) {
+            Some(0) | Some(1) => 0.5,
+            Some(_) => 0.3,
+            None => 0.0,
+        };
+        let one_flip = BeamTarget::Distribution(vec![0.5, 0.5]);
+        let ceremony = filler_ceremony(&c, probability, &one_flip, |pairs: &[ContestantPair]| independent_beams(pairs, probability)).unwrap();
+        assert_eq!(pairs_to_contestants(&ceremony).into_iter().collect::<HashSet<_>>().len(), 6);
+        assert_eq!(ceremony.iter().filter(|pair| truth.contains(pair)).collect::<Vec<_>>(), vec![&truth[0]]);
+        assert_eq!(independent_beams(&ceremony, probability), vec![0.5, 0.5, 0.0, 0.0]);
+
+        // Nobody uncertain left to seat
+        assert_eq!(filler_ceremony(&c, |_| 0.0, &one_flip, |_: &[ContestantPair]| vec![1.0]), None);
+    }
+}
@@ -1,10 +1,21 @@
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::fs::{File};
 use std::io;
 use std::io::{BufRead};
 use rand::seq::IteratorRandom;
+use serde::Serialize;
 
-#[derive(Debug, std::cmp::Eq, std::hash::Hash)]
+/* Whoever the solver pairs up. Who is who is decided by Eq and Hash alone, so names and any other
+   metadata have to stay out of them. The order only has to agree with Eq; it fixes the order in which
+   players are searched, so results don't depend on hashing. Anything with those traits qualifies,
+   from plain ids and UUIDs to richer records, with Player being the one the simulations use */
+pub trait Contestant: Clone + Eq + Hash + Ord + fmt::Display + fmt::Debug {}
+
+impl<T: Clone + Eq + Hash + Ord + fmt::Display + fmt::Debug> Contestant for T {}
+
+#[derive(Debug, std::cmp::Eq, Serialize)]
 pub struct Player {
     pub id: i32,
     pub name: String
@@ -14,11 +25,11 @@ impl Player {
     const NAME_FILE_PATH: &'static str = "./names.txt";
 
     pub fn new(id: i32) -> Player {
-        return Player { id: id, name: Player::pick_name() };
+        return Player { id, name: Player::pick_name() };
     }
 
     fn pick_name() -> String {
-        let file = File::open(Player::NAME_FILE_PATH).unwrap_or_else(|e| panic!("unable to open ./names.txt"));
+        let file = File::open(Player::NAME_FILE_PATH).unwrap_or_else(|_| panic!("unable to open ./names.txt"));
         
         let buffer = io::BufReader::new(file);
         let lines = buffer.lines().map(|line| line.expect("Could not read line"));
@@ -42,57 +53,93 @@ impl PartialEq for Player {
         return self.id == other.id;
     }
 }
+impl Hash for Player {
+    // Names are cosmetic, so only the id may take part in the hash (same as equality)
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+impl PartialOrd for Player {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+impl Ord for Player {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return self.id.cmp(&other.id);
+    }
+}
 
-pub struct Players<'a>(pub  &'a Vec<&'a Player>);
-impl<'a> fmt::Display for Players<'a> {
+pub struct Players<'a, P: Contestant = Player>(pub  &'a Vec<&'a P>);
+impl<'a, P: Contestant> fmt::Display for Players<'a, P> {
     // https://medium.com/apolitical-engineering/how-do-you-impl-display-for-vec-b8dbb21d814f
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.iter().fold(Ok(()), |result, player| {
-            result.and_then(|_| {writeln!(f, "{}, ", player)})
-        })
+        self.0.iter().try_for_each(|player| writeln!(f, "{}, ", player))
     }
 }
 
-#[derive(Debug, Clone, std::cmp::Eq, std::hash::Hash)]
-pub struct ContestantPair {
-    a: Player,
-    b: Player,
+#[derive(Debug, Clone, std::cmp::Eq, Serialize)]
+pub struct ContestantPair<P: Contestant = Player> {
+    a: P,
+    b: P,
 }
-impl ContestantPair {
-    pub fn new(a: Player, b: Player) -> ContestantPair {
+impl<P: Contestant> ContestantPair<P> {
+    pub fn new(a: P, b: P) -> ContestantPair<P> {
         ContestantPair {a, b}
     }
-    pub fn get_a(&self) -> &Player {
+    pub fn get_a(&self) -> &P {
         return &self.a;
     }
-    pub fn get_b(&self) -> &Player {
+    pub fn get_b(&self) -> &P {
         return &self.b;
     }
 
-    pub fn has_player(&self, player: &Player) -> bool {
+    pub fn has_player(&self, player: &P) -> bool {
         // TODO test this
         return &self.a == player || &self.b == player;
     }
+
+    /* The two players, lower one first */
+    fn sorted(&self) -> (&P, &P) {
+        return if self.a <= self.b { (&self.a, &self.b) } else { (&self.b, &self.a) };
+    }
 }
-impl fmt::Display for ContestantPair {
+impl<P: Contestant> fmt::Display for ContestantPair<P> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}, {}", self.get_a(), self.get_b())
     }
 }
 
-impl PartialEq for ContestantPair {
+impl<P: Contestant> PartialEq for ContestantPair<P> {
     fn eq(&self, other: &Self) -> bool {
         // Check that the pair .matches regardless of contestant a or b's order
-        return (self.a.id == other.a.id && self.b.id == other.b.id) || (self.a.id == other.b.id && self.b.id == other.a.id); 
+        return (self.a == other.a && self.b == other.b) || (self.a == other.b && self.b == other.a); 
+    }
+}
+impl<P: Contestant> Hash for ContestantPair<P> {
+    // Hash the players in sorted order so (a, b) and (b, a) land in the same bucket
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let (low, high) = self.sorted();
+        low.hash(state);
+        high.hash(state);
+    }
+}
+impl<P: Contestant> Ord for ContestantPair<P> {
+    // By the lower player and then the higher one, so the order agrees with Eq
+    fn cmp(&self, other: &Self) -> Ordering {
+        return self.sorted().cmp(&other.sorted());
+    }
+}
+impl<P: Contestant> PartialOrd for ContestantPair<P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
     }
 }
-pub struct ContestantPairs<'a>(pub  &'a Vec<ContestantPair>);
-impl<'a> fmt::Display for ContestantPairs<'a> {
+pub struct ContestantPairs<'a, P: Contestant = Player>(pub  &'a Vec<ContestantPair<P>>);
+impl<'a, P: Contestant> fmt::Display for ContestantPairs<'a, P> {
     // https://medium.com/apolitical-engineering/how-do-you-impl-display-for-vec-b8dbb21d814f
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.iter().fold(Ok(()), |result, pair| {
-            result.and_then(|_| {writeln!(f, "{}", pair)})
-        })
+        self.0.iter().try_for_each(|pair| writeln!(f, "{}", pair))
     }
 }
 
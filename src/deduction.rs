@@ -0,0 +1,464 @@
+use crate::contestant::{Contestant, ContestantPair, Player};
+use crate::history::Observation;
+use crate::matching::max_weight_matching;
+use crate::round::SavedRound;
+use crate::solutionspace::SolutionSpace;
+use log::{debug, error};
+use std::collections::{HashMap, HashSet};
+
+/* Why a pair could be decided without sending it to the truth booth */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason<P: Contestant = Player> {
+    // Once impossible pairs are removed, the player has nobody else left
+    OnlyOption(P),
+    // Taking the pair leaves the rest of the cast with no perfect matching
+    NoPerfectMatching,
+    // The saved round has as many beams left as still possible pairs, or none at all
+    CeremonyCount { round_id: u32 },
+    // The beams two saved rounds share pin down how many the rest of the round has
+    OverlappingCeremonies { round_id: u32, other_round_id: u32 },
+    // Every (or no) matching consistent with all saved rounds has the pair
+    EveryMatching,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Deduction<P: Contestant = Player> {
+    Confirmed(ContestantPair<P>, Reason<P>),
+    RuledOut(ContestantPair<P>, Reason<P>),
+}
+
+/* Above this many undecided players only the cheap OnlyOption rule is used */
+pub const MATCHING_CHECK_LIMIT: usize = 40;
+/* At or below this many undecided players the saved rounds are checked against every matching */
+pub const EXACT_LIMIT: usize = 12;
+
+/* Finds pairs decided by the possibilities and the saved rounds, trying the cheapest rules first
+   and returning as soon as one of them turns something up. Callers apply the deductions and ask
+   again until nothing new follows. Saved rounds must only consider pairs among undecided players,
+   with num_correct counting the beams those pairs still owe. */
+pub fn deduce<P: Contestant>(possibilities: &HashMap<P, HashSet<P>>, rounds: &[SavedRound<P>]) -> Vec<Deduction<P>> {
+    let deductions = deduce_from_possibilities(possibilities);
+    if !deductions.is_empty() {
+        return deductions;
+    }
+    let deductions = deduce_from_rounds(possibilities, rounds);
+    if !deductions.is_empty() || rounds.is_empty() || possibilities.len() > EXACT_LIMIT {
+        return deductions;
+    }
+    return deduce_exactly(possibilities, rounds);
+}
+
+/* A pair is ruled out when no perfect matching of the remaining players uses it (a generalisation
+   of Hall's theorem to the non-bipartite game), and confirmed when it is the last option a player has left. */
+pub fn deduce_from_possibilities<P: Contestant>(possibilities: &HashMap<P, HashSet<P>>) -> Vec<Deduction<P>> {
+    let mut players: Vec<&P> = possibilities.keys().collect();
+    players.sort();
+    let index: HashMap<&P, usize> = players.iter().enumerate().map(|(i, p)| (*p, i)).collect();
+
+    let mut adjacency: Vec<HashSet<usize>> = players
+        .iter()
+        .map(|p| possibilities[*p].iter().filter_map(|other| index.get(other).cloned()).collect())
+        .collect();
+
+    let mut deductions = vec![];
+    if players.len() <= MATCHING_CHECK_LIMIT {
+        // Pairs seen in some perfect matching. Preferring the unseen ones covers most pairs in a
+        // few rounds, and only the pairs left over need a matching of their own
+        let mut in_matching: HashSet<(usize, usize)> = HashSet::new();
+        loop {
+            let unseen_first = |a: usize, b: usize| if in_matching.contains(&(a, b)) { 1 } else { 2 };
+            let mate = match perfect_matching(&adjacency, &[], unseen_first) {
+                Some(mate) => mate,
+                None => {
+                    error!("The possibilities have no perfect matching left, the true matching was pruned");
+                    return deductions;
+                }
+            };
+            let seen = in_matching.len();
+            in_matching.extend(matched_pairs(&mate));
+            if in_matching.len() == seen {
+                break;
+            }
+        }
+        for a in 0..players.len() {
+            let mut options: Vec<usize> = adjacency[a].iter().cloned().filter(|&b| a < b).collect();
+            options.sort();
+            for b in options {
+                if in_matching.contains(&(a, b)) {
+                    continue;
+                }
+                let unseen_first = |a: usize, b: usize| if in_matching.contains(&(a, b)) { 1 } else { 2 };
+                match perfect_matching(&adjacency, &[a, b], unseen_first) {
+                    Some(rest) => {
+                        in_matching.insert((a, b));
+                        in_matching.extend(matched_pairs(&rest));
+                    }
+                    None => {
+                        debug!("{} and {} can't be a match without leaving someone out", players[a], players[b]);
+                        deductions.push(Deduction::RuledOut(
+                            ContestantPair::new(players[a].clone(), players[b].clone()),
+                            Reason::NoPerfectMatching,
+                        ));
+                    }
+                }
+            }
+        }
+        for deduction in deductions.iter() {
+            if let Deduction::RuledOut(pair, _) = deduction {
+                let (a, b) = (index[pair.get_a()], index[pair.get_b()]);
+                adjacency[a].remove(&b);
+                adjacency[b].remove(&a);
+            }
+        }
+    }
+
+    for (a, options) in adjacency.iter().enumerate() {
+        if options.len() == 1 {
+            let b = *options.iter().next().unwrap();
+            // Report each forced pair once, from the lower index
+            if a < b || adjacency[b].len() != 1 {
+                deductions.push(Deduction::Confirmed(
+                    ContestantPair::new(players[a].clone(), players[b].clone()),
+                    Reason::OnlyOption(players[a].clone()),
+                ));
+            }
+        }
+    }
+    return deductions;
+}
+
+/* Checks a single pair against the possibilities without counting matchings: it is a match when
+   no perfect matching does without it, and ruled out when none has it. Possibilities with no perfect
+   matching at all have lost the truth, so nothing is decided by them */
+pub fn decided_by_matching<P: Contestant>(possibilities: &HashMap<P, HashSet<P>>, pair: &ContestantPair<P>, is_match: bool) -> bool {
+    if !is_possible(possibilities, pair) {
+        return false;
+    }
+    let players: Vec<&P> = possibilities.keys().collect();
+    let index: HashMap<&P, usize> = players.iter().enumerate().map(|(i, p)| (*p, i)).collect();
+    let mut adjacency: Vec<HashSet<usize>> = players
+        .iter()
+        .map(|p| possibilities[*p].iter().filter_map(|other| index.get(other).cloned()).collect())
+        .collect();
+    if perfect_matching(&adjacency, &[], |_, _| 1).is_none() {
+        return false;
+    }
+    let (a, b) = (index[pair.get_a()], index[pair.get_b()]);
+    if is_match {
+        adjacency[a].remove(&b);
+        adjacency[b].remove(&a);
+        return perfect_matching(&adjacency, &[], |_, _| 1).is_none();
+    }
+    return perfect_matching(&adjacency, &[a, b], |_, _| 1).is_none();
+}
+
+fn is_possible<P: Contestant>(possibilities: &HashMap<P, HashSet<P>>, pair: &ContestantPair<P>) -> bool {
+    return possibilities.get(pair.get_a()).is_some_and(|options| options.contains(pair.get_b()));
+}
+
+fn decide_all<P: Contestant>(pairs: &[&ContestantPair<P>], confirmed: bool, reason: &Reason<P>, deductions: &mut Vec<Deduction<P>>) {
+    for &pair in pairs.iter() {
+        let deduction = if confirmed {
+            Deduction::Confirmed(pair.clone(), reason.clone())
+        } else {
+            Deduction::RuledOut(pair.clone(), reason.clone())
+        };
+        if !deductions.iter().any(|d| matches!(d, Deduction::Confirmed(p, _) | Deduction::RuledOut(p, _) if p == pair)) {
+            deductions.push(deduction);
+        }
+    }
+}
+
+/* Beam counting over the saved rounds. On its own a round is decided once its beams are all
+   accounted for (rule the rest out) or it has exactly as many possible pairs as beams (confirm
+   them all). Two rounds sharing pairs bound how many beams the shared pairs hold, which in turn
+   bounds the beams left for the pairs only one of the rounds has. */
+pub fn deduce_from_rounds<P: Contestant>(possibilities: &HashMap<P, HashSet<P>>, rounds: &[SavedRound<P>]) -> Vec<Deduction<P>> {
+    let open: Vec<Vec<ContestantPair<P>>> = rounds
+        .iter()
+        .map(|round| {
+            round
+                .pick_from_round(round.num_consideration())
+                .into_iter()
+                .filter(|pair| is_possible(possibilities, pair))
+                .collect()
+        })
+        .collect();
+
+    let mut deductions = vec![];
+    for (round, pairs) in rounds.iter().zip(open.iter()) {
+        let pairs: Vec<&ContestantPair<P>> = pairs.iter().collect();
+        let reason = Reason::CeremonyCount { round_id: round.round_id };
+        if round.num_correct > pairs.len() {
+            error!("Saved round #{} needs {} beams from {} pairs", round.round_id, round.num_correct, pairs.len());
+            return vec![];
+        }
+        if round.num_correct == 0 {
+            decide_all(&pairs, false, &reason, &mut deductions);
+        } else if round.num_correct == pairs.len() {
+            decide_all(&pairs, true, &reason, &mut deductions);
+        }
+    }
+    if !deductions.is_empty() {
+        return deductions;
+    }
+
+    for (r, (round, round_pairs)) in rounds.iter().zip(open.iter()).enumerate() {
+        for (q, (other, other_pairs)) in rounds.iter().zip(open.iter()).enumerate() {
+            if r == q {
+                continue;
+            }
+            let (shared, only_round): (Vec<&ContestantPair<P>>, Vec<&ContestantPair<P>>) =
+                round_pairs.iter().partition(|pair| other_pairs.contains(pair));
+            if shared.is_empty() {
+                continue;
+            }
+            let only_other = other_pairs.len() - shared.len();
+
+            // Bounds on the beams among the shared pairs, as seen from both rounds
+            let low = other.num_correct.saturating_sub(only_other).max(round.num_correct.saturating_sub(only_round.len()));
+            let high = shared.len().min(other.num_correct).min(round.num_correct);
+            if low > high {
+                error!("Saved rounds #{} and #{} disagree about their shared pairs", round.round_id, other.round_id);
+                return vec![];
+            }
+
+            let reason = Reason::OverlappingCeremonies { round_id: round.round_id, other_round_id: other.round_id };
+            if high == 0 {
+                decide_all(&shared, false, &reason, &mut deductions);
+            } else if low == shared.len() {
+                decide_all(&shared, true, &reason, &mut deductions);
+            }
+            if !only_round.is_empty() {
+                if round.num_correct == low {
+                    decide_all(&only_round, false, &reason, &mut deductions);
+                } else if round.num_correct - high == only_round.len() {
+                    decide_all(&only_round, true, &reason, &mut deductions);
+                }
+            }
+        }
+    }
+    return deductions;
+}
+
+/* The exact solution space of the undecided players under the possibilities and the saved rounds */
+pub fn exact_space<P: Contestant>(possibilities: &HashMap<P, HashSet<P>>, rounds: &[SavedRound<P>]) -> SolutionSpace<P> {
+    let players: Vec<P> = possibilities.keys().cloned().collect();
+    let mut space = SolutionSpace::new(&players);
+    let sorted = space.players().to_vec();
+    for (i, a) in sorted.iter().enumerate() {
+        for b in sorted[i + 1..].iter() {
+            let pair = ContestantPair::new(a.clone(), b.clone());
+            if !is_possible(possibilities, &pair) {
+                space.rule_out(&pair);
+            }
+        }
+    }
+    for round in rounds.iter() {
+        let pairs = round.pick_from_round(round.num_consideration());
+        space.observe(&Observation::Ceremony { pairs, beams: round.num_correct });
+    }
+    return space;
+}
+
+/* Enumerates every matching of the undecided players that agrees with the saved rounds */
+pub fn deduce_exactly<P: Contestant>(possibilities: &HashMap<P, HashSet<P>>, rounds: &[SavedRound<P>]) -> Vec<Deduction<P>> {
+    let space = exact_space(possibilities, rounds);
+    let summary = space.summarize();
+    if summary.count == 0 {
+        error!("No matching agrees with the saved rounds");
+        return vec![];
+    }
+    let mut deductions = vec![];
+    let players = space.players();
+    for i in 0..players.len() {
+        for j in (i + 1..players.len()).filter(|&j| space.is_allowed(i, j)) {
+            let pair = ContestantPair::new(players[i].clone(), players[j].clone());
+            match summary.pair_count(&pair) {
+                0 => deductions.push(Deduction::RuledOut(pair, Reason::EveryMatching)),
+                count if count == summary.count => deductions.push(Deduction::Confirmed(pair, Reason::EveryMatching)),
+                _ => (),
+            }
+        }
+    }
+    return deductions;
+}
+
+/* A perfect matching of the players besides the left out ones, if there is one. The blossom matcher
+   returns the heaviest of the largest matchings, which is perfect whenever a perfect one exists, so
+   the weights only pick between perfect matchings */
+fn perfect_matching<W: Fn(usize, usize) -> i64>(adjacency: &[HashSet<usize>], left_out: &[usize], weight: W) -> Option<Vec<Option<usize>>> {
+    let mut edges: Vec<(usize, usize, i64)> = vec![];
+    for (a, options) in adjacency.iter().enumerate().filter(|(a, _)| !left_out.contains(a)) {
+        edges.extend(options.iter().filter(|&&b| a < b && !left_out.contains(&b)).map(|&b| (a, b, weight(a, b))));
+    }
+    edges.sort();
+    let mate = max_weight_matching(adjacency.len(), &edges, true);
+    if (0..adjacency.len()).any(|v| mate[v].is_none() && !left_out.contains(&v)) {
+        return None;
+    }
+    return Some(mate);
+}
+
+fn matched_pairs(mate: &[Option<usize>]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    return mate.iter().enumerate().filter_map(|(a, b)| b.filter(|&b| a < b).map(|b| (a, b)));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::contestant::{ContestantPair, Player};
+    use crate::deduction::{deduce, deduce_exactly, deduce_from_rounds, perfect_matching, Deduction, Reason};
+    use crate::round::SavedRound;
+    use crate::solutionspace::SolutionSpace;
+    use crate::utils::{contestants_to_pairs, gen_contestants, get_matches};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::{HashMap, HashSet};
+
+    fn possibilities_from(c: &[Player], pairs: &[(usize, usize)]) -> HashMap<Player, HashSet<Player>> {
+        let mut possibilities: HashMap<Player, HashSet<Player>> = c.iter().map(|p| (p.clone(), HashSet::new())).collect();
+        for &(a, b) in pairs.iter() {
+            possibilities.get_mut(&c[a]).unwrap().insert(c[b].clone());
+            possibilities.get_mut(&c[b]).unwrap().insert(c[a].clone());
+        }
+        return possibilities;
+    }
+
+    #[test]
+    fn test_hall_pruning() {
+        // 0 and 1 can only go with 2 or 3, so 2 and 3 are taken and 4 must pair with 5
+        let c = gen_contestants(6);
+        let possibilities = possibilities_from(&c, &[(0, 2), (0, 3), (1, 2), (1, 3), (2, 4), (2, 5), (3, 4), (3, 5), (4, 5)]);
+        let deductions = deduce(&possibilities, &[]);
+
+        for (a, b) in [(2, 4), (2, 5), (3, 4), (3, 5)] {
+            let pair = ContestantPair::new(c[a].clone(), c[b].clone());
+            assert!(deductions.contains(&Deduction::RuledOut(pair, Reason::NoPerfectMatching)));
+        }
+        let forced = ContestantPair::new(c[4].clone(), c[5].clone());
+        assert!(deductions.contains(&Deduction::Confirmed(forced, Reason::OnlyOption(c[4].clone()))));
+        assert_eq!(deductions.len(), 5);
+    }
+
+    #[test]
+    fn test_nothing_to_deduce() {
+        let c = gen_contestants(6);
+        let mut pairs = vec![];
+        for a in 0..6 {
+            for b in a + 1..6 {
+                pairs.push((a, b));
+            }
+        }
+        assert!(deduce(&possibilities_from(&c, &pairs), &[]).is_empty());
+    }
+
+    fn everyone(c: &[Player]) -> HashMap<Player, HashSet<Player>> {
+        let mut pairs = vec![];
+        for a in 0..c.len() {
+            for b in a + 1..c.len() {
+                pairs.push((a, b));
+            }
+        }
+        return possibilities_from(c, &pairs);
+    }
+
+    #[test]
+    fn test_ceremony_count() {
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut possibilities = everyone(&c);
+        let rounds = vec![SavedRound::new(get_matches(&truth, 2, 2), 2, 1)];
+        assert!(deduce_from_rounds(&possibilities, &rounds).is_empty());
+
+        // Booths rule out the two pairs that weren't matches, leaving two pairs for two beams
+        for pair in get_matches(&truth, 2, 2)[2..].iter() {
+            possibilities.get_mut(pair.get_a()).unwrap().remove(pair.get_b());
+            possibilities.get_mut(pair.get_b()).unwrap().remove(pair.get_a());
+        }
+        let deductions = deduce(&possibilities, &rounds);
+        assert_eq!(
+            deductions,
+            vec![
+                Deduction::Confirmed(truth[0].clone(), Reason::CeremonyCount { round_id: 1 }),
+                Deduction::Confirmed(truth[1].clone(), Reason::CeremonyCount { round_id: 1 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_ceremonies() {
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let possibilities = everyone(&c);
+
+        // The shared pairs hold exactly one beam, so the second beam of round 1 is the third pair
+        let rounds = vec![SavedRound::new(truth[0..3].to_vec(), 2, 1), SavedRound::new(truth[0..2].to_vec(), 1, 2)];
+        let reason = Reason::OverlappingCeremonies { round_id: 1, other_round_id: 2 };
+        assert_eq!(deduce_from_rounds(&possibilities, &rounds), vec![Deduction::Confirmed(truth[2].clone(), reason.clone())]);
+        assert!(deduce_exactly(&possibilities, &rounds).contains(&Deduction::Confirmed(truth[2].clone(), Reason::EveryMatching)));
+
+        // With a single beam in round 1, it has to be one of the shared pairs
+        let rounds = vec![SavedRound::new(truth[0..3].to_vec(), 1, 1), SavedRound::new(truth[0..2].to_vec(), 1, 2)];
+        assert_eq!(deduce_from_rounds(&possibilities, &rounds), vec![Deduction::RuledOut(truth[2].clone(), reason)]);
+        assert!(deduce_exactly(&possibilities, &rounds).contains(&Deduction::RuledOut(truth[2].clone(), Reason::EveryMatching)));
+    }
+
+    #[test]
+    fn test_perfect_matching() {
+        // Two triangles joined by one edge only match perfectly through that edge
+        let adjacency: Vec<HashSet<usize>> = vec![
+            [1, 2].into_iter().collect(),
+            [0, 2].into_iter().collect(),
+            [0, 1, 3].into_iter().collect(),
+            [2, 4, 5].into_iter().collect(),
+            [3, 5].into_iter().collect(),
+            [3, 4].into_iter().collect(),
+        ];
+        let same = |_, _| 1;
+        let mate = perfect_matching(&adjacency, &[], same).unwrap();
+        assert_eq!(mate[2], Some(3));
+        assert_eq!(perfect_matching(&adjacency, &[0, 2], same), None);
+        assert!(perfect_matching(&adjacency, &[0, 1], same).is_some());
+        assert_eq!(perfect_matching(&adjacency, &[3, 4], same), None);
+
+        // Weights choose between perfect matchings, never at the cost of one
+        let prefer = |a, b| if (a, b) == (0, 1) || (a, b) == (4, 5) { 5 } else { 1 };
+        assert_eq!(perfect_matching(&adjacency, &[], prefer).unwrap()[0], Some(1));
+        let heavy = |a, b| if (a, b) == (0, 2) { 100 } else { 1 };
+        assert_eq!(perfect_matching(&adjacency, &[], heavy).unwrap()[2], Some(3));
+    }
+
+    #[test]
+    fn test_agrees_with_solution_space() {
+        // On random sparse graphs every ruled out pair is in no matching and every other pair is in one
+        let c = gen_contestants(10);
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..20 {
+            let mut pairs = vec![];
+            let mut space = SolutionSpace::new(&c);
+            for a in 0..10 {
+                for b in a + 1..10 {
+                    let pair = ContestantPair::new(c[a].clone(), c[b].clone());
+                    if rng.gen_bool(0.35) {
+                        pairs.push((a, b));
+                    } else {
+                        space.rule_out(&pair);
+                    }
+                }
+            }
+            let summary = space.summarize();
+            if summary.count == 0 {
+                continue;
+            }
+            let deductions = deduce(&possibilities_from(&c, &pairs), &[]);
+            for &(a, b) in pairs.iter() {
+                let pair = ContestantPair::new(c[a].clone(), c[b].clone());
+                let ruled_out = deductions.contains(&Deduction::RuledOut(pair.clone(), Reason::NoPerfectMatching));
+                assert_eq!(ruled_out, summary.pair_count(&pair) == 0);
+            }
+            for pair in summary.forced_pairs() {
+                assert!(deductions.iter().any(|d| matches!(d, Deduction::Confirmed(p, _) if p == &pair)));
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::contestant::{Contestant, ContestantPair, ContestantPairs, Player};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Decision {
+    Booth,
+    Ceremony,
+}
+
+/* A piece of known information that pushed the strategy towards its decision */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Constraint<P: Contestant = Player> {
+    ConfirmedMatch(ContestantPair<P>),
+    RuledOutPairs(usize),
+    SavedRound { round_id: u32, num_correct: usize, pairs_left: usize },
+    FewestPossibilities { player: P, possibilities_left: usize },
+    OpeningBook { information: f64 },
+}
+
+impl<P: Contestant> fmt::Display for Constraint<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constraint::ConfirmedMatch(pair) => write!(f, "confirmed match {}", pair),
+            Constraint::RuledOutPairs(count) => write!(f, "{} pairs ruled out", count),
+            Constraint::SavedRound { round_id, num_correct, pairs_left } => {
+                write!(f, "saved round #{} has {} matches left in {} pairs", round_id, num_correct, pairs_left)
+            }
+            Constraint::FewestPossibilities { player, possibilities_left } => {
+                write!(f, "{} has the fewest possibilities left ({})", player, possibilities_left)
+            }
+            Constraint::OpeningBook { information } => write!(f, "opening book move worth {:.3} bits", information),
+        }
+    }
+}
+
+/* How a single pair was rated when the decision was made. Expected information is in bits */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CandidateScore<P: Contestant = Player> {
+    pub pair: ContestantPair<P>,
+    pub probability: f64,
+    pub expected_information: f64,
+}
+
+impl<P: Contestant> fmt::Display for CandidateScore<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -- p {:.3}, {:.3} bits", self.pair, self.probability, self.expected_information)
+    }
+}
+
+/* Why a strategy picked its booth pair or ceremony. For a booth the chosen pair is scored
+   first in candidates, followed by the runners-up. For a ceremony every seated pair is scored. */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation<P: Contestant = Player> {
+    pub decision: Decision,
+    pub chosen: Vec<ContestantPair<P>>,
+    pub constraints: Vec<Constraint<P>>,
+    pub candidates: Vec<CandidateScore<P>>,
+    pub saved_round: Option<u32>,
+}
+
+impl<P: Contestant> Explanation<P> {
+    pub fn new(decision: Decision, chosen: Vec<ContestantPair<P>>) -> Explanation<P> {
+        return Explanation {
+            decision,
+            chosen,
+            constraints: vec![],
+            candidates: vec![],
+            saved_round: None,
+        };
+    }
+}
+
+impl<P: Contestant + Serialize> Explanation<P> {
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Explanations only hold plain data");
+    }
+}
+
+impl<P: Contestant> fmt::Display for Explanation<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:?} decision:\n{}", self.decision, ContestantPairs(&self.chosen))?;
+        if let Some(round_id) = self.saved_round {
+            writeln!(f, "using saved round #{}", round_id)?;
+        }
+        writeln!(f, "constraints:")?;
+        for constraint in self.constraints.iter() {
+            writeln!(f, "  {}", constraint)?;
+        }
+        writeln!(f, "candidates:")?;
+        for candidate in self.candidates.iter() {
+            writeln!(f, "  {}", candidate)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::contestant::ContestantPair;
+    use crate::explanation::{CandidateScore, Constraint, Decision, Explanation};
+    use crate::utils::gen_contestants;
+
+    #[test]
+    fn test_display_and_json() {
+        let c = gen_contestants(4);
+        let pair = ContestantPair::new(c[0].clone(), c[1].clone());
+        let mut explanation = Explanation::new(Decision::Booth, vec![pair.clone()]);
+        explanation.saved_round = Some(2);
+        explanation.constraints.push(Constraint::SavedRound { round_id: 2, num_correct: 1, pairs_left: 2 });
+        explanation.candidates.push(CandidateScore { pair, probability: 0.5, expected_information: 1.0 });
+
+        let printed = explanation.to_string();
+        assert!(printed.contains("using saved round #2"));
+        assert!(printed.contains("saved round #2 has 1 matches left in 2 pairs"));
+
+        let json: serde_json::Value = serde_json::from_str(&explanation.to_json()).unwrap();
+        assert_eq!(json["decision"], "Booth");
+        assert_eq!(json["saved_round"], 2);
+        assert_eq!(json["candidates"][0]["probability"], 0.5);
+        assert_eq!(json["chosen"][0]["a"]["id"], 0);
+    }
+}
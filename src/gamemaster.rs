@@ -1,15 +1,28 @@
 use crate::ContestantPairs;
-use crate::gamestrategy::Feedback;
+use crate::ceremony::{PairConstraints, SeatingError};
+use crate::gamestrategy::{BoothRules, Feedback, GameOutcome, GameRules, GameStrategy};
 use crate::contestant::{ContestantPair, Player};
+use crate::history::{History, Observation};
+use crate::proof::Justified;
+use crate::utils::{contestants_to_pairs, pairs_to_contestants};
+use crate::voting::VotePolicy;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-use log::{debug, error, trace, info};
+use rand::SeedableRng;
+use log::info;
+use std::collections::HashMap;
 
 pub struct GameMaster {
     iterations: i32,
     pub num_matched: i32,
     pub matches: Vec<ContestantPair>,
     max_iterations: i32,
+    booth_matches: Vec<ContestantPair>,
+    booth_rules: BoothRules,
+    vote_policy: Option<VotePolicy>,
+    seating: HashMap<usize, PairConstraints>,
+    history: History,
+    seed: Option<u64>,
 }
 
 impl GameMaster {
@@ -24,23 +37,55 @@ impl GameMaster {
 
         return GameMaster {
             iterations: 0,
+            history: History::new(pairs_to_contestants(&pairs)),
             matches: pairs,
             num_matched: 0,
-            max_iterations
+            max_iterations,
+            booth_matches: vec![],
+            booth_rules: BoothRules::default(),
+            vote_policy: None,
+            seating: HashMap::new(),
+            seed: None,
         };
     }
+
+    /* Pairs the cast off at random, so the same seed always hides the same matching. The seed also
+       orders the cast, drives the house votes and is handed to the strategy in the rules */
+    pub fn with_seed(mut self, seed: u64) -> GameMaster {
+        let mut players = pairs_to_contestants(&self.matches);
+        players.shuffle(&mut StdRng::seed_from_u64(seed));
+        self.matches = contestants_to_pairs(&players);
+        self.seed = Some(seed);
+        return self;
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        return self.seed;
+    }
+
+    pub fn with_booth_rules(mut self, booth_rules: BoothRules) -> GameMaster {
+        self.booth_rules = booth_rules;
+        return self;
+    }
     pub fn contestants(&self) -> Vec<&Player> {
         let mut players: Vec<&Player> = vec![];
 
         for pair in self.matches.iter() {
-            players.push(&pair.get_a());
-            players.push(&pair.get_b());
+            players.push(pair.get_a());
+            players.push(pair.get_b());
         }
-        let mut rng = thread_rng();
-        players.shuffle(&mut rng);
+        players.shuffle(&mut self.week_rng());
         return players;
     }
 
+    /* Random numbers for the house this week, drawn from the seed when there is one */
+    fn week_rng(&self) -> StdRng {
+        return match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(self.iterations as u64)),
+            None => StdRng::from_entropy(),
+        };
+    }
+
     fn is_match(&self, pair: &ContestantPair) -> bool {
         for matched_pair in self.matches.iter() {
             if matched_pair == pair {
@@ -50,13 +95,69 @@ impl GameMaster {
         return false;
     }
 
+    /* Lets the house vote on who goes to the booth instead of the strategy */
+    pub fn with_vote_policy(mut self, vote_policy: VotePolicy) -> GameMaster {
+        self.vote_policy = Some(vote_policy);
+        return self;
+    }
+
+    /* Who has to sit together, and who won't, at the ceremony of the given week. Fails when no
+    ceremony of this cast could keep to the constraints */
+    pub fn with_seating(mut self, week: usize, constraints: PairConstraints) -> Result<GameMaster, SeatingError> {
+        constraints.check(&pairs_to_contestants(&self.matches))?;
+        self.seating.insert(week, constraints);
+        return Ok(self);
+    }
+
+    pub fn seating_constraints(&self) -> PairConstraints {
+        return self.seating.get(&(self.iterations as usize)).cloned().unwrap_or_default();
+    }
+
+    /* The couples the house voted into this week's booths, or None when the strategy gets to choose */
+    pub fn voted_booths(&self) -> Option<Vec<ContestantPair>> {
+        let policy = self.vote_policy.as_ref()?;
+        return Some(policy.vote(&self.history, &self.matches, self.booths_this_week(), &mut self.week_rng()));
+    }
+
+    /* A week with a single booth, whatever the booth rules say */
     pub fn truth_booth(&mut self, guess: ContestantPair) -> Feedback {
-        info!("Attempted match {}", guess);
+        self.iterations += 1;
+        return self.judge_booth(guess);
+    }
 
+    /* How many booths the house gets this week */
+    pub fn booths_this_week(&self) -> usize {
+        return self.booth_rules.booths_in_week(self.iterations as usize);
+    }
+
+    /* Runs this week's booths, ignoring any guesses beyond what the rules allow. Hidden results
+    aren't recorded and don't count towards the matches found */
+    pub fn booth_week(&mut self, guesses: Vec<ContestantPair>) -> Vec<Feedback> {
+        let week = self.iterations as usize;
         self.iterations += 1;
+        let mut results = vec![];
+        for guess in guesses.into_iter().take(self.booth_rules.booths_in_week(week)) {
+            if self.booth_rules.is_hidden(week) {
+                info!("Hidden booth {}", guess);
+                results.push(Feedback::Hidden(guess));
+            } else {
+                results.push(self.judge_booth(guess));
+            }
+        }
+        return results;
+    }
+
+    fn judge_booth(&mut self, guess: ContestantPair) -> Feedback {
+        info!("Attempted match {}", guess);
+
+        self.history.record(Observation::Booth { pair: guess.clone(), is_match: self.is_match(&guess) });
         if self.is_match(&guess) {
             info!("Guessed correctly!\n");
-            self.num_matched += 1;
+            // Sending a known match again doesn't reveal anything new
+            if !self.booth_matches.contains(&guess) {
+                self.booth_matches.push(guess.clone());
+                self.num_matched += 1;
+            }
             return Feedback::Correct(guess);
         } else {
             info!("Wrong guess!\n");
@@ -65,8 +166,14 @@ impl GameMaster {
 
     }
 
-    pub fn ceremony(&mut self, pairs: &Vec<ContestantPair>) -> usize {
+    /* Lights a beam for every perfect match seated. A ceremony that ignores this week's seating
+    is turned away without being recorded */
+    pub fn ceremony(&mut self, pairs: &Vec<ContestantPair>) -> Result<usize, SeatingError> {
         info!("\n{}", &ContestantPairs(pairs));
+        let violations = self.seating_constraints().violations(pairs);
+        if !violations.is_empty() {
+            return Err(SeatingError::Ignored(violations));
+        }
         let mut num_matches = 0;
         for p in pairs.iter() {
             if self.is_match(p) {
@@ -74,10 +181,11 @@ impl GameMaster {
             }
         }
         info!("{} perfect matches are contained!", num_matches);
+        self.history.record(Observation::Ceremony { pairs: pairs.clone(), beams: num_matches });
         if num_matches == self.matches.len() {
             self.num_matched = num_matches as i32;
         }
-        return num_matches;
+        return Ok(num_matches);
     }
 
     pub fn output_stats(&self) {
@@ -99,16 +207,58 @@ impl GameMaster {
     pub fn in_progress(&self) -> bool {
         return (self.iterations < self.max_iterations) && (self.num_matched < (self.matches.len() as i32)-1);
     }
+
+    /* Everything the house has observed so far */
+    pub fn history(&self) -> &History {
+        return &self.history;
+    }
+
+    pub fn rules(&self) -> GameRules {
+        return GameRules {
+            num_contestants: self.matches.len() * 2,
+            max_iterations: self.max_iterations as usize,
+            booths: self.booth_rules.clone(),
+            seed: self.seed,
+        };
+    }
+
+    /* The first decided pair that disagrees with the hidden matching, if any */
+    pub fn audit<'a>(&self, proofs: &'a [Justified]) -> Option<&'a Justified> {
+        return proofs.iter().find(|justified| self.is_match(&justified.pair) != justified.is_match);
+    }
+
+    /* The first hidden match the strategy no longer thinks could be one, if any. Unlike audit this
+       also catches pairs dropped without a proof on record */
+    pub fn discarded(&self, strategy: &dyn GameStrategy) -> Option<&ContestantPair> {
+        return self.matches.iter().find(|pair| !strategy.is_open(pair));
+    }
+
+    /* How the game ended, or None while it is still being played */
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        if self.in_progress() {
+            return None;
+        }
+        let iterations = self.iterations as usize;
+        if self.num_matched >= (self.matches.len() as i32) - 1 {
+            return Some(GameOutcome::Won { iterations });
+        }
+        return Some(GameOutcome::TimedOut { iterations });
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::contestant::ContestantPairs;
-use crate::gamestrategy::Feedback;
+    use crate::ceremony::{PairConstraints, SeatingError};
+    use crate::baseline::ConsistentRandom;
+    use crate::gamestrategy::{BoothRules, Feedback, GameOutcome, GameStrategy};
     use crate::contestant::{ContestantPair, Player};
     use crate::gamemaster::GameMaster;
+    use crate::history::{History, Observation};
+    use crate::proof::{Justified, Proof};
     use crate::utils::{get_matches};
+    use crate::voting::VotePolicy;
+    use std::collections::HashMap;
 
     #[test]
     fn test_game_initialized() {
@@ -124,8 +274,8 @@ use crate::gamestrategy::Feedback;
         // Check that the randomized contestant list is not the same as the one derived from matched pair order
         let randomized: Vec<&Player> = game.contestants();
         let mut num_equal = 0;
-        for i in 0..randomized.len() {
-            if randomized[i].id as usize == i {
+        for (i, player) in randomized.iter().enumerate() {
+            if player.id as usize == i {
                 num_equal += 1;
             }
         }
@@ -147,7 +297,13 @@ use crate::gamestrategy::Feedback;
             iterations: 0,
             matches: vec![matched],
             num_matched: 0,
-            max_iterations: 10
+            max_iterations: 10,
+            booth_matches: vec![],
+            booth_rules: BoothRules::default(),
+            vote_policy: None,
+            seating: HashMap::new(),
+            history: History::new(vec![]),
+            seed: None,
         };
 
         assert_eq!(
@@ -172,19 +328,22 @@ use crate::gamestrategy::Feedback;
             assert_eq!(game.truth_booth(pair.clone()), Feedback::Correct(pair.clone()));
         }
         assert_eq!(game.in_progress(), false);
+        assert_eq!(game.outcome(), Some(GameOutcome::Won { iterations: 6 }));
     }
 
     #[test]
     fn test_in_progress_exceeds_limit() {
         let mut game = GameMaster::initialize_game(12, 5);
         assert_eq!(game.in_progress(), true);
+        assert_eq!(game.outcome(), None);
 
         let cloned = game.matches.to_owned();
-        for i in 0..6 {
-            assert_eq!(game.truth_booth(cloned.get(0).unwrap().clone()), Feedback::Correct(cloned.get(0).unwrap().clone()));
+        for _ in 0..6 {
+            assert_eq!(game.truth_booth(cloned.first().unwrap().clone()), Feedback::Correct(cloned.first().unwrap().clone()));
         }
         
         assert_eq!(game.in_progress(), false);
+        assert_eq!(game.outcome(), Some(GameOutcome::TimedOut { iterations: 6 }));
     }
 
     #[test]
@@ -194,29 +353,133 @@ use crate::gamestrategy::Feedback;
 
         let wrong_match = get_matches(&game.matches, 0, 1).pop().unwrap();
 
-        assert_eq!(game.truth_booth(wrong_match.clone()), Feedback::Wrong(wrong_match));
+        assert_eq!(game.truth_booth(wrong_match.clone()), Feedback::Wrong(wrong_match.clone()));
         assert_eq!(game.iterations, 1);
         assert_eq!(game.num_matched, 0);
 
-        let right_match = game.matches.get(0).unwrap().clone();
-        assert_eq!(game.truth_booth(right_match.clone()), Feedback::Correct(right_match));
+        let right_match = game.matches.first().unwrap().clone();
+        assert_eq!(game.truth_booth(right_match.clone()), Feedback::Correct(right_match.clone()));
         assert_eq!(game.iterations, 2);
         assert_eq!(game.num_matched, 1);
+
+        // A repeated correct guess isn't a new match
+        assert_eq!(game.truth_booth(right_match.clone()), Feedback::Correct(right_match));
+        assert_eq!(game.iterations, 3);
+        assert_eq!(game.num_matched, 1);
+
+        assert_eq!(game.history().observations.len(), 3);
+        assert_eq!(game.history().observations[0], Observation::Booth { pair: wrong_match, is_match: false });
+    }
+
+    #[test]
+    fn test_seeded_matching() {
+        let game = GameMaster::initialize_game(16, 10).with_seed(7);
+        assert_eq!(game.seed(), Some(7));
+        assert_eq!(game.matches, GameMaster::initialize_game(16, 10).with_seed(7).matches);
+        assert_eq!(game.matches.len(), 8);
+        assert_eq!(GameMaster::initialize_game(16, 10).seed(), None);
+        // The cast stays the same, only who is paired with whom changes
+        let mut cast: Vec<i32> = game.contestants().iter().map(|player| player.id).collect();
+        cast.sort();
+        assert_eq!(cast, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_booth_week() {
+        let rules = BoothRules { per_week: 2, sold_weeks: vec![1], hidden_weeks: vec![2] };
+        let mut game = GameMaster::initialize_game(12, 10).with_booth_rules(rules.clone());
+        assert_eq!(game.rules().booths, rules);
+        let right = game.matches[0].clone();
+        let wrong = get_matches(&game.matches, 0, 1).pop().unwrap();
+        let extra = game.matches[1].clone();
+
+        // Only two of the three guesses are judged
+        assert_eq!(game.booths_this_week(), 2);
+        let results = game.booth_week(vec![right.clone(), wrong.clone(), extra]);
+        assert_eq!(results, vec![Feedback::Correct(right.clone()), Feedback::Wrong(wrong.clone())]);
+        assert_eq!(game.get_iterations(), 1);
+        assert_eq!(game.num_matched, 1);
+
+        // A sold week still passes
+        assert_eq!(game.booths_this_week(), 0);
+        assert_eq!(game.booth_week(vec![]), vec![]);
+        assert_eq!(game.get_iterations(), 2);
+
+        let hidden = game.matches[2].clone();
+        assert_eq!(game.booth_week(vec![hidden.clone()]), vec![Feedback::Hidden(hidden)]);
+        assert_eq!(game.num_matched, 1);
+        assert_eq!(game.history().observations.len(), 2);
+    }
+
+    #[test]
+    fn test_voted_booths() {
+        let mut game = GameMaster::initialize_game(12, 10);
+        assert_eq!(game.voted_booths(), None);
+        game = game.with_vote_policy(VotePolicy::Couples { accuracy: 1.0 });
+        let votes = game.voted_booths().unwrap();
+        assert_eq!(votes.len(), 1);
+        assert_eq!(game.booth_week(votes.clone()), vec![Feedback::Correct(votes[0].clone())]);
+    }
+
+    #[test]
+    fn test_seating_constraints() {
+        let game = GameMaster::initialize_game(12, 10);
+        let excluded = game.matches[0].clone();
+        let mut game = game.with_seating(1, PairConstraints::new().with_excluded(excluded.clone())).unwrap();
+        assert_eq!(game.seating_constraints(), PairConstraints::new());
+        game.truth_booth(excluded.clone());
+        assert_eq!(game.seating_constraints().excluded, vec![excluded.clone()]);
+
+        let outsider = ContestantPair::new(Player::new(0), Player::new(20));
+        let game = GameMaster::initialize_game(12, 10);
+        assert_eq!(game.with_seating(0, PairConstraints::new().with_forced(outsider)).err(), Some(SeatingError::UnknownPlayer(Player::new(20))));
+        let game = GameMaster::initialize_game(12, 10);
+        let clash = PairConstraints::new().with_forced(excluded.clone()).with_excluded(excluded.clone());
+        assert_eq!(game.with_seating(0, clash).err(), Some(SeatingError::ForcedAndExcluded(excluded)));
+    }
+
+    #[test]
+    fn test_ceremony_ignores_seating() {
+        let game = GameMaster::initialize_game(12, 10);
+        let matches = game.matches.clone();
+        let mut game = game.with_seating(0, PairConstraints::new().with_excluded(matches[0].clone())).unwrap();
+        assert_eq!(game.ceremony(&matches), Err(SeatingError::Ignored(vec![matches[0].clone()])));
+        assert!(game.history().observations.is_empty());
     }
 
     // This also serves as a test for get_matches()
     #[test]
     fn test_ceremony() {
-        let game = GameMaster::initialize_game(12, 5);
+        let mut game = GameMaster::initialize_game(12, 5);
         let cloned_matches = game.matches.to_owned();
 
         // No correct guesses
         let random_pairs: Vec<ContestantPair> = get_matches(&cloned_matches, 0, 6);
-        assert_eq!(game.ceremony(&random_pairs), 0); 
+        assert_eq!(game.ceremony(&random_pairs), Ok(0));
 
         // Generate 4 random pairs, keep 2 of the correct ones
         let random_pairs: Vec<ContestantPair> = get_matches(&cloned_matches, 2, 4);
-        assert_eq!(game.ceremony(&random_pairs), 2); // should have 2 correct
+        assert_eq!(game.ceremony(&random_pairs), Ok(2)); // should have 2 correct
+        assert_eq!(game.history().observations[1], Observation::Ceremony { pairs: random_pairs, beams: 2 });
     }
 
+    #[test]
+    fn test_audit() {
+        let game = GameMaster::initialize_game(6, 5);
+        let right = Justified { pair: game.matches[0].clone(), is_match: true, proof: Proof::Booth { observation: 0 } };
+        let wrong = Justified { pair: ContestantPair::new(Player::new(0), Player::new(2)), is_match: false, proof: Proof::NoBeams { observation: 1 } };
+        assert_eq!(game.audit(&[right.clone(), wrong.clone()]), None);
+        let unsound = Justified { is_match: false, ..right.clone() };
+        assert_eq!(game.audit(&[right, unsound.clone(), wrong]), Some(&unsound));
+    }
+
+    #[test]
+    fn test_discarded() {
+        let game = GameMaster::initialize_game(6, 5);
+        let mut strategy = ConsistentRandom::initialize(game.contestants());
+        strategy.booth_feedback(Feedback::Correct(game.matches[0].clone()));
+        assert_eq!(game.discarded(&strategy), None);
+        strategy.booth_feedback(Feedback::Wrong(game.matches[1].clone()));
+        assert_eq!(game.discarded(&strategy), Some(&game.matches[1]));
+    }
 }
@@ -1,14 +1,127 @@
-use crate::contestant::{ContestantPair};
+use crate::ceremony::PairConstraints;
+use crate::contestant::{Contestant, ContestantPair, Player};
+use crate::explanation::Explanation;
+use crate::proof::Justified;
 
 #[derive(Debug, PartialEq)]
-pub enum Feedback {
-    Correct(ContestantPair),
-    Wrong(ContestantPair)
+pub enum Feedback<P: Contestant = Player> {
+    Correct(ContestantPair<P>),
+    Wrong(ContestantPair<P>),
+    // The contestants saw the result but the house didn't
+    Hidden(ContestantPair<P>),
 }
 
-pub trait GameStrategy {
-    fn send_to_booth(&mut self) -> ContestantPair;
-    fn booth_feedback(&mut self, feedback: Feedback);
-    fn ceremony_pairs(&mut self) -> Vec<ContestantPair>;
-    fn ceremony_feedback(&mut self, num_right: usize, guess: Vec<ContestantPair>);
-}
\ No newline at end of file
+/* How many truth booths a season runs each week, and which of them the house doesn't get to use */
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoothRules {
+    pub per_week: usize,
+    // Weeks whose booths were sold or skipped, counting from zero
+    pub sold_weeks: Vec<usize>,
+    // Weeks whose booth results were kept from the house
+    pub hidden_weeks: Vec<usize>,
+}
+
+impl Default for BoothRules {
+    fn default() -> Self {
+        return BoothRules { per_week: 1, sold_weeks: vec![], hidden_weeks: vec![] };
+    }
+}
+
+impl BoothRules {
+    pub fn booths_in_week(&self, week: usize) -> usize {
+        if self.sold_weeks.contains(&week) {
+            return 0;
+        }
+        return self.per_week;
+    }
+
+    pub fn is_hidden(&self, week: usize) -> bool {
+        return self.hidden_weeks.contains(&week);
+    }
+}
+
+/* The rules of the game a strategy is about to play */
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRules {
+    pub num_contestants: usize,
+    pub max_iterations: usize,
+    pub booths: BoothRules,
+    // For any random choices the strategy makes, so a seeded game plays out the same every time
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameOutcome {
+    Won { iterations: usize },
+    TimedOut { iterations: usize },
+}
+
+pub trait GameStrategy<P: Contestant = Player> {
+    fn send_to_booth(&mut self) -> ContestantPair<P>;
+    /* Up to count different pairs for the booths of one week. The results come back through booth_feedback
+    one at a time, after all of them were picked */
+    fn send_to_booths(&mut self, count: usize) -> Vec<ContestantPair<P>> {
+        let mut pairs: Vec<ContestantPair<P>> = vec![];
+        for _ in 0..count {
+            let pair = self.send_to_booth();
+            if !pairs.contains(&pair) {
+                pairs.push(pair);
+            }
+        }
+        return pairs;
+    }
+    fn booth_feedback(&mut self, feedback: Feedback<P>);
+    /* The result of a booth somebody else picked. It teaches the same as one of our own by default */
+    fn observe_booth(&mut self, feedback: Feedback<P>) {
+        self.booth_feedback(feedback);
+    }
+    fn ceremony_pairs(&mut self) -> Vec<ContestantPair<P>>;
+    /* A ceremony that respects this week's seating. By default the usual pick is patched up to fit,
+    and left alone when the seating can't be kept at all */
+    fn ceremony_pairs_within(&mut self, seating: &PairConstraints<P>) -> Vec<ContestantPair<P>> {
+        let pairs = self.ceremony_pairs();
+        return seating.enforce(pairs.clone()).unwrap_or(pairs);
+    }
+    fn ceremony_feedback(&mut self, num_right: usize, guess: Vec<ContestantPair<P>>);
+
+    /* Pairs that can never be a match, like two contestants on the same side. Called before the
+    first ceremony, and ignored by strategies that don't keep track of ruled out pairs */
+    fn exclude_pairs(&mut self, _pairs: &[ContestantPair<P>]) {}
+    /* Called once before the first ceremony */
+    fn game_start(&mut self, _contestants: &[&P], _rules: &GameRules) {}
+    /* Called once the game master has ended the game, whether it was won or not */
+    fn game_end(&mut self, _outcome: &GameOutcome) {}
+
+    /* The full pairing the strategy currently thinks is most likely */
+    fn best_guess(&self) -> Vec<ContestantPair<P>>;
+    /* How likely it is that best_guess is the perfect pairing, from 0 to 1 */
+    fn confidence(&self) -> f64 {
+        return 0.0;
+    }
+    fn is_solved(&self) -> bool {
+        return self.confidence() >= 1.0;
+    }
+
+    /* Named numbers describing the strategy's internal state, for logging and plots */
+    fn stats(&self) -> Vec<(String, f64)> {
+        return vec![];
+    }
+    /* Why the strategy made its most recent decision, if it keeps track */
+    fn explain_last_decision(&self) -> Option<Explanation<P>> {
+        return None;
+    }
+    /* Every pair the strategy has confirmed or ruled out along with why, if it keeps track */
+    fn proofs(&self) -> &[Justified<P>] {
+        return &[];
+    }
+    /* Whether the pair could still be a match as far as the strategy knows, confirmed ones included */
+    fn is_open(&self, _pair: &ContestantPair<P>) -> bool {
+        return true;
+    }
+
+    fn output_stats(&self) {
+        for (name, value) in self.stats() {
+            println!("{}: {}", name, value);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::contestant::{Contestant, ContestantPair, ContestantPairs, Player};
+use serde::Serialize;
+use std::fmt;
+
+/* Something the house learned during the game */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Observation<P: Contestant = Player> {
+    Booth { pair: ContestantPair<P>, is_match: bool },
+    Ceremony { pairs: Vec<ContestantPair<P>>, beams: usize },
+}
+
+impl<P: Contestant> fmt::Display for Observation<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Observation::Booth { pair, is_match } => {
+                write!(f, "Truth booth {} -- {}", pair, if *is_match { "perfect match" } else { "no match" })
+            }
+            Observation::Ceremony { pairs, beams } => write!(f, "Ceremony with {} beams\n{}", beams, ContestantPairs(pairs)),
+        }
+    }
+}
+
+/* The cast of a game along with everything observed so far, in the order it happened */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct History<P: Contestant = Player> {
+    pub contestants: Vec<P>,
+    // Pairs that could never be a match, like two contestants on the same side
+    pub excluded: Vec<ContestantPair<P>>,
+    pub observations: Vec<Observation<P>>,
+}
+
+impl<P: Contestant> History<P> {
+    pub fn new(contestants: Vec<P>) -> History<P> {
+        return History { contestants, excluded: vec![], observations: vec![] };
+    }
+
+    pub fn with_excluded(mut self, pairs: Vec<ContestantPair<P>>) -> History<P> {
+        self.excluded.extend(pairs);
+        return self;
+    }
+
+    pub fn record(&mut self, observation: Observation<P>) {
+        self.observations.push(observation);
+    }
+
+    /* A copy of the history with one more observation on the end */
+    pub fn with(&self, observation: Observation<P>) -> History<P> {
+        let mut history = self.clone();
+        history.record(observation);
+        return history;
+    }
+}
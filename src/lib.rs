@@ -1,5 +1,37 @@
-#[cfg(test)]
-mod tests {
+// Explicit returns and `assert_eq!(.., true)` are the house style throughout the solver
+#![allow(clippy::needless_return, clippy::bool_assert_comparison)]
 
+pub mod contestant;
+pub mod gamemaster;
+pub mod gamestrategy;
+pub mod bruteforce;
+pub mod utils;
+pub mod round;
+pub mod roundmanager;
+pub mod baseline;
+pub mod registry;
+pub mod explanation;
+pub mod history;
+pub mod solutionspace;
+pub mod whatif;
+pub mod deduction;
+pub mod proof;
+pub mod matching;
+pub mod ceremony;
+pub mod voting;
+pub mod season;
+pub mod postmortem;
+pub mod optimal;
+pub mod canonical;
+pub mod openingbook;
+pub mod stats;
+pub mod plotting;
+pub mod memory;
+pub mod performance;
+pub mod simulation;
+#[cfg(test)]
+mod properties;
 
-}
+// Modules reach these through the crate root
+use crate::utils::pretty_string_poss;
+use crate::contestant::{ContestantPairs, Players};
@@ -1,143 +1,202 @@
-pub mod contestant;
-pub mod gamemaster;
-pub mod gamestrategy;
-pub mod bruteforce;
-pub mod utils;
-pub mod round;
-pub mod roundmanager;
-
-use std::collections::HashMap;
-use plotters::prelude::*;
-use log::{debug, trace, info};
-use log::LevelFilter;
-use crate::utils::pretty_string_poss;
-use crate::contestant::ContestantPairs;
-use crate::contestant::Players;
-use crate::gamemaster::GameMaster;
-use crate::gamestrategy::GameStrategy;
-use crate::bruteforce::BruteForce;
-
-const OUTPUT_FOLDER: &'static str = "trials/";
-
-fn game(num_players: i32, timeout_rounds: usize) -> u32 {
-
-    let mut game = GameMaster::initialize_game(num_players, timeout_rounds as i32);
-
-    let mut strategy = BruteForce::initialize(game.contestants());
-    info!("-------------------------Correct pairing-------------------------\n{}", ContestantPairs(&game.matches));
-    while game.in_progress() {
-        
-        info!("\\/\\/\\/\\/\\/\\/\\/\\/ROUND {}\\/\\/\\/\\/\\/\\/\\/\\/", game.get_iterations());
-        let guess = strategy.ceremony_pairs();
-        info!("Ceremony guess --- rounds used: {} -----------------------------------------------", &strategy.round_manager.times_round_used);
-
-        let num_correct = game.ceremony(&guess);
-        if !game.in_progress() {
-            break;
-        }
+// Explicit returns and `assert_eq!(.., true)` are the house style throughout the solver
+#![allow(clippy::needless_return, clippy::bool_assert_comparison)]
+
+use are_you_the_one::gamestrategy::BoothRules;
+use are_you_the_one::registry::{StrategyConfig, StrategyRegistry};
+use are_you_the_one::postmortem::{Timeline, MAX_TIMELINE_CAST};
+use are_you_the_one::season::{report, Season};
+use are_you_the_one::optimal::{lower_bound, Comparison, MAX_CONTESTANTS};
+use are_you_the_one::openingbook::{OpeningBook, BOOK_FOLDER, STANDARD_SIZES};
+use are_you_the_one::stats::{Method, StatsCollector};
+use are_you_the_one::plotting::{describe_run, Plot, PlotConfig, Points, Series};
+use are_you_the_one::memory::CountingAllocator;
+use are_you_the_one::performance::{fit_profile, Measure};
+use are_you_the_one::simulation::{game, play};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const OUTPUT_FOLDER: &str = "trials/";
+
+/* Replays one finished game against the exact solution space to see how long the answer was known */
+fn generate_postmortem(num_players: i32, registry: &StrategyRegistry, config: &StrategyConfig, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let game = play(num_players, 500, &BoothRules::default(), registry, config, &mut StatsCollector::new())?;
+    let timeline = Timeline::from_history(game.history())
+        .ok_or_else(|| format!("A {} contestant cast is too large to replay exactly, try {} or fewer", num_players, MAX_TIMELINE_CAST))?;
+    println!("{}", timeline);
 
-        strategy.ceremony_feedback(num_correct, guess);
-        trace!("{}", pretty_string_poss(&strategy.possibilities));
-        info!("Poss remaining: {}, Num correct: {}", strategy.poss_left(), game.num_matched);
+    let output_location = OUTPUT_FOLDER.to_owned() + file_name;
+    timeline.plot(&output_location, &format!("Solution space over a {} game", config))?;
+    println!("Result has been saved to {}", output_location);
+    Ok(())
+}
 
-        let booth_result = game.truth_booth(strategy.send_to_booth());
-        strategy.booth_feedback(booth_result);
+/* Sets a strategy against optimal play on casts small enough to solve exactly */
+fn compare_to_optimal(num_trials: usize, registry: &StrategyRegistry, config: &StrategyConfig) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{} against optimal play", config);
+    let mut collector = StatsCollector::new();
+    for num_contestants in (4..=MAX_CONTESTANTS).step_by(2) {
+        let mut weeks = vec![];
+        for _trial in 0..num_trials {
+            weeks.push(game(num_contestants as i32, 500, &BoothRules::default(), registry, config, &mut collector)? as usize);
+        }
+        println!("{}", Comparison { bound: lower_bound(num_contestants), weeks });
     }
-    game.output_stats();
-    strategy.output_stats();
-    return game.get_iterations() as u32;
+    Ok(())
 }
 
-fn generate_normal_game_distribution(num_trials: usize, file_name: &str) -> Result<(), Box<dyn std::error::Error>>{
-    let mut trial = 0;
-    let mut frequency_map: HashMap<u32, u32> = HashMap::new();
-    while trial < num_trials {
-        trial += 1;
-        let num_rounds = game(16, 500);
-
-        if frequency_map.contains_key(&num_rounds) {
-            let count = frequency_map.get_mut(&num_rounds).unwrap();
-            *count += 1;
-        } else {
-            frequency_map.insert(num_rounds, 1);
+/* Plays every strategy on a 16 contestant cast and writes the records, returning where the CSV went */
+fn simulate_normal_games(num_trials: usize, registry: &StrategyRegistry, strategies: &[StrategyConfig], stem: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut collector = StatsCollector::new();
+    for config in strategies.iter() {
+        for _trial in 0..num_trials {
+            game(16, 500, &BoothRules::default(), registry, config, &mut collector)?;
         }
     }
-    let output_location = OUTPUT_FOLDER.to_owned() + file_name;
-    let root = BitMapBackend::new(&output_location, (640, 480)).into_drawing_area();
-
-    root.fill(&WHITE)?;
-
-    let mut chart = ChartBuilder::on(&root)
-        .x_label_area_size(35 as u32)
-        .y_label_area_size(40 as u32)
-        .margin(5 as u32)
-        .caption("Are You The One Distribution of Rounds To Win", ("sans-serif", 25 as f32))
-        .build_cartesian_2d((0u32..25u32).into_segmented(), 0u32..1000u32)?;
-
-    chart
-        .configure_mesh()
-        .disable_x_mesh()
-        .bold_line_style(&WHITE.mix(0.3))
-        .y_desc("Count")
-        .x_desc("Rounds to win")
-        .axis_desc_style(("sans-serif", 15 as i32))
-        .draw()?;
-
-    chart.draw_series(
-        Histogram::vertical(&chart)
-            .style(RED.mix(0.5).filled())
-            .data(frequency_map.iter().map(|(rounds, count)| (*rounds, *count))),
-    )?;
-
-    // To avoid the IO failure being ignored silently, we manually call the present function
-    root.present().expect("Unable to write result to file, please make sure 'plotters-doc-data' dir exists under current dir");
-    println!("Result has been saved to {}", output_location);
+    for aggregate in collector.aggregate() {
+        println!("{}", aggregate);
+    }
+    let records_stem = OUTPUT_FOLDER.to_owned() + stem;
+    collector.write_records(&records_stem)?;
+    println!("Records have been saved to {}.csv and {}.jsonl", records_stem, records_stem);
+    return Ok(records_stem + ".csv");
+}
 
-    Ok(())
+fn generate_normal_game_distribution(num_trials: usize, registry: &StrategyRegistry, strategies: &[StrategyConfig], file_name: &str) -> Result<(), Box<dyn std::error::Error>>{
+    let records = simulate_normal_games(num_trials, registry, strategies, stem(file_name))?;
+    plot_records("distribution", &records, file_name)?;
+    plot_records("cdf", &records, &with_suffix(file_name, "_cdf"))?;
+    return plot_records("box", &records, &with_suffix(file_name, "_box"));
 }
 
-fn generate_running_time_plot(max_pairs: i32, step_size: usize, iterations: usize, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let mut results: Vec<(u32, u32)> = vec![]; // instead is (contestant size, 
-    
-    for _i in 0..iterations {
-        for num_pairs in (4..max_pairs).step_by(step_size) {
-            let num_rounds = game(num_pairs, 500);
-            results.push((2 * num_pairs as u32, num_rounds));
+/* Plays every strategy over a range of cast sizes and plots how long a ceremony and a booth
+   decision take and how much memory a game needs, each with the growth fitted to it */
+fn generate_running_time_plot(max_pairs: i32, step_size: usize, iterations: usize, registry: &StrategyRegistry, strategies: &[StrategyConfig], file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut collector = StatsCollector::new();
+
+    for config in strategies.iter() {
+        for _i in 0..iterations {
+            for num_pairs in (4..max_pairs).step_by(step_size) {
+                game(num_pairs, 500, &BoothRules::default(), registry, config, &mut collector)?;
+            }
         }
     }
-        
+    let records_stem = OUTPUT_FOLDER.to_owned() + stem(file_name);
+    collector.write_records(&records_stem)?;
+    println!("Records have been saved to {}.csv and {}.jsonl", records_stem, records_stem);
+    for (kind, measure) in [("ceremony_time", Measure::Decision(Method::Ceremony)), ("booth_time", Measure::Decision(Method::Booth)), ("memory", Measure::Memory)] {
+        for (strategy, fit) in fit_profile(&collector, measure) {
+            println!("{} for {}: {}", measure.describe(), strategy, fit);
+        }
+        plot_records(kind, &(records_stem.clone() + ".csv"), &with_suffix(file_name, &format!("_{}", kind)))?;
+    }
+    return plot_records("rounds_by_size", &(records_stem + ".csv"), &with_suffix(file_name, "_rounds"));
+}
 
+/* Draws one kind of plot from a records file, so it can be redrawn without simulating. The
+   image is PNG or SVG by its extension */
+fn plot_records(kind: &str, records: &str, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let collector = StatsCollector::load(records)?;
+    let run = describe_run(&collector);
+    let rounds = Series::by_strategy(&collector, |game| game.rounds as f64);
+    let (plot, config) = match kind {
+        "distribution" => (Plot::Histogram(rounds), PlotConfig::new(&format!("Rounds to win: {}", run), "Rounds to win", "Games")),
+        "cdf" => (Plot::Cdf(rounds), PlotConfig::new(&format!("Games won by each round: {}", run), "Rounds to win", "Share of games")),
+        "box" => (Plot::BoxPlot(rounds), PlotConfig::new(&format!("Rounds to win: {}", run), "Strategy", "Rounds to win")),
+        "rounds_by_size" => (
+            Plot::Scatter(Points::by_strategy(&collector, |game| (game.num_contestants as f64, game.rounds as f64))),
+            PlotConfig::new(&format!("Rounds to win by cast size: {}", run), "Number of contestants", "Rounds to win"),
+        ),
+        "ceremony_time" | "booth_time" | "memory" => {
+            let measure = match kind {
+                "ceremony_time" => Measure::Decision(Method::Ceremony),
+                "booth_time" => Measure::Decision(Method::Booth),
+                _ => Measure::Memory,
+            };
+            (Plot::Fitted(measure.points(&collector)), PlotConfig::new(&format!("{}: {}", measure.describe(), run), "Number of contestants", measure.describe()))
+        }
+        _ => return Err(format!("unknown plot '{}', expected distribution, cdf, box, rounds_by_size, ceremony_time, booth_time or memory", kind).into()),
+    };
     let output_location = OUTPUT_FOLDER.to_owned() + file_name;
-    let root = BitMapBackend::new(&output_location, (640, 480)).into_drawing_area();
-
-    root.fill(&WHITE)?;
-
-    let mut scatter_ctx = ChartBuilder::on(&root)
-        .x_label_area_size(40i32)
-        .y_label_area_size(40i32)
-        .build_cartesian_2d(0f64..200f64, 0f64..300f64)?;
-
-    scatter_ctx
-        .configure_mesh()
-        .disable_x_mesh()
-        .disable_y_mesh()
-        .y_desc("Rounds to win")
-        .x_desc("Number of Contestants")
-        .axis_desc_style(("sans-serif", 15 as i32))
-        .draw()?;
-    
-    scatter_ctx.draw_series(results.iter().map(|(x, y)| Circle::new((*x as f64, *y as f64), 2 as f32, RED.filled())))?;
-
-    // To avoid the IO failure being ignored silently, we manually call the present function
-    root.present().expect("Unable to write result to file, please make sure 'plotters-doc-data' dir exists under current dir");
+    plot.render(&config, &output_location)?;
     println!("Result has been saved to {}", output_location);
-
     Ok(())
 }
 
-fn main() {
-    // simple_logging::log_to_file("test.log", LevelFilter::Info);
-    // game(16, 500);
-    generate_normal_game_distribution(2000, "normal_game_bruteforce_round_optimization.png");
-    generate_running_time_plot(100, 2, 4, "running_time_bruteforce_round_optimization.png");
+/* The file name without its extension, which records written next to a plot are named after */
+fn stem(file_name: &str) -> &str {
+    return file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
+}
+
+/* "runs.png" with "_cdf" becomes "runs_cdf.png" */
+fn with_suffix(file_name: &str, suffix: &str) -> String {
+    return match file_name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}{}.{}", stem, suffix, extension),
+        None => format!("{}{}", file_name, suffix),
+    };
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // simple_logging::log_to_file("test.log", log::LevelFilter::Info);
+    let registry = StrategyRegistry::with_defaults();
+
+    // `season <file>...` replays aired seasons instead of simulating games
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("season") {
+        for path in args[1..].iter() {
+            let season = Season::parse(&std::fs::read_to_string(path)?)?;
+            println!("{}\n{}", season.name.as_deref().unwrap_or(path), report(&season));
+        }
+        return Ok(());
+    }
+    // `plot <kind> <records> <image>` redraws a plot from saved records
+    if args.first().map(|arg| arg.as_str()) == Some("plot") {
+        return match (args.get(1), args.get(2), args.get(3)) {
+            (Some(kind), Some(records), Some(image)) => plot_records(kind, records, image),
+            _ => Err("usage: plot distribution|cdf|box|rounds_by_size|ceremony_time|booth_time|memory <records.csv|records.jsonl> <image.png|image.svg>".into()),
+        };
+    }
+    // `book [size]...` generates opening books, to be played with `bruteforce:opening_book=<file>`
+    if args.first().map(|arg| arg.as_str()) == Some("book") {
+        let mut sizes = args[1..].iter().map(|size| size.parse()).collect::<Result<Vec<usize>, _>>()?;
+        if sizes.is_empty() {
+            sizes = STANDARD_SIZES.to_vec();
+        }
+        std::fs::create_dir_all(BOOK_FOLDER)?;
+        for size in sizes {
+            let book = OpeningBook::generate(size);
+            let location = BOOK_FOLDER.to_owned() + &OpeningBook::file_name(size);
+            book.save(&location)?;
+            println!("{}\nResult has been saved to {}", book, location);
+        }
+        return Ok(());
+    }
+
+    // Strategies can be picked on the command line, e.g. `bruteforce:round_optimization=false random`
+    let mut strategies = vec![];
+    for spec in std::env::args().skip(1) {
+        strategies.push(StrategyConfig::parse(&spec)?);
+    }
+    if strategies.is_empty() {
+        strategies = vec![
+            StrategyConfig::new("bruteforce"),
+            StrategyConfig::new("random"),
+            StrategyConfig::new("consistent_random"),
+            StrategyConfig::new("beam_heuristic"),
+        ];
+    }
+    for config in strategies.iter() {
+        if !registry.contains(&config.name) {
+            return Err(format!("unknown strategy '{}', expected one of {:?}", config.name, registry.names()).into());
+        }
+    }
+
+    let bruteforce = StrategyConfig::new("bruteforce");
+    // game(16, 500, &BoothRules::default(), &registry, &bruteforce)?;
+    generate_normal_game_distribution(2000, &registry, std::slice::from_ref(&bruteforce), "normal_game_bruteforce_round_optimization.png")?;
+    generate_normal_game_distribution(2000, &registry, &strategies, "normal_game_strategies.png")?;
+    compare_to_optimal(200, &registry, &bruteforce)?;
+    generate_postmortem(16, &registry, &bruteforce, "postmortem_bruteforce.png")?;
+    generate_running_time_plot(100, 2, 4, &registry, &strategies, "running_time_strategies.png")?;
+    Ok(())
 }
@@ -0,0 +1,588 @@
+/* Maximum weight matching on general graphs (Edmonds' blossom algorithm with Galil's
+   primal-dual bookkeeping, O(n^3)). Vertices are 0..num_vertices and each edge is
+   (a, b, weight). Endpoints are numbered 2k and 2k + 1 for edge k, so that p ^ 1 is
+   the other end of the same edge. With max_cardinality the heaviest of the largest
+   matchings is returned, which is how perfect matchings are found. */
+pub fn max_weight_matching(num_vertices: usize, edges: &[(usize, usize, i64)], max_cardinality: bool) -> Vec<Option<usize>> {
+    if edges.is_empty() {
+        return vec![None; num_vertices];
+    }
+    let mut matcher = Matcher::new(num_vertices, edges);
+    matcher.run(max_cardinality);
+    return matcher
+        .mate
+        .iter()
+        .map(|&p| if p == NONE { None } else { Some(matcher.endpoint[p as usize]) })
+        .collect();
+}
+
+const NONE: isize = -1;
+
+struct Matcher<'a> {
+    edges: &'a [(usize, usize, i64)],
+    n: usize,
+    endpoint: Vec<usize>,
+    neighbend: Vec<Vec<usize>>,
+    // Endpoint that each vertex is matched through
+    mate: Vec<isize>,
+    // 0 free, 1 S (outer), 2 T (inner), for vertices and top level blossoms
+    label: Vec<u8>,
+    labelend: Vec<isize>,
+    inblossom: Vec<usize>,
+    blossomparent: Vec<isize>,
+    blossomchilds: Vec<Vec<usize>>,
+    blossombase: Vec<isize>,
+    blossomendps: Vec<Vec<usize>>,
+    bestedge: Vec<isize>,
+    blossombestedges: Vec<Option<Vec<usize>>>,
+    unusedblossoms: Vec<usize>,
+    dualvar: Vec<i64>,
+    allowedge: Vec<bool>,
+    queue: Vec<usize>,
+}
+
+/* Python style index into a cyclic list, where -1 is the last entry */
+fn cyclic(list: &[usize], i: isize) -> usize {
+    return list[i.rem_euclid(list.len() as isize) as usize];
+}
+
+impl<'a> Matcher<'a> {
+    fn new(n: usize, edges: &'a [(usize, usize, i64)]) -> Matcher<'a> {
+        let max_weight = edges.iter().map(|e| e.2).max().unwrap().max(0);
+        let endpoint = (0..2 * edges.len()).map(|p| if p % 2 == 0 { edges[p / 2].0 } else { edges[p / 2].1 }).collect();
+        let mut neighbend = vec![vec![]; n];
+        for (k, &(a, b, _)) in edges.iter().enumerate() {
+            neighbend[a].push(2 * k + 1);
+            neighbend[b].push(2 * k);
+        }
+        let mut dualvar = vec![max_weight; n];
+        dualvar.extend(vec![0; n]);
+        return Matcher {
+            edges,
+            n,
+            endpoint,
+            neighbend,
+            mate: vec![NONE; n],
+            label: vec![0; 2 * n],
+            labelend: vec![NONE; 2 * n],
+            inblossom: (0..n).collect(),
+            blossomparent: vec![NONE; 2 * n],
+            blossomchilds: vec![vec![]; 2 * n],
+            blossombase: (0..n as isize).chain(std::iter::repeat_n(NONE, n)).collect(),
+            blossomendps: vec![vec![]; 2 * n],
+            bestedge: vec![NONE; 2 * n],
+            blossombestedges: vec![None; 2 * n],
+            unusedblossoms: (n..2 * n).collect(),
+            dualvar,
+            allowedge: vec![false; edges.len()],
+            queue: vec![],
+        };
+    }
+
+    fn slack(&self, k: usize) -> i64 {
+        let (a, b, weight) = self.edges[k];
+        return self.dualvar[a] + self.dualvar[b] - 2 * weight;
+    }
+
+    fn leaves(&self, b: usize) -> Vec<usize> {
+        if b < self.n {
+            return vec![b];
+        }
+        let mut leaves = vec![];
+        for &child in self.blossomchilds[b].iter() {
+            leaves.extend(self.leaves(child));
+        }
+        return leaves;
+    }
+
+    fn assign_label(&mut self, w: usize, t: u8, p: isize) {
+        let b = self.inblossom[w];
+        self.label[w] = t;
+        self.label[b] = t;
+        self.labelend[w] = p;
+        self.labelend[b] = p;
+        self.bestedge[w] = NONE;
+        self.bestedge[b] = NONE;
+        if t == 1 {
+            let leaves = self.leaves(b);
+            self.queue.extend(leaves);
+        } else if t == 2 {
+            let base = self.blossombase[b] as usize;
+            let mate = self.mate[base];
+            self.assign_label(self.endpoint[mate as usize], 1, mate ^ 1);
+        }
+    }
+
+    /* Traces back from v and w to find a new blossom's base, or NONE for an augmenting path */
+    fn scan_blossom(&mut self, v: usize, w: usize) -> isize {
+        let mut path = vec![];
+        let mut base = NONE;
+        let mut v = v as isize;
+        let mut w = w as isize;
+        while v != NONE || w != NONE {
+            let mut b = self.inblossom[v as usize];
+            if self.label[b] & 4 != 0 {
+                base = self.blossombase[b];
+                break;
+            }
+            path.push(b);
+            self.label[b] = 5;
+            if self.labelend[b] == NONE {
+                v = NONE;
+            } else {
+                v = self.endpoint[self.labelend[b] as usize] as isize;
+                b = self.inblossom[v as usize];
+                v = self.endpoint[self.labelend[b] as usize] as isize;
+            }
+            if w != NONE {
+                std::mem::swap(&mut v, &mut w);
+            }
+        }
+        for b in path {
+            self.label[b] = 1;
+        }
+        return base;
+    }
+
+    fn add_blossom(&mut self, base: usize, k: usize) {
+        let (mut v, mut w, _) = self.edges[k];
+        let bb = self.inblossom[base];
+        let mut bv = self.inblossom[v];
+        let mut bw = self.inblossom[w];
+        let b = self.unusedblossoms.pop().unwrap();
+        self.blossombase[b] = base as isize;
+        self.blossomparent[b] = NONE;
+        self.blossomparent[bb] = b as isize;
+
+        let mut path = vec![];
+        let mut endps = vec![];
+        while bv != bb {
+            self.blossomparent[bv] = b as isize;
+            path.push(bv);
+            endps.push(self.labelend[bv] as usize);
+            v = self.endpoint[self.labelend[bv] as usize];
+            bv = self.inblossom[v];
+        }
+        path.push(bb);
+        path.reverse();
+        endps.reverse();
+        endps.push(2 * k);
+        while bw != bb {
+            self.blossomparent[bw] = b as isize;
+            path.push(bw);
+            endps.push((self.labelend[bw] ^ 1) as usize);
+            w = self.endpoint[self.labelend[bw] as usize];
+            bw = self.inblossom[w];
+        }
+        self.blossomchilds[b] = path.clone();
+        self.blossomendps[b] = endps;
+
+        self.label[b] = 1;
+        self.labelend[b] = self.labelend[bb];
+        self.dualvar[b] = 0;
+        for leaf in self.leaves(b) {
+            if self.label[self.inblossom[leaf]] == 2 {
+                self.queue.push(leaf);
+            }
+            self.inblossom[leaf] = b;
+        }
+
+        // Keep the least slack edge from the new blossom to every neighbouring S blossom
+        let mut bestedgeto = vec![NONE; 2 * self.n];
+        for &child in path.iter() {
+            let lists: Vec<Vec<usize>> = match self.blossombestedges[child].take() {
+                Some(list) => vec![list],
+                None => self.leaves(child).iter().map(|&leaf| self.neighbend[leaf].iter().map(|p| p / 2).collect()).collect(),
+            };
+            for list in lists {
+                for k in list {
+                    // Look from the new blossom to the far end of the edge
+                    let (i, j, _) = self.edges[k];
+                    let j = if self.inblossom[j] == b { i } else { j };
+                    let bj = self.inblossom[j];
+                    if bj != b
+                        && self.label[bj] == 1
+                        && (bestedgeto[bj] == NONE || self.slack(k) < self.slack(bestedgeto[bj] as usize))
+                    {
+                        bestedgeto[bj] = k as isize;
+                    }
+                }
+            }
+            self.bestedge[child] = NONE;
+        }
+        let best: Vec<usize> = bestedgeto.into_iter().filter(|&k| k != NONE).map(|k| k as usize).collect();
+        self.bestedge[b] = NONE;
+        for &k in best.iter() {
+            if self.bestedge[b] == NONE || self.slack(k) < self.slack(self.bestedge[b] as usize) {
+                self.bestedge[b] = k as isize;
+            }
+        }
+        self.blossombestedges[b] = Some(best);
+    }
+
+    fn expand_blossom(&mut self, b: usize, endstage: bool) {
+        for s in self.blossomchilds[b].clone() {
+            self.blossomparent[s] = NONE;
+            if s < self.n {
+                self.inblossom[s] = s;
+            } else if endstage && self.dualvar[s] == 0 {
+                self.expand_blossom(s, endstage);
+            } else {
+                for leaf in self.leaves(s) {
+                    self.inblossom[leaf] = s;
+                }
+            }
+        }
+
+        // A T blossom expanded mid stage needs its children relabelled along the even path
+        if !endstage && self.label[b] == 2 {
+            let childs = self.blossomchilds[b].clone();
+            let endps = self.blossomendps[b].clone();
+            let entrychild = self.inblossom[self.endpoint[(self.labelend[b] ^ 1) as usize]];
+            let mut j = childs.iter().position(|&c| c == entrychild).unwrap() as isize;
+            let (jstep, endptrick): (isize, usize) = if j & 1 == 1 {
+                j -= childs.len() as isize;
+                (1, 0)
+            } else {
+                (-1, 1)
+            };
+            let mut p = self.labelend[b];
+            while j != 0 {
+                self.label[self.endpoint[(p ^ 1) as usize]] = 0;
+                let q = cyclic(&endps, j - endptrick as isize);
+                self.label[self.endpoint[q ^ endptrick ^ 1]] = 0;
+                self.assign_label(self.endpoint[(p ^ 1) as usize], 2, p);
+                self.allowedge[q / 2] = true;
+                j += jstep;
+                p = (cyclic(&endps, j - endptrick as isize) ^ endptrick) as isize;
+                self.allowedge[(p / 2) as usize] = true;
+                j += jstep;
+            }
+            let bv = cyclic(&childs, j);
+            let end = self.endpoint[(p ^ 1) as usize];
+            self.label[end] = 2;
+            self.label[bv] = 2;
+            self.labelend[end] = p;
+            self.labelend[bv] = p;
+            self.bestedge[bv] = NONE;
+            j += jstep;
+            while cyclic(&childs, j) != entrychild {
+                let bv = cyclic(&childs, j);
+                if self.label[bv] == 1 {
+                    j += jstep;
+                    continue;
+                }
+                if let Some(v) = self.leaves(bv).into_iter().find(|&v| self.label[v] != 0) {
+                    self.label[v] = 0;
+                    let mate = self.mate[self.blossombase[bv] as usize];
+                    self.label[self.endpoint[mate as usize]] = 0;
+                    self.assign_label(v, 2, self.labelend[v]);
+                }
+                j += jstep;
+            }
+        }
+        self.label[b] = 0;
+        self.labelend[b] = NONE;
+        self.blossomchilds[b] = vec![];
+        self.blossomendps[b] = vec![];
+        self.blossombase[b] = NONE;
+        self.blossombestedges[b] = None;
+        self.bestedge[b] = NONE;
+        self.unusedblossoms.push(b);
+    }
+
+    /* Swaps matched and unmatched edges along the even path from v to the blossom's base */
+    fn augment_blossom(&mut self, b: usize, v: usize) {
+        let mut t = v;
+        while self.blossomparent[t] != b as isize {
+            t = self.blossomparent[t] as usize;
+        }
+        if t >= self.n {
+            self.augment_blossom(t, v);
+        }
+        let childs = self.blossomchilds[b].clone();
+        let endps = self.blossomendps[b].clone();
+        let i = childs.iter().position(|&c| c == t).unwrap();
+        let mut j = i as isize;
+        let (jstep, endptrick): (isize, usize) = if i & 1 == 1 {
+            j -= childs.len() as isize;
+            (1, 0)
+        } else {
+            (-1, 1)
+        };
+        while j != 0 {
+            j += jstep;
+            let t = cyclic(&childs, j);
+            let p = cyclic(&endps, j - endptrick as isize) ^ endptrick;
+            if t >= self.n {
+                self.augment_blossom(t, self.endpoint[p]);
+            }
+            j += jstep;
+            let t = cyclic(&childs, j);
+            if t >= self.n {
+                self.augment_blossom(t, self.endpoint[p ^ 1]);
+            }
+            self.mate[self.endpoint[p]] = (p ^ 1) as isize;
+            self.mate[self.endpoint[p ^ 1]] = p as isize;
+        }
+        self.blossomchilds[b].rotate_left(i);
+        self.blossomendps[b].rotate_left(i);
+        self.blossombase[b] = self.blossombase[self.blossomchilds[b][0]];
+    }
+
+    fn augment_matching(&mut self, k: usize) {
+        let (v, w, _) = self.edges[k];
+        for (s, p) in [(v, 2 * k + 1), (w, 2 * k)] {
+            let mut s = s;
+            let mut p = p;
+            loop {
+                let bs = self.inblossom[s];
+                if bs >= self.n {
+                    self.augment_blossom(bs, s);
+                }
+                self.mate[s] = p as isize;
+                if self.labelend[bs] == NONE {
+                    break;
+                }
+                let t = self.endpoint[self.labelend[bs] as usize];
+                let bt = self.inblossom[t];
+                s = self.endpoint[self.labelend[bt] as usize];
+                let j = self.endpoint[(self.labelend[bt] ^ 1) as usize];
+                if bt >= self.n {
+                    self.augment_blossom(bt, j);
+                }
+                self.mate[j] = self.labelend[bt];
+                p = (self.labelend[bt] ^ 1) as usize;
+            }
+        }
+    }
+
+    fn run(&mut self, max_cardinality: bool) {
+        let n = self.n;
+        for _ in 0..n {
+            self.label = vec![0; 2 * n];
+            self.bestedge = vec![NONE; 2 * n];
+            for b in n..2 * n {
+                self.blossombestedges[b] = None;
+            }
+            self.allowedge = vec![false; self.edges.len()];
+            self.queue.clear();
+            for v in 0..n {
+                if self.mate[v] == NONE && self.label[self.inblossom[v]] == 0 {
+                    self.assign_label(v, 1, NONE);
+                }
+            }
+
+            let mut augmented = false;
+            loop {
+                while !augmented {
+                    let v = match self.queue.pop() {
+                        Some(v) => v,
+                        None => break,
+                    };
+                    for p in self.neighbend[v].clone() {
+                        let k = p / 2;
+                        let w = self.endpoint[p];
+                        if self.inblossom[v] == self.inblossom[w] {
+                            continue;
+                        }
+                        let mut kslack = 0;
+                        if !self.allowedge[k] {
+                            kslack = self.slack(k);
+                            if kslack <= 0 {
+                                self.allowedge[k] = true;
+                            }
+                        }
+                        if self.allowedge[k] {
+                            if self.label[self.inblossom[w]] == 0 {
+                                self.assign_label(w, 2, (p ^ 1) as isize);
+                            } else if self.label[self.inblossom[w]] == 1 {
+                                let base = self.scan_blossom(v, w);
+                                if base != NONE {
+                                    self.add_blossom(base as usize, k);
+                                } else {
+                                    self.augment_matching(k);
+                                    augmented = true;
+                                    break;
+                                }
+                            } else if self.label[w] == 0 {
+                                self.label[w] = 2;
+                                self.labelend[w] = (p ^ 1) as isize;
+                            }
+                        } else if self.label[self.inblossom[w]] == 1 {
+                            let b = self.inblossom[v];
+                            if self.bestedge[b] == NONE || kslack < self.slack(self.bestedge[b] as usize) {
+                                self.bestedge[b] = k as isize;
+                            }
+                        } else if self.label[w] == 0 && (self.bestedge[w] == NONE || kslack < self.slack(self.bestedge[w] as usize)) {
+                            self.bestedge[w] = k as isize;
+                        }
+                    }
+                }
+                if augmented {
+                    break;
+                }
+
+                // No augmenting path with the current duals, so work out how far they can move
+                let mut deltatype = 0;
+                let mut delta = 0;
+                let mut deltaedge = 0;
+                let mut deltablossom = 0;
+                if !max_cardinality {
+                    deltatype = 1;
+                    delta = *self.dualvar[..n].iter().min().unwrap();
+                }
+                for v in 0..n {
+                    if self.label[self.inblossom[v]] == 0 && self.bestedge[v] != NONE {
+                        let d = self.slack(self.bestedge[v] as usize);
+                        if deltatype == 0 || d < delta {
+                            delta = d;
+                            deltatype = 2;
+                            deltaedge = self.bestedge[v] as usize;
+                        }
+                    }
+                }
+                for b in 0..2 * n {
+                    if self.blossomparent[b] == NONE && self.label[b] == 1 && self.bestedge[b] != NONE {
+                        let d = self.slack(self.bestedge[b] as usize) / 2;
+                        if deltatype == 0 || d < delta {
+                            delta = d;
+                            deltatype = 3;
+                            deltaedge = self.bestedge[b] as usize;
+                        }
+                    }
+                }
+                for b in n..2 * n {
+                    if self.blossombase[b] != NONE
+                        && self.blossomparent[b] == NONE
+                        && self.label[b] == 2
+                        && (deltatype == 0 || self.dualvar[b] < delta)
+                    {
+                        delta = self.dualvar[b];
+                        deltatype = 4;
+                        deltablossom = b;
+                    }
+                }
+                if deltatype == 0 {
+                    // Only reachable with max_cardinality: no more augmenting paths at all
+                    deltatype = 1;
+                    delta = (*self.dualvar[..n].iter().min().unwrap()).max(0);
+                }
+
+                for v in 0..n {
+                    match self.label[self.inblossom[v]] {
+                        1 => self.dualvar[v] -= delta,
+                        2 => self.dualvar[v] += delta,
+                        _ => (),
+                    }
+                }
+                for b in n..2 * n {
+                    if self.blossombase[b] != NONE && self.blossomparent[b] == NONE {
+                        match self.label[b] {
+                            1 => self.dualvar[b] += delta,
+                            2 => self.dualvar[b] -= delta,
+                            _ => (),
+                        }
+                    }
+                }
+
+                match deltatype {
+                    1 => break,
+                    2 => {
+                        self.allowedge[deltaedge] = true;
+                        let (mut i, j, _) = self.edges[deltaedge];
+                        if self.label[self.inblossom[i]] == 0 {
+                            i = j;
+                        }
+                        self.queue.push(i);
+                    }
+                    3 => {
+                        self.allowedge[deltaedge] = true;
+                        self.queue.push(self.edges[deltaedge].0);
+                    }
+                    _ => self.expand_blossom(deltablossom, false),
+                }
+            }
+            if !augmented {
+                break;
+            }
+            for b in n..2 * n {
+                if self.blossomparent[b] == NONE && self.blossombase[b] != NONE && self.label[b] == 1 && self.dualvar[b] == 0 {
+                    self.expand_blossom(b, true);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matching::max_weight_matching;
+    use rand::Rng;
+
+    /* The weight of the heaviest perfect matching, found by trying them all */
+    fn best_perfect(n: usize, weights: &[Vec<Option<i64>>], used: &mut Vec<bool>) -> Option<i64> {
+        let first = match (0..n).find(|&i| !used[i]) {
+            Some(first) => first,
+            None => return Some(0),
+        };
+        used[first] = true;
+        let mut best = None;
+        for other in first + 1..n {
+            if let (false, Some(weight)) = (used[other], weights[first][other]) {
+                used[other] = true;
+                if let Some(rest) = best_perfect(n, weights, used) {
+                    best = best.max(Some(rest + weight));
+                }
+                used[other] = false;
+            }
+        }
+        used[first] = false;
+        return best;
+    }
+
+    #[test]
+    fn test_small_graphs() {
+        assert_eq!(max_weight_matching(2, &[(0, 1, 1)], false), vec![Some(1), Some(0)]);
+        // The heavier middle edge loses to the two outer ones together
+        assert_eq!(max_weight_matching(4, &[(0, 1, 5), (1, 2, 8), (2, 3, 5)], false), vec![Some(1), Some(0), Some(3), Some(2)]);
+        // Without max_cardinality a lone heavy edge wins
+        assert_eq!(max_weight_matching(4, &[(0, 1, 2), (1, 2, 10), (2, 3, 2)], false), vec![None, Some(2), Some(1), None]);
+        assert_eq!(max_weight_matching(4, &[(0, 1, 2), (1, 2, 10), (2, 3, 2)], true), vec![Some(1), Some(0), Some(3), Some(2)]);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..300 {
+            let n = 2 * rng.gen_range(1..=5);
+            let mut edges = vec![];
+            for a in 0..n {
+                for b in a + 1..n {
+                    if rng.gen_bool(0.6) {
+                        edges.push((a, b, rng.gen_range(-20..40)));
+                    }
+                }
+            }
+            let mut weights = vec![vec![None; n]; n];
+            for &(a, b, weight) in edges.iter() {
+                weights[a][b] = Some(weight);
+                weights[b][a] = Some(weight);
+            }
+            let expected = best_perfect(n, &weights, &mut vec![false; n]);
+            let mate = max_weight_matching(n, &edges, true);
+            match expected {
+                Some(best) => {
+                    let mut total = 0;
+                    for a in 0..n {
+                        let b = mate[a].expect("A perfect matching exists");
+                        assert_eq!(mate[b], Some(a));
+                        total += weights[a][b].unwrap();
+                    }
+                    assert_eq!(total, 2 * best);
+                }
+                None => assert!(mate.iter().any(|m| m.is_none())),
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+// Held while the peak is reset, and for as long as a measurement can't have it reset underneath
+static RESETTING: Mutex<()> = Mutex::new(());
+
+/* The system allocator, keeping count of the bytes held so games can report how much memory they
+   needed. Installed as the global allocator in main */
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn grow(size: usize) {
+        let now = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(now, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            CountingAllocator::grow(layout.size());
+        }
+        return ptr;
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            CountingAllocator::grow(layout.size());
+        }
+        return ptr;
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                CountingAllocator::grow(new_size - layout.size());
+            } else {
+                ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+        }
+        return new_ptr;
+    }
+}
+
+/* Bytes held right now, across every thread */
+pub fn allocated() -> usize {
+    return ALLOCATED.load(Ordering::Relaxed);
+}
+
+/* Starts measuring a new peak from what is held now, which is handed back as the baseline */
+pub fn reset_peak() -> usize {
+    let _resetting = RESETTING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    return store_peak();
+}
+
+fn store_peak() -> usize {
+    let now = allocated();
+    PEAK.store(now, Ordering::Relaxed);
+    return now;
+}
+
+/* The most held at once since the last reset */
+pub fn peak() -> usize {
+    return PEAK.load(Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::{peak, store_peak, CountingAllocator, RESETTING};
+
+    // Only the binary installs it otherwise, so library users keep their own allocator
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn test_peak_counts_allocations() {
+        // Other tests allocate and free at the same time, so leave them some room. Games starting
+        // meanwhile would reset the peak, so they wait until it has been read
+        let _measuring = RESETTING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let baseline = store_peak();
+        let block: Vec<u8> = vec![1; 1 << 24];
+        drop(block);
+        assert!(peak() >= baseline + (1 << 23));
+    }
+}
@@ -0,0 +1,286 @@
+use crate::canonical::{canonicalize, CanonicalForm};
+use crate::ceremony::{design_with_filler, independent_beams, most_likely_ceremony, BeamTarget, PairConstraints};
+use crate::contestant::{Contestant, ContestantPair, Player};
+use crate::history::{History, Observation};
+use crate::solutionspace::{SolutionSpace, SpaceSummary};
+use crate::utils::{binary_entropy, contestants_to_pairs, gen_contestants};
+use crate::whatif::{analyze_action_in, Action};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Where `book` writes the books it generates
+pub const BOOK_FOLDER: &str = "books/";
+// Cast sizes the show has used, which `book` generates when given none
+pub const STANDARD_SIZES: [usize; 2] = [16, 20];
+// Hill climbing passes for each second week ceremony
+const DESIGN_PASSES: usize = 10;
+// States with at most this many matchings have their second ceremony designed on exact beam counts
+const EXACT_DESIGN_LIMIT: u64 = 5_000;
+
+/* A move with contestants given by their canonical labels in the state it is played from */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BookMove {
+    Ceremony(Vec<(usize, usize)>),
+    Booth((usize, usize)),
+}
+
+/* What to play from one state, and the bits it is expected to reveal there */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookEntry {
+    pub state: CanonicalForm,
+    pub action: BookMove,
+    pub information: f64,
+}
+
+/* Best moves for the states every game of a cast size starts from. States are stored in canonical
+   form, so a game finds its entry however its contestants happen to be numbered. The book covers
+   the opening ceremony, the booth for each beam count it could light, and the second ceremony for
+   each result of that booth */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpeningBook {
+    pub num_contestants: usize,
+    pub entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    /* Works through the exact solution space three times, so 20 contestants take a while (each
+    pass goes over about 650 million matchings) */
+    pub fn generate(num_contestants: usize) -> OpeningBook {
+        let contestants = gen_contestants(num_contestants);
+        let start = History::new(contestants.clone());
+        let space = SolutionSpace::new(&contestants);
+        let most_beams = num_contestants / 2;
+        let count_beams = |seats: &[(usize, usize)], partner: &[usize]| seats.iter().filter(|&&(a, b)| partner[a] == b).count();
+
+        // With nothing known every ceremony is the same up to relabeling, so any one is the best
+        let ceremony = contestants_to_pairs(&contestants);
+        let seats: Vec<(usize, usize)> = ceremony.iter().map(|pair| space.pair_indices(pair)).collect();
+        let by_beams = space.partition(most_beams + 1, |partner| count_beams(&seats, partner));
+
+        let (state, pairs) = OpeningBook::relabel(&start, &ceremony);
+        let mut entries = vec![BookEntry { state, action: BookMove::Ceremony(pairs), information: information(&by_beams) }];
+        let mut booths: Vec<Option<ContestantPair>> = vec![None; most_beams + 1];
+        for (beams, summary) in by_beams.iter().enumerate() {
+            // Lighting every beam ends the game, so there is no booth to play
+            if summary.count == 0 || beams == most_beams {
+                continue;
+            }
+            let week = start.with(Observation::Ceremony { pairs: ceremony.clone(), beams });
+            let mut best: Option<(ContestantPair, f64)> = None;
+            for (i, a) in contestants.iter().enumerate() {
+                for b in contestants[i + 1..].iter() {
+                    let pair = ContestantPair::new(a.clone(), b.clone());
+                    let bits = binary_entropy(summary.probability(&pair));
+                    if best.as_ref().is_none_or(|(_, best)| bits > *best) {
+                        best = Some((pair, bits));
+                    }
+                }
+            }
+            let (booth, bits) = best.expect("A cast has at least one pair");
+            let (state, pairs) = OpeningBook::relabel(&week, std::slice::from_ref(&booth));
+            entries.push(BookEntry { state, action: BookMove::Booth(pairs[0]), information: bits });
+            booths[beams] = Some(booth);
+        }
+
+        // The second ceremony, for every way the first week could have gone. Each is designed from the
+        // pair probabilities of its state, then scored exactly
+        let booth_seats: Vec<Option<(usize, usize)>> = booths.iter().map(|booth| booth.as_ref().map(|pair| space.pair_indices(pair))).collect();
+        let week_one = |partner: &[usize]| {
+            let beams = count_beams(&seats, partner);
+            2 * beams + usize::from(booth_seats[beams].is_some_and(|(a, b)| partner[a] == b))
+        };
+        let by_week_one = space.partition(2 * (most_beams + 1), week_one);
+        let mut second: Vec<Option<(History, Vec<ContestantPair>)>> = vec![None; by_week_one.len()];
+        let mut second_seats: Vec<Option<Vec<(usize, usize)>>> = vec![None; by_week_one.len()];
+        for (outcome, summary) in by_week_one.iter().enumerate() {
+            let Some(booth) = booths[outcome / 2].clone() else {
+                continue;
+            };
+            if summary.count == 0 {
+                continue;
+            }
+            let week = start
+                .with(Observation::Ceremony { pairs: ceremony.clone(), beams: outcome / 2 })
+                .with(Observation::Booth { pair: booth, is_match: outcome % 2 == 1 });
+            let probability = |pair: &ContestantPair| summary.probability(pair);
+            let likely = most_likely_ceremony(&contestants, probability, &PairConstraints::new()).expect("A matching is left, so it can be seated");
+            // Pairs of a state can be far from independent, so few enough matchings are gone through one by one
+            let designed = if summary.count <= EXACT_DESIGN_LIMIT {
+                let state = SolutionSpace::from_history(&week);
+                let exact = |pairs: &[ContestantPair]| -> Vec<f64> {
+                    analyze_action_in(&state, &Action::Ceremony(pairs.to_vec())).scenarios.iter().map(|scenario| scenario.probability).collect()
+                };
+                design_with_filler(likely, probability, &BeamTarget::EvenSplit, exact, DESIGN_PASSES)
+            } else {
+                let independent = |pairs: &[ContestantPair]| independent_beams(pairs, probability);
+                design_with_filler(likely, probability, &BeamTarget::EvenSplit, independent, DESIGN_PASSES)
+            };
+            second_seats[outcome] = Some(designed.iter().map(|pair| space.pair_indices(pair)).collect());
+            second[outcome] = Some((week, designed));
+        }
+        // Matchings whose first week has no second ceremony go in the last bucket
+        let unplanned = second.len() * (most_beams + 1);
+        let by_week_two = space.partition(unplanned + 1, |partner| {
+            let outcome = week_one(partner);
+            match &second_seats[outcome] {
+                Some(designed_seats) => outcome * (most_beams + 1) + count_beams(designed_seats, partner),
+                None => unplanned,
+            }
+        });
+        for (outcome, planned) in second.into_iter().enumerate() {
+            if let Some((week, designed)) = planned {
+                let buckets = &by_week_two[outcome * (most_beams + 1)..(outcome + 1) * (most_beams + 1)];
+                let (state, pairs) = OpeningBook::relabel(&week, &designed);
+                entries.push(BookEntry { state, action: BookMove::Ceremony(pairs), information: information(buckets) });
+            }
+        }
+        return OpeningBook { num_contestants, entries };
+    }
+
+    /* The state in canonical form along with the pairs in its labels */
+    fn relabel(state: &History, pairs: &[ContestantPair]) -> (CanonicalForm, Vec<(usize, usize)>) {
+        let canonical = canonicalize(state);
+        let label = |player: &Player| canonical.labels[state.contestants.iter().position(|other| other == player).unwrap()];
+        let pairs = pairs
+            .iter()
+            .map(|pair| {
+                let (a, b) = (label(pair.get_a()), label(pair.get_b()));
+                (a.min(b), a.max(b))
+            })
+            .collect();
+        return (canonical.form, pairs);
+    }
+
+    pub fn file_name(num_contestants: usize) -> String {
+        return format!("opening_book_{}.json", num_contestants);
+    }
+
+    pub fn save(&self, location: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(location, serde_json::to_string_pretty(self)?)?;
+        return Ok(());
+    }
+
+    pub fn load(location: &str) -> Result<OpeningBook, Box<dyn std::error::Error>> {
+        return Ok(serde_json::from_str(&std::fs::read_to_string(location)?)?);
+    }
+
+    /* The entry for the game so far, with the contestant behind each canonical label. Equivalent
+    moves are picked between with the given random numbers, so the book doesn't always seat the same
+    couples but a seeded game still plays the same */
+    fn find<P: Contestant, R: Rng>(&self, history: &History<P>, rng: &mut R) -> Option<(&BookEntry, Vec<P>)> {
+        let deepest = self.entries.iter().map(|entry| entry.state.observations.len()).max().unwrap_or(0);
+        if history.contestants.len() != self.num_contestants || history.observations.len() > deepest {
+            return None;
+        }
+        let mut shuffled = history.clone();
+        shuffled.contestants.shuffle(rng);
+        let canonical = canonicalize(&shuffled);
+        let entry = self.entries.iter().find(|entry| entry.state == canonical.form)?;
+        let mut by_label = shuffled.contestants.clone();
+        for (player, &label) in shuffled.contestants.iter().zip(canonical.labels.iter()) {
+            by_label[label] = player.clone();
+        }
+        return Some((entry, by_label));
+    }
+
+    pub fn ceremony<P: Contestant, R: Rng>(&self, history: &History<P>, rng: &mut R) -> Option<(Vec<ContestantPair<P>>, f64)> {
+        return match self.find(history, rng)? {
+            (BookEntry { action: BookMove::Ceremony(pairs), information, .. }, by_label) => {
+                Some((pairs.iter().map(|&(a, b)| ContestantPair::new(by_label[a].clone(), by_label[b].clone())).collect(), *information))
+            }
+            _ => None,
+        };
+    }
+
+    pub fn booth<P: Contestant, R: Rng>(&self, history: &History<P>, rng: &mut R) -> Option<(ContestantPair<P>, f64)> {
+        return match self.find(history, rng)? {
+            (BookEntry { action: BookMove::Booth((a, b)), information, .. }, by_label) => {
+                Some((ContestantPair::new(by_label[*a].clone(), by_label[*b].clone()), *information))
+            }
+            _ => None,
+        };
+    }
+}
+
+/* Bits revealed by learning which of the summaries the hidden matching is in */
+fn information(outcomes: &[SpaceSummary]) -> f64 {
+    let total: u64 = outcomes.iter().map(|summary| summary.count).sum();
+    return outcomes
+        .iter()
+        .filter(|summary| summary.count > 0)
+        .map(|summary| summary.count as f64 / total as f64)
+        .map(|p| -p * p.log2())
+        .sum();
+}
+
+impl fmt::Display for OpeningBook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Opening book for {} contestants", self.num_contestants)?;
+        for entry in self.entries.iter() {
+            let action = match &entry.action {
+                BookMove::Ceremony(pairs) => format!("ceremony {}", pairs.iter().map(|(a, b)| format!("{}+{}", a, b)).collect::<Vec<_>>().join(", ")),
+                BookMove::Booth((a, b)) => format!("booth {}+{}", a, b),
+            };
+            write!(f, "\n{} -> {} ({:.3} bits)", entry.state, action, entry.information)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{History, Observation};
+    use crate::openingbook::{BookMove, OpeningBook};
+    use crate::utils::{contestants_to_pairs, gen_contestants};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_generate_and_consult() {
+        let book = OpeningBook::generate(8);
+        // The opening ceremony, then a booth for 0, 1 and 2 beams (three can't happen), then a
+        // second ceremony for either result of each booth
+        assert_eq!(book.entries.len(), 10);
+        assert!(matches!(book.entries[0].action, BookMove::Ceremony(ref pairs) if pairs.len() == 4));
+        assert!(book.entries[1..4].iter().all(|entry| matches!(entry.action, BookMove::Booth(_)) && entry.information > 0.0));
+        assert!(book.entries[4..].iter().all(|entry| matches!(entry.action, BookMove::Ceremony(_)) && entry.information > 0.0));
+
+        let location = std::env::temp_dir().join("opening_book_test.json");
+        book.save(location.to_str().unwrap()).unwrap();
+        let loaded = OpeningBook::load(location.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.entries.len(), book.entries.len());
+        for (loaded, entry) in loaded.entries.iter().zip(book.entries.iter()) {
+            assert_eq!((&loaded.state, &loaded.action), (&entry.state, &entry.action));
+            assert!((loaded.information - entry.information).abs() < 1e-12);
+        }
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut c = gen_contestants(8);
+        let start = History::new(c.clone());
+        let (ceremony, _) = book.ceremony(&start, &mut rng).unwrap();
+        assert_eq!(ceremony.len(), 4);
+        assert_eq!(book.booth(&start, &mut rng), None);
+
+        // Any seating with no beams is the same state, and the booth then can't pick a seated pair
+        c.shuffle(&mut rng);
+        let seated = contestants_to_pairs(&c);
+        let week = start.with(Observation::Ceremony { pairs: seated.clone(), beams: 0 });
+        let (booth, bits) = book.booth(&week, &mut rng).unwrap();
+        assert!(!seated.contains(&booth));
+        assert!(bits > 0.0);
+        assert_eq!(book.ceremony(&week, &mut rng), None);
+
+        // Whatever the booth says, the book has the second ceremony and nothing after it
+        for is_match in [false, true] {
+            let later = week.with(Observation::Booth { pair: booth.clone(), is_match });
+            let (second, bits) = book.ceremony(&later, &mut rng).unwrap();
+            assert_eq!(second.len(), 4);
+            assert!(bits > 0.0);
+            assert_eq!(book.booth(&later, &mut rng), None);
+            assert_eq!(book.booth(&later.with(Observation::Ceremony { pairs: second, beams: 1 }), &mut rng), None);
+        }
+    }
+}
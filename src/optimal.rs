@@ -0,0 +1,389 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/* The best any strategy can do on a small cast, found by searching the whole game tree.
+   Weeks are counted the way GameMaster counts iterations: each week is a ceremony followed by
+   a booth, a ceremony with every beam lit ends the game before that week's booth, and booth
+   confirming all but one match ends it after. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct LowerBound {
+    pub num_contestants: usize,
+    // Fewest weeks that guarantee a win whatever the hidden matching is
+    pub worst_case: usize,
+    // Fewest weeks on average when every matching is equally likely to be the hidden one
+    pub expected: f64,
+}
+
+// Every matching of the cast has to fit in one bitset, and 8 contestants have 105
+pub const MAX_CONTESTANTS: usize = 8;
+
+// The consistent matchings as a bitset over every matching of the cast
+type States = u128;
+// Booth confirmed pairs as a bitset over pair indices
+type Confirmed = u64;
+
+/* Which matchings are still consistent is all that matters about the observations, so two
+   transcripts that leave the same matchings and the same booth confirmed pairs share an entry.
+   The memos are keyed on the raw bitsets rather than a canonical form as in canonical.rs. That
+   works on histories, not sets of matchings, and the canonical set of matchings would be the
+   smallest image under every relabeling of the cast, which is 40320 of them at 8 contestants for
+   every lookup. The whole 8 contestant solve needs about fifteen thousand entries as it is, and the
+   first ceremony already only tries one seating since they are all the same up to relabeling */
+struct Solver {
+    num_contestants: usize,
+    num_matchings: usize,
+    // For every possible ceremony, the matchings that would light each number of beams
+    beam_masks: Vec<Vec<States>>,
+    // For every pair, the matchings that hold it
+    pair_masks: Vec<(usize, States)>,
+    worst: HashMap<(States, Confirmed), (usize, usize)>,
+    expected: HashMap<(States, Confirmed), f64>,
+}
+
+fn all_matchings(n: usize) -> Vec<Vec<usize>> {
+    fn extend(partner: &mut Vec<usize>, found: &mut Vec<Vec<usize>>) {
+        let first = match partner.iter().position(|&p| p == usize::MAX) {
+            Some(first) => first,
+            None => {
+                found.push(partner.clone());
+                return;
+            }
+        };
+        for other in first + 1..partner.len() {
+            if partner[other] == usize::MAX {
+                partner[first] = other;
+                partner[other] = first;
+                extend(partner, found);
+                partner[first] = usize::MAX;
+                partner[other] = usize::MAX;
+            }
+        }
+    }
+    let mut found = vec![];
+    extend(&mut vec![usize::MAX; n], &mut found);
+    return found;
+}
+
+fn size(states: States) -> usize {
+    return states.count_ones() as usize;
+}
+
+impl Solver {
+    fn new(num_contestants: usize) -> Solver {
+        let matchings = all_matchings(num_contestants);
+        let beams = |ceremony: &[usize], matching: &[usize]| (0..num_contestants).filter(|&i| i < ceremony[i] && matching[i] == ceremony[i]).count();
+        let beam_masks = matchings
+            .iter()
+            .map(|ceremony| {
+                let mut masks = vec![0; num_contestants / 2 + 1];
+                for (m, matching) in matchings.iter().enumerate() {
+                    masks[beams(ceremony, matching)] |= 1 << m;
+                }
+                masks
+            })
+            .collect();
+        let mut pair_masks = vec![];
+        for a in 0..num_contestants {
+            for b in a + 1..num_contestants {
+                let holding = matchings.iter().enumerate().filter(|(_, matching)| matching[a] == b).fold(0, |mask, (m, _)| mask | 1 << m);
+                pair_masks.push((pair_masks.len(), holding));
+            }
+        }
+        return Solver {
+            num_contestants,
+            num_matchings: matchings.len(),
+            beam_masks,
+            pair_masks,
+            worst: HashMap::new(),
+            expected: HashMap::new(),
+        };
+    }
+
+    fn everything(&self) -> States {
+        return States::MAX >> (128 - self.num_matchings);
+    }
+
+    /* The consistent matchings split by how many beams each would light, leaving out the bucket
+    where every beam lights since that ends the game. Ceremonies that split them the same way are
+    the same move, so only one of each is kept */
+    fn ceremony_outcomes(&self, states: States) -> Vec<Vec<States>> {
+        let mut seen: HashSet<Vec<States>> = HashSet::new();
+        let mut moves = vec![];
+        // Before anything is known every seating is the same up to relabeling, so one will do
+        let candidates = if states == self.everything() { &self.beam_masks[..1] } else { &self.beam_masks[..] };
+        for masks in candidates.iter() {
+            let buckets: Vec<States> = masks[..self.num_contestants / 2].iter().map(|mask| mask & states).filter(|&b| b != 0).collect();
+            if seen.insert(buckets.clone()) {
+                moves.push(buckets);
+            }
+        }
+        // Trying the most even splits first lets the bounds cut the rest short
+        moves.sort_by_key(|buckets| buckets.iter().map(|&b| size(b)).max().unwrap_or(0));
+        return moves;
+    }
+
+    /* Every booth that teaches something or confirms a new pair, as (pair, matchings with it, matchings without) */
+    fn booth_outcomes(&self, states: States, confirmed: Confirmed) -> Vec<(usize, States, States)> {
+        let mut moves = vec![];
+        for &(pair, holding) in self.pair_masks.iter() {
+            let (with, without) = (states & holding, states & !holding);
+            let known = without == 0 && confirmed & (1 << pair) != 0;
+            if with != 0 && !known {
+                moves.push((pair, with, without));
+            }
+        }
+        moves.sort_by_key(|&(_, with, without)| size(with).max(size(without)));
+        return moves;
+    }
+
+    /* Booth outcomes as (matchings left, booth confirmed pairs, whether the game is over) */
+    fn after_booth(&self, pair: usize, with: States, without: States, confirmed: Confirmed) -> Vec<(States, Confirmed, bool)> {
+        let mut outcomes = vec![];
+        if with != 0 {
+            let confirmed = confirmed | (1 << pair);
+            let won = confirmed.count_ones() as usize + 1 >= self.num_contestants / 2;
+            outcomes.push((with, confirmed, won));
+        }
+        if without != 0 {
+            outcomes.push((without, confirmed, false));
+        }
+        return outcomes;
+    }
+
+    /* The most matchings a ceremony can still sort out with this many booth weeks to go, when
+    booths alone can't end the game. Of the n/2 + 1 beam counts one is the win and one (all but one
+    pair right) can't happen, and each booth splits what is left in two */
+    fn capacity(&self, weeks: usize) -> usize {
+        let mut capacity: usize = 1;
+        for _ in 0..weeks {
+            capacity = capacity.saturating_mul(2 * (self.num_contestants / 2 - 1)).saturating_add(1);
+        }
+        return capacity;
+    }
+
+    /* Whether a win can be guaranteed with at most `weeks` more booths, starting at a ceremony */
+    fn wins_within(&mut self, states: States, confirmed: Confirmed, weeks: usize) -> bool {
+        if size(states) == 1 {
+            return true;
+        }
+        let booths_could_win = confirmed.count_ones() as usize + weeks + 1 >= self.num_contestants / 2;
+        if weeks == 0 || (!booths_could_win && size(states) > self.capacity(weeks)) {
+            return false;
+        }
+        // Memoized as the most weeks known to fall short and the fewest known to be enough
+        let (short, enough) = self.worst.get(&(states, confirmed)).cloned().unwrap_or((0, usize::MAX));
+        if weeks >= enough {
+            return true;
+        }
+        if weeks <= short {
+            return false;
+        }
+        let mut wins = false;
+        for mut buckets in self.ceremony_outcomes(states) {
+            buckets.sort_by_key(|&b| std::cmp::Reverse(size(b)));
+            if buckets.into_iter().all(|bucket| self.booth_wins_within(bucket, confirmed, weeks)) {
+                wins = true;
+                break;
+            }
+        }
+        let entry = self.worst.entry((states, confirmed)).or_insert((0, usize::MAX));
+        if wins {
+            entry.1 = entry.1.min(weeks);
+        } else {
+            entry.0 = entry.0.max(weeks);
+        }
+        return wins;
+    }
+
+    fn booth_wins_within(&mut self, states: States, confirmed: Confirmed, weeks: usize) -> bool {
+        for (pair, with, without) in self.booth_outcomes(states, confirmed) {
+            let outcomes = self.after_booth(pair, with, without, confirmed);
+            if outcomes.into_iter().all(|(left, confirmed, won)| won || self.wins_within(left, confirmed, weeks - 1)) {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    fn worst_case(&mut self) -> usize {
+        let everything = self.everything();
+        let mut weeks = 0;
+        while !self.wins_within(everything, 0, weeks) {
+            weeks += 1;
+        }
+        return weeks;
+    }
+
+    /* Fewest weeks on average to settle this many matchings, starting from `ceremonies` ceremonies.
+    Each ceremony can guess one of them, and every week after it there are at most 2(n/2 - 1) times
+    as many ceremonies. Once `booth_win` booths could have confirmed enough matches, a booth can
+    end the game for any number of them at once */
+    fn fill_floor(&self, total: usize, ceremonies: usize, booth_win: usize) -> f64 {
+        let branching = 2 * (self.num_contestants / 2 - 1);
+        let (mut placed, mut weeks, mut ceremonies, mut cost) = (0, 0, ceremonies, 0);
+        while placed < total && weeks < booth_win {
+            let here = ceremonies.min(total - placed);
+            cost += here * weeks;
+            placed += here;
+            weeks += 1;
+            ceremonies = ceremonies.saturating_mul(branching.max(1));
+        }
+        cost += (total - placed) * weeks;
+        return cost as f64 / total as f64;
+    }
+
+    fn booths_to_win(&self, confirmed: Confirmed) -> usize {
+        return (self.num_contestants / 2 - 1).saturating_sub(confirmed.count_ones() as usize);
+    }
+
+    /* Lower bound for expected_ceremony */
+    fn ceremony_floor(&self, states: States, confirmed: Confirmed) -> f64 {
+        return self.fill_floor(size(states), 1, self.booths_to_win(confirmed));
+    }
+
+    /* Lower bound for what expected_booth adds after the booth's own week. Both outcomes go on to a
+    ceremony, and confirming a match brings a booth win one closer */
+    fn booth_floor(&self, states: States, confirmed: Confirmed) -> f64 {
+        return self.fill_floor(size(states), 2, self.booths_to_win(confirmed).saturating_sub(1));
+    }
+
+    fn expected_ceremony(&mut self, states: States, confirmed: Confirmed) -> f64 {
+        if size(states) == 1 {
+            return 0.0;
+        }
+        if let Some(&weeks) = self.expected.get(&(states, confirmed)) {
+            return weeks;
+        }
+        let total = size(states) as f64;
+        let mut best = f64::INFINITY;
+        for buckets in self.ceremony_outcomes(states) {
+            // Each bucket takes at least its booth week and whatever follows can't beat the floor, so
+            // count that up front and add the rest as it is found
+            let floors: Vec<f64> = buckets.iter().map(|&bucket| 1.0 + self.booth_floor(bucket, confirmed)).collect();
+            let mut expected: f64 = buckets.iter().zip(floors.iter()).map(|(&bucket, floor)| size(bucket) as f64 / total * floor).sum();
+            for (bucket, floor) in buckets.into_iter().zip(floors) {
+                if expected >= best {
+                    break;
+                }
+                expected += size(bucket) as f64 / total * (self.expected_booth(bucket, confirmed) - floor);
+            }
+            best = best.min(expected);
+        }
+        self.expected.insert((states, confirmed), best);
+        return best;
+    }
+
+    fn expected_booth(&mut self, states: States, confirmed: Confirmed) -> f64 {
+        let total = size(states) as f64;
+        let mut best = f64::INFINITY;
+        for (pair, with, without) in self.booth_outcomes(states, confirmed) {
+            let outcomes = self.after_booth(pair, with, without, confirmed);
+            let floors: Vec<f64> = outcomes.iter().map(|&(left, confirmed, won)| if won { 0.0 } else { self.ceremony_floor(left, confirmed) }).collect();
+            let mut expected: f64 = outcomes.iter().zip(floors.iter()).map(|(&(left, _, _), floor)| size(left) as f64 / total * (1.0 + floor)).sum();
+            for ((left, confirmed, won), floor) in outcomes.into_iter().zip(floors) {
+                if expected >= best {
+                    break;
+                }
+                if !won {
+                    expected += size(left) as f64 / total * (self.expected_ceremony(left, confirmed) - floor);
+                }
+            }
+            best = best.min(expected);
+        }
+        return best;
+    }
+}
+
+/* Weeks a strategy actually took over simulated games, next to the best possible */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub bound: LowerBound,
+    pub weeks: Vec<usize>,
+}
+
+impl Comparison {
+    pub fn mean(&self) -> f64 {
+        return self.weeks.iter().sum::<usize>() as f64 / self.weeks.len().max(1) as f64;
+    }
+
+    pub fn worst(&self) -> usize {
+        return self.weeks.iter().cloned().max().unwrap_or(0);
+    }
+
+    /* Average weeks lost to the optimal strategy per game */
+    pub fn gap(&self) -> f64 {
+        return self.mean() - self.bound.expected;
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>2} contestants: optimal {:.3} weeks on average and {} at worst, simulated {:.3} and {} over {} games ({:+.3})",
+            self.bound.num_contestants,
+            self.bound.expected,
+            self.bound.worst_case,
+            self.mean(),
+            self.worst(),
+            self.weeks.len(),
+            self.gap()
+        )
+    }
+}
+
+/* Solves the game for an even cast of at most MAX_CONTESTANTS */
+pub fn lower_bound(num_contestants: usize) -> LowerBound {
+    if num_contestants % 2 == 1 || num_contestants > MAX_CONTESTANTS {
+        panic!("Optimal play is only solved for even casts of up to {} contestants, got {}", MAX_CONTESTANTS, num_contestants);
+    }
+    let mut solver = Solver::new(num_contestants);
+    let everything = solver.everything();
+    return LowerBound {
+        num_contestants,
+        worst_case: solver.worst_case(),
+        expected: solver.expected_ceremony(everything, 0),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::optimal::{all_matchings, lower_bound, Comparison};
+
+    #[test]
+    fn test_all_matchings() {
+        assert_eq!(all_matchings(2).len(), 1);
+        assert_eq!(all_matchings(6).len(), 15);
+        assert_eq!(all_matchings(8).len(), 105);
+    }
+
+    #[test]
+    fn test_lower_bound() {
+        // Two contestants are matched at the first ceremony
+        let two = lower_bound(2);
+        assert_eq!((two.worst_case, two.expected), (0, 0.0));
+
+        // With four, a wrong first ceremony lights nothing and leaves two matchings. Any booth on one
+        // of their pairs either confirms a match, which is enough, or settles the other matching
+        let four = lower_bound(4);
+        assert_eq!(four.worst_case, 1);
+        assert!((four.expected - 2.0 / 3.0).abs() < 1e-9);
+
+        // Checked against a plain search over every ceremony and booth without bounds or pruning
+        let six = lower_bound(6);
+        assert_eq!(six.worst_case, 3);
+        assert!((six.expected - 5.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_comparison() {
+        let comparison = Comparison { bound: lower_bound(4), weeks: vec![0, 1, 2] };
+        assert_eq!(comparison.mean(), 1.0);
+        assert_eq!(comparison.worst(), 2);
+        assert!((comparison.gap() - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            comparison.to_string(),
+            " 4 contestants: optimal 0.667 weeks on average and 1 at worst, simulated 1.000 and 2 over 3 games (+0.333)"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use crate::plotting::Points;
+use crate::stats::{GameStats, Method, StatsCollector};
+use std::fmt;
+
+/* y = coefficient * x^exponent, fitted by least squares on the logs of both. The exponent is how
+   the cost grows with the cast, 2 for quadratic and so on */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerLaw {
+    pub coefficient: f64,
+    pub exponent: f64,
+    // Share of the variance in log y the fit explains, 1 for a perfect fit
+    pub r_squared: f64,
+}
+
+impl PowerLaw {
+    /* Needs two distinct positive x values. Points at or below zero have no log and are left out */
+    pub fn fit(points: &[(f64, f64)]) -> Option<PowerLaw> {
+        let logs: Vec<(f64, f64)> = points.iter().filter(|(x, y)| *x > 0.0 && *y > 0.0).map(|(x, y)| (x.ln(), y.ln())).collect();
+        if logs.len() < 2 {
+            return None;
+        }
+        let count = logs.len() as f64;
+        let mean_x = logs.iter().map(|(x, _)| x).sum::<f64>() / count;
+        let mean_y = logs.iter().map(|(_, y)| y).sum::<f64>() / count;
+        let spread_x: f64 = logs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        if spread_x <= f64::EPSILON {
+            return None;
+        }
+        let exponent = logs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>() / spread_x;
+        let intercept = mean_y - exponent * mean_x;
+        let residual: f64 = logs.iter().map(|(x, y)| (y - intercept - exponent * x).powi(2)).sum();
+        let total: f64 = logs.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+        let r_squared = if total <= f64::EPSILON { 1.0 } else { 1.0 - residual / total };
+        return Some(PowerLaw { coefficient: intercept.exp(), exponent, r_squared });
+    }
+
+    pub fn at(&self, x: f64) -> f64 {
+        return self.coefficient * x.powf(self.exponent);
+    }
+}
+
+impl fmt::Display for PowerLaw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.3e} * n^{:.2} (R² {:.3})", self.coefficient, self.exponent, self.r_squared)
+    }
+}
+
+/* What a profile point measures for a game, against its cast size */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Measure {
+    // Milliseconds for one ceremony_pairs or send_to_booths decision
+    Decision(Method),
+    // Peak heap of the game in megabytes
+    Memory,
+}
+
+impl Measure {
+    pub fn of(&self, game: &GameStats) -> Option<f64> {
+        return match self {
+            Measure::Decision(method) => game.timings.per_decision(*method).map(|time| time.as_secs_f64() * 1000.0),
+            Measure::Memory => Some(game.peak_memory as f64 / (1024.0 * 1024.0)),
+        };
+    }
+
+    pub fn describe(&self) -> &'static str {
+        return match self {
+            Measure::Decision(Method::Booth) => "Time per booth decision (ms)",
+            Measure::Decision(_) => "Time per ceremony decision (ms)",
+            Measure::Memory => "Peak memory (MB)",
+        };
+    }
+
+    /* Cast size against the measure for every game that has one, a series for each strategy */
+    pub fn points(&self, collector: &StatsCollector) -> Vec<Points> {
+        let mut points = Points::by_strategy(collector, |game| (game.num_contestants as f64, self.of(game).unwrap_or(f64::NAN)));
+        for series in points.iter_mut() {
+            series.points.retain(|(_, y)| !y.is_nan());
+        }
+        return points;
+    }
+}
+
+/* The fitted growth of the measure for each strategy that has enough points */
+pub fn fit_profile(collector: &StatsCollector, measure: Measure) -> Vec<(String, PowerLaw)> {
+    return measure.points(collector).into_iter().filter_map(|series| Some((series.name, PowerLaw::fit(&series.points)?))).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::performance::{fit_profile, Measure, PowerLaw};
+    use crate::stats::{GameStats, Method, StatsCollector};
+    use std::time::Duration;
+
+    #[test]
+    fn test_power_law_fit() {
+        let points: Vec<(f64, f64)> = [4.0, 8.0, 16.0, 32.0].iter().map(|&n: &f64| (n, 0.5 * n.powf(2.5))).collect();
+        let fit = PowerLaw::fit(&points).unwrap();
+        assert!((fit.exponent - 2.5).abs() < 1e-9);
+        assert!((fit.coefficient - 0.5).abs() < 1e-9);
+        assert!((fit.r_squared - 1.0).abs() < 1e-9);
+        assert!((fit.at(10.0) - 0.5 * 10f64.powf(2.5)).abs() < 1e-6);
+        assert_eq!(PowerLaw::fit(&[(4.0, 1.0), (4.0, 2.0)]), None);
+        assert_eq!(PowerLaw::fit(&[(4.0, 0.0), (8.0, 2.0)]), None);
+    }
+
+    #[test]
+    fn test_profile_per_decision() {
+        let mut collector = StatsCollector::new();
+        for n in [4, 8, 16] {
+            let mut stats = GameStats::new("bruteforce", n);
+            // Linear per decision, however many decisions the game took
+            stats.timings.ceremony = Duration::from_millis(n as u64 * n as u64);
+            stats.timings.ceremony_calls = n;
+            collector.record(stats);
+        }
+        // No booth decisions were made, so there is nothing to fit
+        assert!(fit_profile(&collector, Measure::Decision(Method::Booth)).is_empty());
+        let fits = fit_profile(&collector, Measure::Decision(Method::Ceremony));
+        assert_eq!(fits.len(), 1);
+        assert!((fits[0].1.exponent - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,382 @@
+use crate::performance::PowerLaw;
+use crate::stats::{GameStats, StatsCollector};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::ops::Range;
+use std::path::Path;
+
+/* Picked from the extension of the output location, PNG unless it ends in .svg */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Png,
+    Svg,
+}
+
+impl Format {
+    pub fn of(location: &str) -> Format {
+        let svg = Path::new(location).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+        return if svg { Format::Svg } else { Format::Png };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotConfig {
+    pub title: String,
+    pub x_desc: String,
+    pub y_desc: String,
+    pub size: (u32, u32),
+}
+
+impl PlotConfig {
+    pub fn new(title: &str, x_desc: &str, y_desc: &str) -> PlotConfig {
+        return PlotConfig { title: title.to_string(), x_desc: x_desc.to_string(), y_desc: y_desc.to_string(), size: (800, 600) };
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> PlotConfig {
+        self.size = (width, height);
+        return self;
+    }
+}
+
+/* One strategy's values, like the rounds it took to win each game */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+/* One value per game, grouped by strategy in the order the strategies first show up in the records */
+fn group_by_strategy<T, F: Fn(&GameStats) -> T>(collector: &StatsCollector, value: F) -> Vec<(String, Vec<T>)> {
+    let mut groups: Vec<(String, Vec<T>)> = vec![];
+    for game in collector.games.iter() {
+        match groups.iter_mut().find(|(name, _)| *name == game.strategy) {
+            Some((_, values)) => values.push(value(game)),
+            None => groups.push((game.strategy.clone(), vec![value(game)])),
+        }
+    }
+    return groups;
+}
+
+impl Series {
+    /* A series for each strategy, in the order they first show up in the records */
+    pub fn by_strategy<F: Fn(&GameStats) -> f64>(collector: &StatsCollector, value: F) -> Vec<Series> {
+        return group_by_strategy(collector, value).into_iter().map(|(name, values)| Series { name, values }).collect();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Points {
+    pub name: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Points {
+    pub fn by_strategy<F: Fn(&GameStats) -> (f64, f64)>(collector: &StatsCollector, point: F) -> Vec<Points> {
+        return group_by_strategy(collector, point).into_iter().map(|(name, points)| Points { name, points }).collect();
+    }
+}
+
+/* Strategies, cast sizes and game counts of a run, for plot titles */
+pub fn describe_run(collector: &StatsCollector) -> String {
+    let series = Series::by_strategy(collector, |game| game.num_contestants as f64);
+    let names: Vec<&str> = series.iter().map(|series| series.name.as_str()).collect();
+    let min = collector.games.iter().map(|game| game.num_contestants).min().unwrap_or(0);
+    let max = collector.games.iter().map(|game| game.num_contestants).max().unwrap_or(0);
+    let cast = if min == max { format!("{} contestants", min) } else { format!("{}-{} contestants", min, max) };
+    let games = match series.first() {
+        Some(first) if series.len() > 1 && series.iter().all(|series| series.values.len() == first.values.len()) => format!("{} games each", first.values.len()),
+        _ => format!("{} games", collector.games.len()),
+    };
+    return format!("{}, {}, {}", names.join(" vs "), cast, games);
+}
+
+/* Every plot draws one series per strategy, in its own colour, with axes fitted to the data */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Plot {
+    // Overlaid counts of whole number values, such as rounds to win
+    Histogram(Vec<Series>),
+    // Share of values at or below each value
+    Cdf(Vec<Series>),
+    BoxPlot(Vec<Series>),
+    Scatter(Vec<Points>),
+    // Points joined up in order, for values over time
+    Lines(Vec<Points>),
+    // Points on log-log axes with the power law fitted to each series, for costs that grow with the cast
+    Fitted(Vec<Points>),
+}
+
+impl Plot {
+    /* Writes the plot as SVG or PNG depending on the extension, creating any missing folders */
+    pub fn render(&self, config: &PlotConfig, location: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = Path::new(location).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        return match Format::of(location) {
+            Format::Png => self.draw(BitMapBackend::new(location, config.size).into_drawing_area(), config),
+            Format::Svg => self.draw(SVGBackend::new(location, config.size).into_drawing_area(), config),
+        };
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: DrawingArea<DB, Shift>, config: &PlotConfig) -> Result<(), Box<dyn std::error::Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        root.fill(&WHITE)?;
+        match self {
+            Plot::Histogram(series) => draw_histogram(&root, config, series)?,
+            Plot::Cdf(series) => draw_cdf(&root, config, series)?,
+            Plot::BoxPlot(series) => draw_box_plot(&root, config, series)?,
+            Plot::Scatter(points) => draw_points(&root, config, points, false)?,
+            Plot::Lines(points) => draw_points(&root, config, points, true)?,
+            Plot::Fitted(points) => draw_fitted(&root, config, points)?,
+        }
+        // Present by hand so a failed write is reported instead of ignored on drop
+        root.present()?;
+        return Ok(());
+    }
+}
+
+/* The range the values cover with a little room either side, so nothing sits on the frame */
+fn span<I: Iterator<Item = f64>>(values: I) -> Range<f64> {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+    if min > max {
+        return 0.0..1.0;
+    }
+    let room = if max > min { (max - min) * 0.05 } else { 1.0 };
+    return min - room..max + room;
+}
+
+/* Like span but for a log axis, so only positive values count and the room is a factor */
+fn log_span<I: Iterator<Item = f64>>(values: I) -> Range<f64> {
+    let (min, max) = values.filter(|value| *value > 0.0).fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+    if min > max {
+        return 1.0..10.0;
+    }
+    return min / 1.5..max * 1.5;
+}
+
+fn builder<'a, 'b, DB: DrawingBackend>(root: &'a DrawingArea<DB, Shift>, config: &PlotConfig) -> ChartBuilder<'a, 'b, DB> {
+    let mut builder = ChartBuilder::on(root);
+    builder.x_label_area_size(35u32).y_label_area_size(50u32).margin(10u32).caption(&config.title, ("sans-serif", 20f32));
+    return builder;
+}
+
+fn color(index: usize) -> PaletteColor<Palette99> {
+    return Palette99::pick(index);
+}
+
+fn draw_histogram<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, config: &PlotConfig, series: &[Series]) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let counts: Vec<Vec<(u32, u32)>> = series
+        .iter()
+        .map(|series| {
+            let mut counts: Vec<(u32, u32)> = vec![];
+            for value in series.values.iter().map(|value| value.round().max(0.0) as u32) {
+                match counts.iter_mut().find(|(bin, _)| *bin == value) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((value, 1)),
+                }
+            }
+            return counts;
+        })
+        .collect();
+    let bins = counts.iter().flatten().map(|(bin, _)| *bin);
+    let (min, max) = (bins.clone().min().unwrap_or(0), bins.max().unwrap_or(0));
+    let max_count = counts.iter().flatten().map(|(_, count)| *count).max().unwrap_or(0);
+
+    let mut chart = builder(root, config).build_cartesian_2d((min..max + 1).into_segmented(), 0u32..max_count + max_count / 10 + 1)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .bold_line_style(WHITE.mix(0.3))
+        .x_desc(config.x_desc.as_str())
+        .y_desc(config.y_desc.as_str())
+        .axis_desc_style(("sans-serif", 15i32))
+        .draw()?;
+
+    for (i, (series, counts)) in series.iter().zip(counts.iter()).enumerate() {
+        let color = color(i).mix(0.5);
+        chart
+            .draw_series(Histogram::vertical(&chart).style(color.filled()).data(counts.iter().cloned()))?
+            .label(series.name.as_str())
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], color.filled()));
+    }
+    chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    return Ok(());
+}
+
+fn draw_cdf<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, config: &PlotConfig, series: &[Series]) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let x_range = span(series.iter().flat_map(|series| series.values.iter().cloned()));
+    let mut chart = builder(root, config).build_cartesian_2d(x_range, 0f64..1.05)?;
+    chart
+        .configure_mesh()
+        .x_desc(config.x_desc.as_str())
+        .y_desc(config.y_desc.as_str())
+        .axis_desc_style(("sans-serif", 15i32))
+        .draw()?;
+
+    for (i, series) in series.iter().enumerate() {
+        let mut sorted = series.values.clone();
+        sorted.sort_by(f64::total_cmp);
+        // A step up at every value, so ties show as one taller step
+        let mut steps = vec![];
+        for (j, value) in sorted.iter().enumerate() {
+            steps.push((*value, j as f64 / sorted.len() as f64));
+            steps.push((*value, (j + 1) as f64 / sorted.len() as f64));
+        }
+        let color = color(i);
+        chart
+            .draw_series(LineSeries::new(steps, color.stroke_width(2)))?
+            .label(series.name.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart.configure_series_labels().position(SeriesLabelPosition::LowerRight).background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    return Ok(());
+}
+
+fn draw_box_plot<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, config: &PlotConfig, series: &[Series]) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let y_range = span(series.iter().flat_map(|series| series.values.iter().cloned()));
+    let mut chart = builder(root, config).build_cartesian_2d((0..series.len() as u32).into_segmented(), y_range.start as f32..y_range.end as f32)?;
+    let name = |value: &SegmentValue<u32>| match value {
+        SegmentValue::CenterOf(i) | SegmentValue::Exact(i) => series.get(*i as usize).map(|series| series.name.clone()).unwrap_or_default(),
+        SegmentValue::Last => String::new(),
+    };
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(series.len())
+        .x_label_formatter(&name)
+        .x_desc(config.x_desc.as_str())
+        .y_desc(config.y_desc.as_str())
+        .axis_desc_style(("sans-serif", 15i32))
+        .draw()?;
+
+    for (i, series) in series.iter().enumerate() {
+        // Quartiles of nothing would panic
+        if series.values.is_empty() {
+            continue;
+        }
+        let quartiles = Quartiles::new(&series.values);
+        chart.draw_series(std::iter::once(Boxplot::new_vertical(SegmentValue::CenterOf(i as u32), &quartiles).width(30).style(color(i).stroke_width(2))))?;
+    }
+    return Ok(());
+}
+
+fn draw_points<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, config: &PlotConfig, points: &[Points], joined: bool) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let x_range = span(points.iter().flat_map(|points| points.points.iter().map(|(x, _)| *x)));
+    let y_range = span(points.iter().flat_map(|points| points.points.iter().map(|(_, y)| *y)));
+    let mut chart = builder(root, config).build_cartesian_2d(x_range, y_range)?;
+    chart
+        .configure_mesh()
+        .x_desc(config.x_desc.as_str())
+        .y_desc(config.y_desc.as_str())
+        .axis_desc_style(("sans-serif", 15i32))
+        .draw()?;
+
+    for (i, points) in points.iter().enumerate() {
+        let color = color(i);
+        if joined {
+            chart.draw_series(LineSeries::new(points.points.clone(), color.stroke_width(2)))?;
+        }
+        chart
+            .draw_series(points.points.iter().map(|point| Circle::new(*point, 3f32, color.filled())))?
+            .label(points.name.as_str())
+            .legend(move |(x, y)| Circle::new((x + 10, y), 3f32, color.filled()));
+    }
+    chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    return Ok(());
+}
+
+fn draw_fitted<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, config: &PlotConfig, points: &[Points]) -> Result<(), Box<dyn std::error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let x_range = log_span(points.iter().flat_map(|points| points.points.iter().map(|(x, _)| *x)));
+    let y_range = log_span(points.iter().flat_map(|points| points.points.iter().map(|(_, y)| *y)));
+    let mut chart = builder(root, config).build_cartesian_2d(x_range.log_scale(), y_range.log_scale())?;
+    chart
+        .configure_mesh()
+        .x_desc(config.x_desc.as_str())
+        .y_desc(config.y_desc.as_str())
+        .axis_desc_style(("sans-serif", 15i32))
+        .draw()?;
+
+    for (i, points) in points.iter().enumerate() {
+        let color = color(i).to_rgba();
+        let positive = points.points.iter().filter(|(x, y)| *x > 0.0 && *y > 0.0);
+        chart
+            .draw_series(positive.map(|point| Circle::new(*point, 3f32, color.mix(0.6).filled())))?
+            .label(points.name.as_str())
+            .legend(move |(x, y)| Circle::new((x + 10, y), 3f32, color.filled()));
+        if let Some(fit) = PowerLaw::fit(&points.points) {
+            // Over the cast sizes the series covers, in enough steps to look smooth on a log axis
+            let sizes = points.points.iter().map(|(x, _)| *x).filter(|x| *x > 0.0);
+            let (start, end) = (sizes.clone().fold(f64::INFINITY, f64::min), sizes.fold(0.0, f64::max));
+            let curve = (0..=50).map(|step| start * (end / start).powf(step as f64 / 50.0)).map(|x| (x, fit.at(x)));
+            chart
+                .draw_series(LineSeries::new(curve, color.stroke_width(2)))?
+                .label(format!("{} fit: n^{:.2}", points.name, fit.exponent))
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+        }
+    }
+    chart.configure_series_labels().position(SeriesLabelPosition::UpperLeft).background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plotting::{describe_run, Format, Plot, PlotConfig, Points, Series};
+    use crate::stats::{GameStats, StatsCollector};
+
+    fn collector() -> StatsCollector {
+        let mut collector = StatsCollector::new();
+        for (strategy, num_contestants, rounds) in [("bruteforce", 8, 3), ("random", 8, 9), ("bruteforce", 12, 5), ("random", 12, 14)] {
+            let mut stats = GameStats::new(strategy, num_contestants);
+            stats.rounds = rounds;
+            collector.record(stats);
+        }
+        return collector;
+    }
+
+    #[test]
+    fn test_series_and_title() {
+        let collector = collector();
+        let rounds = Series::by_strategy(&collector, |game| game.rounds as f64);
+        assert_eq!(rounds, vec![Series { name: "bruteforce".to_string(), values: vec![3.0, 5.0] }, Series { name: "random".to_string(), values: vec![9.0, 14.0] }]);
+        let points = Points::by_strategy(&collector, |game| (game.num_contestants as f64, game.rounds as f64));
+        assert_eq!(points[1].points, vec![(8.0, 9.0), (12.0, 14.0)]);
+        assert_eq!(describe_run(&collector), "bruteforce vs random, 8-12 contestants, 2 games each");
+        assert_eq!(Format::of("plots/run.SVG"), Format::Svg);
+        assert_eq!(Format::of("plots/run.png"), Format::Png);
+    }
+
+    #[test]
+    fn test_render_creates_folders() {
+        let collector = collector();
+        let rounds = Series::by_strategy(&collector, |game| game.rounds as f64);
+        let points = Points::by_strategy(&collector, |game| (game.num_contestants as f64, game.rounds as f64));
+        let folder = std::env::temp_dir().join(format!("plotting_test_{}", std::process::id())).join("nested");
+        let config = PlotConfig::new(&describe_run(&collector), "Rounds to win", "Games").with_size(320, 240);
+        let plots = [("histogram", Plot::Histogram(rounds.clone())), ("cdf", Plot::Cdf(rounds.clone())), ("box", Plot::BoxPlot(rounds)), ("scatter", Plot::Scatter(points.clone())), ("fitted", Plot::Fitted(points))];
+        for (name, plot) in plots.iter() {
+            for extension in ["png", "svg"] {
+                let location = folder.join(format!("{}.{}", name, extension));
+                plot.render(&config, location.to_str().unwrap()).unwrap();
+                assert!(std::fs::metadata(&location).unwrap().len() > 0);
+            }
+        }
+        assert!(std::fs::read_to_string(folder.join("cdf.svg")).unwrap().contains("<svg"));
+        std::fs::remove_dir_all(folder.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+use crate::contestant::ContestantPair;
+use crate::history::{History, Observation};
+use crate::solutionspace::SolutionSpace;
+use crate::plotting::{Plot, PlotConfig, Points};
+use std::collections::HashSet;
+use std::fmt;
+
+/* How much was known at the end of a week, week zero being before anything aired */
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    pub week: usize,
+    pub matchings_left: u64,
+    pub entropy: f64,
+    // Matches the booth confirmed, and pairs every remaining matching agrees on
+    pub confirmed_pairs: usize,
+    pub forced_pairs: usize,
+}
+
+/* Week by week shrinkage of the solution space over a finished game */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline {
+    pub entries: Vec<TimelineEntry>,
+    pub determined_week: Option<usize>,
+}
+
+/* Splits a transcript into weeks. Each week opens with its ceremony, as in GameMaster games */
+pub fn split_weeks(history: &History) -> Vec<Vec<Observation>> {
+    let mut weeks: Vec<Vec<Observation>> = vec![];
+    for observation in history.observations.iter() {
+        let opens_week = matches!(observation, Observation::Ceremony { .. });
+        if opens_week || weeks.is_empty() {
+            weeks.push(vec![]);
+        }
+        weeks.last_mut().unwrap().push(observation.clone());
+    }
+    return weeks;
+}
+
+// Every week is summarized over the whole space, and a 16 contestant cast starts out with about two million matchings
+pub const MAX_TIMELINE_CAST: usize = 16;
+
+impl Timeline {
+    /* None when the cast is too large to replay against the exact solution space */
+    pub fn from_history(history: &History) -> Option<Timeline> {
+        if history.contestants.len() > MAX_TIMELINE_CAST {
+            return None;
+        }
+        let mut space = SolutionSpace::new(&history.contestants);
+        for pair in history.excluded.iter() {
+            space.rule_out(pair);
+        }
+        return Some(Timeline::from_weeks(space, &split_weeks(history)));
+    }
+
+    /* Starts from the given space, so anything known up front (like same side couples) can be ruled out first */
+    pub fn from_weeks(mut space: SolutionSpace, weeks: &[Vec<Observation>]) -> Timeline {
+        let mut confirmed: HashSet<ContestantPair> = HashSet::new();
+        let mut entries = vec![];
+        let mut record = |week: usize, space: &SolutionSpace, confirmed_pairs: usize| {
+            let summary = space.summarize();
+            entries.push(TimelineEntry {
+                week,
+                matchings_left: summary.count,
+                entropy: summary.entropy(),
+                confirmed_pairs,
+                forced_pairs: summary.forced_pairs().len(),
+            });
+        };
+        record(0, &space, confirmed.len());
+        for (i, week) in weeks.iter().enumerate() {
+            for observation in week.iter() {
+                // Sending a known match back to the booth confirms nothing new
+                if let Observation::Booth { pair, is_match: true } = observation {
+                    confirmed.insert(pair.clone());
+                }
+                space.observe(observation);
+            }
+            record(i + 1, &space, confirmed.len());
+        }
+        let determined_week = entries.iter().find(|entry| entry.matchings_left == 1).map(|entry| entry.week);
+        return Timeline { entries, determined_week };
+    }
+
+    pub fn weeks_played(&self) -> usize {
+        return self.entries.len() - 1;
+    }
+
+    /* Weeks played after the answer was already pinned down */
+    pub fn weeks_wasted(&self) -> Option<usize> {
+        return self.determined_week.map(|week| self.weeks_played() - week);
+    }
+
+    /* Bits left over the weeks, with a marker at the week the matching was determined */
+    pub fn plot(&self, location: &str, title: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let points: Vec<(f64, f64)> = self.entries.iter().map(|entry| (entry.week as f64, entry.entropy)).collect();
+        let mut lines = vec![Points { name: "solution space".to_string(), points }];
+        if let Some(week) = self.determined_week {
+            let max_bits = self.entries.iter().map(|entry| entry.entropy).fold(1.0, f64::max);
+            lines.push(Points { name: "determined".to_string(), points: vec![(week as f64, 0.0), (week as f64, max_bits)] });
+        }
+        return Plot::Lines(lines).render(&PlotConfig::new(title, "Week", "Bits left (log2 of matchings)"), location);
+    }
+}
+
+impl fmt::Display for Timeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "week  matchings left   bits  confirmed  forced")?;
+        for entry in self.entries.iter() {
+            writeln!(
+                f,
+                "{:>4}  {:>14}  {:>5.2}  {:>9}  {:>6}",
+                entry.week, entry.matchings_left, entry.entropy, entry.confirmed_pairs, entry.forced_pairs
+            )?;
+        }
+        match (self.determined_week, self.weeks_wasted()) {
+            (Some(week), Some(wasted)) => write!(f, "Determined after week {}, {} weeks played after that", week, wasted),
+            _ => write!(f, "Never determined in {} weeks", self.weeks_played()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{History, Observation};
+    use crate::postmortem::{split_weeks, Timeline, MAX_TIMELINE_CAST};
+    use crate::utils::{contestants_to_pairs, gen_contestants, get_matches};
+
+    #[test]
+    fn test_timeline() {
+        let c = gen_contestants(6);
+        let truth = contestants_to_pairs(&c);
+        let mut history = History::new(c.clone());
+        history.record(Observation::Ceremony { pairs: get_matches(&truth, 1, 2), beams: 1 });
+        history.record(Observation::Booth { pair: truth[0].clone(), is_match: true });
+        history.record(Observation::Booth { pair: truth[0].clone(), is_match: true });
+        history.record(Observation::Ceremony { pairs: truth.clone(), beams: 3 });
+
+        assert_eq!(split_weeks(&history).iter().map(|week| week.len()).collect::<Vec<_>>(), vec![3, 1]);
+        let timeline = Timeline::from_history(&history).unwrap();
+        assert_eq!(timeline.entries.len(), 3);
+        assert_eq!(timeline.entries[0].matchings_left, 15);
+        assert_eq!(timeline.entries[1].confirmed_pairs, 1);
+        assert_eq!(timeline.entries[2].matchings_left, 1);
+        assert_eq!(timeline.entries[2].forced_pairs, 3);
+        assert_eq!(timeline.entries[2].entropy, 0.0);
+        assert_eq!(timeline.determined_week, Some(2));
+        assert_eq!(timeline.weeks_wasted(), Some(0));
+        assert!(timeline.to_string().ends_with("Determined after week 2, 0 weeks played after that"));
+
+        assert_eq!(Timeline::from_history(&History::new(gen_contestants(MAX_TIMELINE_CAST + 2))), None);
+    }
+}
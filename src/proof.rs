@@ -0,0 +1,280 @@
+use crate::contestant::{Contestant, ContestantPair, Player};
+use crate::deduction::{decided_by_matching, exact_space, Reason, EXACT_LIMIT};
+use crate::history::{History, Observation};
+use crate::round::SavedRound;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/* What justifies deciding a pair. Observations are indexes into the game's history */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Proof<P: Contestant = Player> {
+    // Excluded before the game started, e.g. both are on the same side
+    Excluded,
+    // The truth booth said so
+    Booth { observation: usize },
+    // Every beam of the ceremony belongs to a confirmed match, so none of its other pairs is one
+    NoBeams { observation: usize },
+    // Follows by one of the deduction rules from the ceremonies and booths at these observations
+    // and the pairs decided before it
+    Deduced { reason: Reason<P>, observations: Vec<usize> },
+}
+
+impl<P: Contestant> Proof<P> {
+    /* The observations the proof relies on */
+    pub fn observations(&self) -> Vec<usize> {
+        return match self {
+            Proof::Excluded => vec![],
+            Proof::Booth { observation } | Proof::NoBeams { observation } => vec![*observation],
+            Proof::Deduced { observations, .. } => observations.clone(),
+        };
+    }
+}
+
+impl<P: Contestant> fmt::Display for Proof<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Proof::Excluded => write!(f, "the rules of the season"),
+            Proof::Booth { observation } => write!(f, "the truth booth (observation {})", observation),
+            Proof::NoBeams { observation } => write!(f, "a ceremony without new beams (observation {})", observation),
+            Proof::Deduced { reason, observations } => write!(f, "{:?} from observations {:?}", reason, observations),
+        }
+    }
+}
+
+/* A pair a strategy confirmed or ruled out, and why */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Justified<P: Contestant = Player> {
+    pub pair: ContestantPair<P>,
+    pub is_match: bool,
+    pub proof: Proof<P>,
+}
+
+/* What the booths, the season's rules and the earlier proofs have settled */
+struct Decided<P: Contestant> {
+    confirmed: HashSet<ContestantPair<P>>,
+    ruled_out: HashSet<ContestantPair<P>>,
+}
+
+impl<P: Contestant> Decided<P> {
+    fn new(history: &History<P>, earlier: &[Justified<P>]) -> Decided<P> {
+        let mut confirmed = HashSet::new();
+        let mut ruled_out: HashSet<ContestantPair<P>> = history.excluded.iter().cloned().collect();
+        let booths = history.observations.iter().filter_map(|observation| match observation {
+            Observation::Booth { pair, is_match } => Some((pair, *is_match)),
+            _ => None,
+        });
+        for (pair, is_match) in booths.chain(earlier.iter().map(|justified| (&justified.pair, justified.is_match))) {
+            if is_match {
+                confirmed.insert(pair.clone());
+            } else {
+                ruled_out.insert(pair.clone());
+            }
+        }
+        return Decided { confirmed, ruled_out };
+    }
+
+    fn is_confirmed(&self, player: &P) -> bool {
+        return self.confirmed.iter().any(|pair| pair.has_player(player));
+    }
+
+    fn is_open(&self, pair: &ContestantPair<P>) -> bool {
+        return !self.ruled_out.contains(pair) && !self.is_confirmed(pair.get_a()) && !self.is_confirmed(pair.get_b());
+    }
+
+    /* Who each undecided player could still be matched with */
+    fn possibilities(&self, history: &History<P>) -> HashMap<P, HashSet<P>> {
+        let open: Vec<&P> = history.contestants.iter().filter(|player| !self.is_confirmed(player)).collect();
+        return open
+            .iter()
+            .map(|&a| {
+                let partners = open.iter().filter(|&&b| a != b && self.is_open(&ContestantPair::new(a.clone(), b.clone())));
+                (a.clone(), partners.map(|&b| b.clone()).collect())
+            })
+            .collect();
+    }
+
+    /* The ceremony's undecided pairs and the beams no confirmed match accounts for */
+    fn ceremony(&self, history: &History<P>, observation: usize) -> Option<(Vec<ContestantPair<P>>, usize)> {
+        let Some(Observation::Ceremony { pairs, beams }) = history.observations.get(observation) else {
+            return None;
+        };
+        let owed = beams.checked_sub(pairs.iter().filter(|pair| self.confirmed.contains(pair)).count())?;
+        return Some((pairs.iter().filter(|pair| self.is_open(pair)).cloned().collect(), owed));
+    }
+}
+
+/* Whether counting beams over one ceremony, or two that share pairs, decides the pair. With two, the
+   beams among the shared pairs are bounded from both sides, which bounds the rest of each ceremony */
+fn decided_by_beams<P: Contestant>(pair: &ContestantPair<P>, is_match: bool, ceremonies: &[(Vec<ContestantPair<P>>, usize)]) -> bool {
+    let (group, least, most) = match ceremonies {
+        [(pairs, beams)] if pairs.contains(pair) && *beams <= pairs.len() => (pairs.len(), *beams, *beams),
+        [(first, a), (second, b)] => {
+            let shared = first.iter().filter(|pair| second.contains(pair)).count();
+            let (only_first, only_second) = (first.len() - shared, second.len() - shared);
+            let low = a.saturating_sub(only_first).max(b.saturating_sub(only_second));
+            let high = shared.min(*a).min(*b);
+            if low > high {
+                return false;
+            }
+            match (first.contains(pair), second.contains(pair)) {
+                (true, true) => (shared, low, high),
+                (true, false) => (only_first, a - high, a - low),
+                (false, true) => (only_second, b - high, b - low),
+                (false, false) => return false,
+            }
+        }
+        _ => return false,
+    };
+    return if is_match { least == group } else { most == 0 };
+}
+
+impl<P: Contestant> Justified<P> {
+    /* Whether the proof holds up against the history, given the proofs recorded before it. Deductions
+    are checked again from the observations they cite: the possibility rules by the blossom matcher
+    with the pair forced or removed, ceremony counts by bounding the beams, and only EveryMatching by
+    counting matchings, which it does itself */
+    pub fn verify(&self, history: &History<P>, earlier: &[Justified<P>]) -> bool {
+        let decided = Decided::new(history, earlier);
+        return match &self.proof {
+            Proof::Excluded => !self.is_match && history.excluded.contains(&self.pair),
+            Proof::Booth { observation } => {
+                history.observations.get(*observation) == Some(&Observation::Booth { pair: self.pair.clone(), is_match: self.is_match })
+            }
+            Proof::NoBeams { observation } => match history.observations.get(*observation) {
+                Some(Observation::Ceremony { pairs, beams }) => {
+                    !self.is_match
+                        && pairs.contains(&self.pair)
+                        && !decided.confirmed.contains(&self.pair)
+                        && *beams == pairs.iter().filter(|pair| decided.confirmed.contains(pair)).count()
+                }
+                _ => false,
+            },
+            Proof::Deduced { reason, observations } => {
+                if !decided.is_open(&self.pair) || observations.iter().any(|&observation| observation >= history.observations.len()) {
+                    return false;
+                }
+                match reason {
+                    Reason::OnlyOption(_) | Reason::NoPerfectMatching => {
+                        decided_by_matching(&decided.possibilities(history), &self.pair, self.is_match)
+                    }
+                    Reason::CeremonyCount { .. } | Reason::OverlappingCeremonies { .. } => {
+                        let ceremonies: Option<Vec<_>> = observations.iter().map(|&observation| decided.ceremony(history, observation)).collect();
+                        ceremonies.is_some_and(|ceremonies| decided_by_beams(&self.pair, self.is_match, &ceremonies))
+                    }
+                    Reason::EveryMatching => {
+                        let possibilities = decided.possibilities(history);
+                        let ceremonies: Option<Vec<_>> = observations.iter().map(|&observation| decided.ceremony(history, observation)).collect();
+                        let Some(ceremonies) = ceremonies.filter(|_| possibilities.len() <= EXACT_LIMIT) else {
+                            return false;
+                        };
+                        let rounds: Vec<SavedRound<P>> = ceremonies
+                            .into_iter()
+                            .zip(observations.iter())
+                            .map(|((pairs, beams), &observation)| SavedRound::new(pairs, beams, observation as u32))
+                            .collect();
+                        let summary = exact_space(&possibilities, &rounds).summarize();
+                        let count = summary.pair_count(&self.pair);
+                        if self.is_match { summary.count > 0 && count == summary.count } else { summary.count > 0 && count == 0 }
+                    }
+                }
+            }
+        };
+    }
+}
+
+impl<P: Contestant> fmt::Display for Justified<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} by {}", self.pair, if self.is_match { "confirmed" } else { "ruled out" }, self.proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::contestant::ContestantPair;
+    use crate::deduction::Reason;
+    use crate::history::{History, Observation};
+    use crate::proof::{decided_by_beams, Justified, Proof};
+    use crate::utils::{contestants_to_pairs, gen_contestants};
+
+    #[test]
+    fn test_verify() {
+        let c = gen_contestants(6);
+        let pairs = contestants_to_pairs(&c);
+        let wrong = ContestantPair::new(c[0].clone(), c[2].clone());
+        let mut history = History::new(c.clone());
+        history.record(Observation::Booth { pair: wrong.clone(), is_match: false });
+        history.record(Observation::Ceremony { pairs: pairs.clone(), beams: 1 });
+
+        let booth = Justified { pair: wrong.clone(), is_match: false, proof: Proof::Booth { observation: 0 } };
+        assert!(booth.verify(&history, &[]));
+        assert!(!Justified { is_match: true, ..booth.clone() }.verify(&history, &[]));
+        assert!(!Justified { proof: Proof::Booth { observation: 1 }, ..booth }.verify(&history, &[]));
+
+        // The one beam is only accounted for once its match is confirmed
+        let no_beams = Justified { pair: pairs[1].clone(), is_match: false, proof: Proof::NoBeams { observation: 1 } };
+        assert!(!no_beams.verify(&history, &[]));
+        let earlier = [Justified { pair: pairs[0].clone(), is_match: true, proof: Proof::Excluded }];
+        assert!(no_beams.verify(&history, &earlier));
+
+        // The beam might still be that pair's, and nothing is left to decide about the booth's pair
+        let deduced = Justified { pair: pairs[2].clone(), is_match: false, proof: Proof::Deduced { reason: Reason::EveryMatching, observations: vec![1] } };
+        assert!(!deduced.verify(&history, &[]));
+        assert!(!Justified { pair: wrong, ..deduced.clone() }.verify(&history, &[]));
+        assert!(!Justified { proof: Proof::Deduced { reason: Reason::EveryMatching, observations: vec![2] }, ..deduced }.verify(&history, &[]));
+    }
+
+    #[test]
+    fn test_verify_large_cast() {
+        // Too many contestants to count matchings, so only the checks that don't count can decide
+        let c = gen_contestants(20);
+        let mut history = History::new(c.clone());
+        for other in c[2..].iter() {
+            history.record(Observation::Booth { pair: ContestantPair::new(c[0].clone(), other.clone()), is_match: false });
+        }
+        let booths: Vec<usize> = (0..18).collect();
+        let deduced = |a: usize, b: usize, is_match: bool, reason: Reason| Justified {
+            pair: ContestantPair::new(c[a].clone(), c[b].clone()),
+            is_match,
+            proof: Proof::Deduced { reason, observations: booths.clone() },
+        };
+
+        assert!(deduced(0, 1, true, Reason::OnlyOption(c[0].clone())).verify(&history, &[]));
+        assert!(deduced(1, 2, false, Reason::NoPerfectMatching).verify(&history, &[]));
+        assert!(!deduced(2, 3, false, Reason::NoPerfectMatching).verify(&history, &[]));
+        assert!(!deduced(2, 3, true, Reason::NoPerfectMatching).verify(&history, &[]));
+        assert!(!deduced(1, 2, false, Reason::EveryMatching).verify(&history, &[]));
+
+        // A full house confirms every pair of the ceremony it cites, and only that one
+        history.record(Observation::Ceremony { pairs: contestants_to_pairs(&c), beams: 10 });
+        let counted = |observations: Vec<usize>, is_match: bool| Justified {
+            pair: ContestantPair::new(c[6].clone(), c[7].clone()),
+            is_match,
+            proof: Proof::Deduced { reason: Reason::CeremonyCount { round_id: 1 }, observations },
+        };
+        assert!(counted(vec![18], true).verify(&history, &[]));
+        assert!(!counted(vec![18], false).verify(&history, &[]));
+        assert!(!counted(vec![17], true).verify(&history, &[]));
+        assert!(!counted(vec![], true).verify(&history, &[]));
+    }
+
+    #[test]
+    fn test_decided_by_beams() {
+        let c = gen_contestants(8);
+        let p = contestants_to_pairs(&c);
+        let extra = ContestantPair::new(c[0].clone(), c[2].clone());
+        let first = vec![p[0].clone(), p[1].clone(), p[2].clone()];
+        let second = vec![p[0].clone(), p[1].clone(), p[3].clone()];
+
+        // The second ceremony leaves no beam for the shared pairs, so the first one's is the third pair
+        let ceremonies = [(first.clone(), 1), (second.clone(), 0)];
+        assert!(decided_by_beams(&p[0], false, &ceremonies));
+        assert!(decided_by_beams(&p[2], true, &ceremonies));
+        assert!(!decided_by_beams(&p[2], false, &ceremonies));
+        assert!(decided_by_beams(&p[3], false, &ceremonies));
+        assert!(!decided_by_beams(&extra, false, &ceremonies));
+
+        // Either ceremony's beam could be among the shared pairs
+        let ceremonies = [(first, 1), (second, 1)];
+        assert!((0..4).all(|i| !decided_by_beams(&p[i], false, &ceremonies) && !decided_by_beams(&p[i], true, &ceremonies)));
+    }
+}
@@ -0,0 +1,162 @@
+/* Properties of the whole game loop, checked over random hidden matchings and random observations
+   rather than hand-built casts. On a failure proptest shrinks the case to a small one and prints it.
+   Case counts are kept low for the everyday test run, and PROPTEST_CASES=<n> runs more */
+
+use crate::bruteforce::BruteForce;
+use crate::contestant::{Contestant, ContestantPair, Player};
+use crate::gamestrategy::{BoothRules, Feedback, GameStrategy};
+use crate::registry::{StrategyConfig, StrategyRegistry};
+use crate::simulation::play_seeded;
+use crate::stats::StatsCollector;
+use crate::utils::{contestants_to_pairs, gen_contestants};
+use proptest::prelude::*;
+use std::collections::HashSet;
+
+/* Something the house could reveal: a ceremony seating everyone in the given order, or a booth */
+#[derive(Debug, Clone)]
+enum Step {
+    Ceremony(Vec<usize>),
+    Booth(usize, usize),
+}
+
+/* Contestants in an order that pairs them off, for a hidden matching or a ceremony seating */
+fn seating(num_contestants: usize) -> impl Strategy<Value = Vec<usize>> {
+    return Just((0..num_contestants).collect::<Vec<usize>>()).prop_shuffle();
+}
+
+/* A hidden matching on an even cast of up to max_pairs couples */
+fn hidden_matching(max_pairs: usize) -> impl Strategy<Value = Vec<usize>> {
+    return (2..=max_pairs).prop_flat_map(|pairs| seating(pairs * 2));
+}
+
+/* A hidden matching and what is revealed about it */
+fn scenario() -> impl Strategy<Value = (Vec<usize>, Vec<Step>)> {
+    return hidden_matching(6).prop_flat_map(|order| {
+        let num_contestants = order.len();
+        let step = prop_oneof![
+            seating(num_contestants).prop_map(Step::Ceremony),
+            (0..num_contestants, 1..num_contestants).prop_map(move |(a, offset)| Step::Booth(a, (a + offset) % num_contestants)),
+        ];
+        return (Just(order), proptest::collection::vec(step, 1..16));
+    });
+}
+
+/* The given number of cases unless PROPTEST_CASES asks for another */
+fn config(cases: u32) -> ProptestConfig {
+    let cases = std::env::var("PROPTEST_CASES").ok().and_then(|cases| cases.parse().ok()).unwrap_or(cases);
+    return ProptestConfig::with_cases(cases);
+}
+
+fn seat(contestants: &[Player], order: &[usize]) -> Vec<ContestantPair> {
+    return contestants_to_pairs(&order.iter().map(|&i| contestants[i].clone()).collect::<Vec<Player>>());
+}
+
+fn is_open(strategy: &BruteForce, pair: &ContestantPair) -> bool {
+    return strategy.possibilities.get(pair.get_a()).is_some_and(|partners| partners.contains(pair.get_b()));
+}
+
+/* Lets the solver pick its own ceremonies and booths against the hidden matching, running the check
+   after every result it is told. Fails if the matching isn't seated within the given weeks */
+pub(crate) fn play_against<P: Contestant, C: FnMut(&BruteForce<P>) -> Result<(), TestCaseError>>(
+    strategy: &mut BruteForce<P>,
+    truth: &[ContestantPair<P>],
+    weeks: usize,
+    mut check: C,
+) -> Result<(), TestCaseError> {
+    for _week in 0..weeks {
+        let guess = strategy.ceremony_pairs();
+        let beams = guess.iter().filter(|pair| truth.contains(pair)).count();
+        if beams == truth.len() {
+            return Ok(());
+        }
+        strategy.ceremony_feedback(beams, guess);
+        check(strategy)?;
+        let pair = strategy.send_to_booth();
+        strategy.booth_feedback(if truth.contains(&pair) { Feedback::Correct(pair) } else { Feedback::Wrong(pair) });
+        check(strategy)?;
+    }
+    return Err(TestCaseError::fail(format!("the solver didn't find the matching in {} weeks", weeks)));
+}
+
+/* Everything the solver believes has to stay true of the hidden matching */
+fn check_invariants(strategy: &BruteForce, truth: &[ContestantPair]) -> Result<(), TestCaseError> {
+    for pair in truth.iter() {
+        prop_assert!(strategy.confirmed().contains(pair) || (is_open(strategy, pair) && is_open(strategy, &ContestantPair::new(pair.get_b().clone(), pair.get_a().clone()))), "{} was ruled out", pair);
+    }
+    for pair in strategy.confirmed().iter() {
+        prop_assert!(truth.contains(pair), "{} was confirmed but isn't a match", pair);
+    }
+    if !strategy.possibilities.is_empty() {
+        let pairing = strategy.possible_pairing();
+        prop_assert_eq!(pairing.len(), truth.len());
+        let seated: HashSet<&Player> = pairing.iter().flat_map(|pair| [pair.get_a(), pair.get_b()]).collect();
+        prop_assert_eq!(seated.len(), truth.len() * 2, "someone is seated twice");
+        for pair in pairing.iter() {
+            prop_assert!(strategy.confirmed().contains(pair) || is_open(strategy, pair), "{} was ruled out but seated", pair);
+        }
+    }
+    for justified in strategy.proofs().iter() {
+        prop_assert_eq!(truth.contains(&justified.pair), justified.is_match, "{}", justified);
+    }
+    for round in strategy.round_manager.rounds.iter() {
+        prop_assert!(round.num_correct <= round.num_consideration(), "round {} expects {} beams from {} pairs", round.round_id, round.num_correct, round.num_consideration());
+    }
+    return Ok(());
+}
+
+proptest! {
+    #![proptest_config(config(32))]
+
+    #[test]
+    fn prop_observations_keep_the_truth((order, steps) in scenario()) {
+        let contestants = gen_contestants(order.len());
+        let truth = seat(&contestants, &order);
+        let mut strategy = BruteForce::initialize(contestants.iter().collect());
+        strategy.sound = true;
+        for step in steps {
+            match step {
+                Step::Ceremony(seating) => {
+                    let guess = seat(&contestants, &seating);
+                    let beams = guess.iter().filter(|pair| truth.contains(pair)).count();
+                    // A full house ends the game, so there is no feedback to give
+                    if beams == truth.len() {
+                        break;
+                    }
+                    strategy.ceremony_feedback(beams, guess);
+                }
+                Step::Booth(a, b) => {
+                    let pair = ContestantPair::new(contestants[a].clone(), contestants[b].clone());
+                    strategy.booth_feedback(if truth.contains(&pair) { Feedback::Correct(pair) } else { Feedback::Wrong(pair) });
+                }
+            }
+            check_invariants(&strategy, &truth)?;
+        }
+    }
+
+    #[test]
+    fn prop_solver_play_keeps_the_truth(order in hidden_matching(7)) {
+        // The solver picking its own moves this time
+        let contestants = gen_contestants(order.len());
+        let truth = seat(&contestants, &order);
+        let mut strategy = BruteForce::initialize(contestants.iter().collect());
+        strategy.sound = true;
+        play_against(&mut strategy, &truth, 100, |strategy| check_invariants(strategy, &truth))?;
+    }
+}
+
+proptest! {
+    // Whole games are slower, so fewer of them
+    #![proptest_config(config(8))]
+
+    #[test]
+    fn prop_games_end_within_the_bound(pairs in 2usize..=6, seed in any::<u64>(), timeout in 1usize..40) {
+        let registry = StrategyRegistry::with_defaults();
+        for name in registry.names() {
+            let game = play_seeded((pairs * 2) as i32, timeout, &BoothRules::default(), &registry, &StrategyConfig::new(name), seed, &mut StatsCollector::new())
+                .map_err(|error| TestCaseError::fail(error.to_string()))?;
+            prop_assert!(!game.in_progress(), "{} left the game running", name);
+            prop_assert!(game.outcome().is_some());
+            prop_assert!(game.get_iterations() as usize <= timeout, "{} played {} rounds with a bound of {}", name, game.get_iterations(), timeout);
+        }
+    }
+}
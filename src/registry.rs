@@ -0,0 +1,309 @@
+use crate::baseline::{BeamHeuristic, ConsistentRandom, FullyRandom};
+use crate::bruteforce::BruteForce;
+use crate::ceremony::ModePolicy;
+use crate::openingbook::OpeningBook;
+use crate::contestant::Player;
+use crate::gamestrategy::GameStrategy;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/* Builds a strategy for the contestants of a new game, reading whatever parameters it needs from the config */
+pub type StrategyFactory = fn(Vec<&Player>, &StrategyConfig) -> Result<Box<dyn GameStrategy>, RegistryError>;
+
+#[derive(Debug, PartialEq)]
+pub enum RegistryError {
+    UnknownStrategy(String),
+    InvalidParam { key: String, value: String },
+    // A parameter the strategy never read, usually a typo
+    UnknownParam { strategy: String, key: String },
+    MalformedConfig(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::UnknownStrategy(name) => write!(f, "no strategy is registered as '{}'", name),
+            RegistryError::InvalidParam { key, value } => write!(f, "'{}' is not a valid value for parameter '{}'", value, key),
+            RegistryError::UnknownParam { strategy, key } => write!(f, "strategy '{}' has no parameter '{}'", strategy, key),
+            RegistryError::MalformedConfig(spec) => write!(f, "could not parse strategy config '{}'", spec),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/* A strategy name along with its parameters. Written out as `name:key=value,key=value` */
+#[derive(Debug, Clone)]
+pub struct StrategyConfig {
+    pub name: String,
+    params: BTreeMap<String, String>,
+    // Keys looked up since the last build, so parameters nobody reads can be rejected
+    read: RefCell<BTreeSet<String>>,
+}
+
+impl PartialEq for StrategyConfig {
+    fn eq(&self, other: &Self) -> bool {
+        return self.name == other.name && self.params == other.params;
+    }
+}
+
+impl StrategyConfig {
+    pub fn new(name: &str) -> StrategyConfig {
+        return StrategyConfig { name: name.to_string(), params: BTreeMap::new(), read: RefCell::new(BTreeSet::new()) };
+    }
+
+    pub fn with_param(mut self, key: &str, value: &str) -> StrategyConfig {
+        self.params.insert(key.to_string(), value.to_string());
+        return self;
+    }
+
+    pub fn parse(spec: &str) -> Result<StrategyConfig, RegistryError> {
+        let (name, params) = match spec.split_once(':') {
+            Some((name, params)) => (name, params),
+            None => (spec, ""),
+        };
+        if name.trim().is_empty() {
+            return Err(RegistryError::MalformedConfig(spec.to_string()));
+        }
+
+        let mut config = StrategyConfig::new(name.trim());
+        for param in params.split(',').filter(|p| !p.trim().is_empty()) {
+            match param.split_once('=') {
+                Some((key, value)) => config = config.with_param(key.trim(), value.trim()),
+                None => return Err(RegistryError::MalformedConfig(spec.to_string())),
+            }
+        }
+        return Ok(config);
+    }
+
+    pub fn get_bool(&self, key: &str, default: bool) -> Result<bool, RegistryError> {
+        return self.get_parsed(key, default);
+    }
+
+    pub fn get_usize(&self, key: &str, default: usize) -> Result<usize, RegistryError> {
+        return self.get_parsed(key, default);
+    }
+
+    pub fn get_f64(&self, key: &str, default: f64) -> Result<f64, RegistryError> {
+        return self.get_parsed(key, default);
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.read.borrow_mut().insert(key.to_string());
+        return self.params.get(key).map(|value| value.as_str());
+    }
+
+    /* Parameters given that nothing has looked up since the last build */
+    pub fn unread_params(&self) -> Vec<&str> {
+        let read = self.read.borrow();
+        return self.params.keys().filter(|key| !read.contains(*key)).map(|key| key.as_str()).collect();
+    }
+
+    fn get_parsed<T: std::str::FromStr>(&self, key: &str, default: T) -> Result<T, RegistryError> {
+        self.read.borrow_mut().insert(key.to_string());
+        return match self.params.get(key) {
+            Some(value) => value.parse().map_err(|_| RegistryError::InvalidParam {
+                key: key.to_string(),
+                value: value.clone(),
+            }),
+            None => Ok(default),
+        };
+    }
+}
+
+impl fmt::Display for StrategyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.params.is_empty() {
+            let params: Vec<String> = self.params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            write!(f, ":{}", params.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/* Maps strategy names to the factories that build them, so the game loop never has to
+   know about a concrete strategy */
+pub struct StrategyRegistry {
+    factories: HashMap<String, StrategyFactory>,
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StrategyRegistry {
+    pub fn new() -> StrategyRegistry {
+        return StrategyRegistry { factories: HashMap::new() };
+    }
+
+    /* A registry holding every strategy that ships with the crate */
+    pub fn with_defaults() -> StrategyRegistry {
+        let mut registry = StrategyRegistry::new();
+        registry.register("bruteforce", |contestants, config| {
+            let num_contestants = contestants.len();
+            let mut strategy = BruteForce::initialize(contestants);
+            strategy.round_optimization = config.get_bool("round_optimization", true)?;
+            strategy.deduction = config.get_bool("deduction", true)?;
+            strategy.likely_ceremony = config.get_bool("likely_ceremony", false)?;
+            strategy.sound = config.get_bool("sound", false)?;
+            if let Some(location) = config.get_str("opening_book") {
+                let invalid = || RegistryError::InvalidParam { key: String::from("opening_book"), value: location.to_string() };
+                let book = OpeningBook::load(location).map_err(|_| invalid())?;
+                if book.num_contestants != num_contestants {
+                    return Err(invalid());
+                }
+                strategy.opening_book = Some(book);
+            }
+            if let Some(target) = config.get_str("beam_target") {
+                strategy.beam_target = target.parse().map_err(|_| RegistryError::InvalidParam { key: String::from("beam_target"), value: target.to_string() })?;
+            }
+            if config.get_bool("information_ceremonies", false)? {
+                strategy.mode_policy = Some(ModePolicy {
+                    win_weeks_left: config.get_usize("win_weeks_left", 1)?,
+                    win_probability: config.get_f64("win_probability", 0.5)?,
+                });
+            }
+            Ok(Box::new(strategy))
+        });
+        registry.register("random", |contestants, _| Ok(Box::new(FullyRandom::initialize(contestants))));
+        registry.register("consistent_random", |contestants, _| Ok(Box::new(ConsistentRandom::initialize(contestants))));
+        registry.register("beam_heuristic", |contestants, _| Ok(Box::new(BeamHeuristic::initialize(contestants))));
+        return registry;
+    }
+
+    /* Registering a name twice replaces the earlier factory */
+    pub fn register(&mut self, name: &str, factory: StrategyFactory) {
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        return names;
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        return self.factories.contains_key(name);
+    }
+
+    /* Fails on parameters the factory didn't read, so a misspelt one isn't silently ignored */
+    pub fn build(&self, config: &StrategyConfig, contestants: Vec<&Player>) -> Result<Box<dyn GameStrategy>, RegistryError> {
+        let factory = match self.factories.get(&config.name) {
+            Some(factory) => factory,
+            None => return Err(RegistryError::UnknownStrategy(config.name.clone())),
+        };
+        config.read.borrow_mut().clear();
+        let strategy = factory(contestants, config)?;
+        if let Some(key) = config.unread_params().first() {
+            return Err(RegistryError::UnknownParam { strategy: config.name.clone(), key: key.to_string() });
+        }
+        return Ok(strategy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ceremony::BeamTarget;
+    use crate::registry::{RegistryError, StrategyConfig, StrategyRegistry};
+    use crate::utils::gen_contestants;
+
+    #[test]
+    fn test_parse_config() {
+        let config = StrategyConfig::parse("bruteforce:round_optimization=false, win_weeks_left = 3").unwrap();
+        assert_eq!(config.name, "bruteforce");
+        assert_eq!(config.get_bool("round_optimization", true), Ok(false));
+        assert_eq!(config.get_usize("win_weeks_left", 0), Ok(3));
+        assert_eq!(config.get_usize("missing", 7), Ok(7));
+        assert_eq!(config.to_string(), "bruteforce:round_optimization=false,win_weeks_left=3");
+
+        assert_eq!(StrategyConfig::parse("random").unwrap(), StrategyConfig::new("random"));
+        assert_eq!(
+            StrategyConfig::parse("random:seed"),
+            Err(RegistryError::MalformedConfig(String::from("random:seed")))
+        );
+        assert!(StrategyConfig::parse(":sound=true").is_err());
+    }
+
+    #[test]
+    fn test_invalid_param() {
+        let config = StrategyConfig::new("bruteforce").with_param("round_optimization", "maybe");
+        assert_eq!(
+            config.get_bool("round_optimization", true),
+            Err(RegistryError::InvalidParam { key: String::from("round_optimization"), value: String::from("maybe") })
+        );
+
+        let registry = StrategyRegistry::with_defaults();
+        let c = gen_contestants(4);
+        assert!(registry.build(&config, c.iter().collect()).is_err());
+
+        let missing_book = StrategyConfig::new("bruteforce").with_param("opening_book", "no_such_book.json");
+        assert_eq!(
+            registry.build(&missing_book, c.iter().collect()).err(),
+            Some(RegistryError::InvalidParam { key: String::from("opening_book"), value: String::from("no_such_book.json") })
+        );
+    }
+
+    #[test]
+    fn test_beam_target_param() {
+        assert_eq!("0.2/0.5/0.3".parse(), Ok(BeamTarget::Distribution(vec![0.2, 0.5, 0.3])));
+        assert_eq!("even".parse(), Ok(BeamTarget::EvenSplit));
+        assert!("0.2/0.5".parse::<BeamTarget>().is_err());
+
+        let registry = StrategyRegistry::with_defaults();
+        let c = gen_contestants(8);
+        let blackout = StrategyConfig::parse("bruteforce:information_ceremonies=true,beam_target=1/0/0/0/0").unwrap();
+        assert!(registry.build(&blackout, c.iter().collect()).is_ok());
+        let lopsided = StrategyConfig::parse("bruteforce:beam_target=0.9/0.9").unwrap();
+        assert_eq!(
+            registry.build(&lopsided, c.iter().collect()).err(),
+            Some(RegistryError::InvalidParam { key: String::from("beam_target"), value: String::from("0.9/0.9") })
+        );
+    }
+
+    #[test]
+    fn test_unknown_param() {
+        let registry = StrategyRegistry::with_defaults();
+        let c = gen_contestants(4);
+        let typo = StrategyConfig::parse("bruteforce:deduction=false,round_optimisation=false").unwrap();
+        assert_eq!(
+            registry.build(&typo, c.iter().collect()).err(),
+            Some(RegistryError::UnknownParam { strategy: String::from("bruteforce"), key: String::from("round_optimisation") })
+        );
+        assert_eq!(typo.unread_params(), vec!["round_optimisation"]);
+
+        // The baselines take no parameters at all
+        let tuned_random = StrategyConfig::new("random").with_param("temperature", "1");
+        assert!(registry.build(&tuned_random, c.iter().collect()).is_err());
+        let tuned = StrategyConfig::parse("bruteforce:information_ceremonies=true,win_weeks_left=2").unwrap();
+        assert!(registry.build(&tuned, c.iter().collect()).is_ok());
+    }
+
+    #[test]
+    fn test_build_strategies() {
+        let registry = StrategyRegistry::with_defaults();
+        let c = gen_contestants(8);
+
+        for name in registry.names() {
+            let mut strategy = registry.build(&StrategyConfig::new(name), c.iter().collect()).unwrap();
+            assert_eq!(strategy.ceremony_pairs().len(), 4);
+        }
+        assert_eq!(
+            registry.build(&StrategyConfig::new("oracle"), c.iter().collect()).err(),
+            Some(RegistryError::UnknownStrategy(String::from("oracle")))
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = StrategyRegistry::new();
+        assert!(!registry.contains("bruteforce"));
+
+        registry.register("bruteforce", |contestants, _| Ok(Box::new(crate::bruteforce::BruteForce::initialize(contestants))));
+        assert!(registry.contains("bruteforce"));
+        assert_eq!(registry.names(), vec!["bruteforce"]);
+    }
+}
@@ -1,17 +1,17 @@
-use crate::contestant::Player;
+use crate::contestant::{Contestant, Player};
 use crate::contestant::ContestantPair;
-use log::{debug, error, trace, info};
+use log::debug;
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct SavedRound {
-    guesses: Vec<ContestantPair>,
+pub struct SavedRound<P: Contestant = Player> {
+    guesses: Vec<ContestantPair<P>>,
     in_consideration: Vec<usize>,
     pub num_correct: usize,
     pub round_id: u32,
 }
 
-impl SavedRound {
-    pub fn new(guesses: Vec<ContestantPair>, num_correct: usize, round_id: u32) -> SavedRound {
+impl<P: Contestant> SavedRound<P> {
+    pub fn new(guesses: Vec<ContestantPair<P>>, num_correct: usize, round_id: u32) -> SavedRound<P> {
         let mut in_consideration = vec![];
         for i in 0..guesses.len() {
             in_consideration.push(i);
@@ -23,17 +23,22 @@ impl SavedRound {
             round_id
         }
     }
-    pub fn pick_from_round(&self, num: usize) -> Vec<ContestantPair> {
+    pub fn pick_from_round(&self, num: usize) -> Vec<ContestantPair<P>> {
         if num > self.num_consideration() {
             panic!("Can't pick {} players because only {} have not been eliminated", num, self.num_consideration());
         }
         let mut picked_players = vec![];
-        for i in self.in_consideration.iter() {
-            picked_players.push(self.guesses.get(i.clone()).unwrap().clone());
+        for i in self.in_consideration.iter().take(num) {
+            picked_players.push(self.guesses.get(*i).unwrap().clone());
         }
         return picked_players;
     }
 
+    /* Whether the pair is still one of the guesses that could hold a match */
+    pub fn considers(&self, pair: &ContestantPair<P>) -> bool {
+        return self.in_consideration.iter().any(|&i| &self.guesses[i] == pair);
+    }
+
     pub fn num_consideration(&self) -> usize {
         return self.in_consideration.len();
     }
@@ -47,10 +52,10 @@ impl SavedRound {
         return (self.num_correct as f32) / (self.num_consideration() as f32);
     }
 
-    pub fn eliminate_guesses(&mut self, pairs: &Vec<ContestantPair>) {
+    pub fn eliminate_guesses(&mut self, pairs: &[ContestantPair<P>]) {
         self.in_consideration.retain(|&i| {
             let pair = self.guesses.get(i).unwrap();
-            let should_keep = !pairs.contains(&pair);
+            let should_keep = !pairs.contains(pair);
             if !should_keep {
                 debug!("Eliminating pair round #{}: {}", self.round_id, &pair);
             }
@@ -58,7 +63,7 @@ impl SavedRound {
         });
     }
 
-    pub fn eliminate_player(&mut self, player: &Player) {
+    pub fn eliminate_player(&mut self, player: &P) {
         let mut num_removed = 0;
         let round_size = self.guesses_left();
         
@@ -81,7 +86,7 @@ mod tests {
     use crate::round::SavedRound;
     use crate::utils::get_matches;
     use crate::utils::gen_contestants;
-    use log::{debug, error, trace, info};
+    use log::debug;
 
     #[test]
     fn test_eliminate_pair() {
@@ -92,7 +97,8 @@ mod tests {
         let mut round = SavedRound::new(guesses.clone(), 2, 1);
 
         assert_eq!(round.num_consideration(), 6);
-        round.eliminate_guesses(&round.guesses[0..6].to_vec());
+        let all_guesses = round.guesses[0..6].to_vec();
+        round.eliminate_guesses(&all_guesses);
         assert_eq!(round.num_consideration(), 0);
 
         // Test that specific items are removed
@@ -105,7 +111,7 @@ mod tests {
             assert_eq!(round.pick_from_round(round.num_consideration()).contains(g), true);
             debug!("{}", round.num_consideration());
             debug!("pre {}", &ContestantPairs(&round.pick_from_round(round.num_consideration())));
-            round.eliminate_guesses(&vec![g.clone()]);
+            round.eliminate_guesses(std::slice::from_ref(g));
             debug!("post {}", &ContestantPairs(&round.pick_from_round(round.num_consideration())));
             assert_eq!(round.pick_from_round(round.num_consideration()).contains(g), false);
         }
@@ -122,7 +128,8 @@ mod tests {
         assert_eq!(round.probability(), 2.0/6.0);
 
         // Test that probability changes when you eliminate options
-        round.eliminate_guesses(&round.guesses[0..2].to_vec());
+        let first_two = round.guesses[0..2].to_vec();
+        round.eliminate_guesses(&first_two);
         assert_eq!(round.probability(), 2.0/4.0);
     }
 
@@ -133,9 +140,10 @@ mod tests {
         let copy = &guesses.to_vec();
         
         let mut round = SavedRound::new(guesses.clone(), 2, 1);
-        round.eliminate_guesses(&copy[0..2].to_vec());
+        round.eliminate_guesses(&copy[0..2]);
 
-        assert_eq!(round.pick_from_round(4), guesses[..4])
+        // The first two were eliminated, so the remaining four are picked
+        assert_eq!(round.pick_from_round(4), guesses[2..])
     }
 
     #[test]
@@ -150,5 +158,6 @@ mod tests {
         // there should only be 4 pairs left because c[0] and c[1] have different pairs
         assert_eq!(round.guesses.len(), 6);
         assert_eq!(round.guesses_left(), 4); 
+        assert!(round.guesses.iter().filter(|g| round.considers(g)).all(|g| !g.has_player(&c[0]) && !g.has_player(&c[1])));
     }
 }
\ No newline at end of file
@@ -1,19 +1,25 @@
 use crate::ContestantPairs;
 use crate::contestant::ContestantPair;
 use std::collections::HashSet;
-use crate::contestant::Player;
+use crate::contestant::{Contestant, Player};
 use std::collections::HashMap;
 use crate::round::SavedRound;
-use log::{debug, error, trace, info};
+use log::{debug, error};
 
-pub struct RoundManager {
-    pub rounds: Vec<SavedRound>,
+pub struct RoundManager<P: Contestant = Player> {
+    pub rounds: Vec<SavedRound<P>>,
     pub times_round_used: usize,
     last_round_id: u32,
 }
 
-impl RoundManager {
-    pub fn new() -> RoundManager {
+impl<P: Contestant> Default for RoundManager<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Contestant> RoundManager<P> {
+    pub fn new() -> RoundManager<P> {
         return RoundManager{
             rounds: Vec::new(),
             times_round_used: 0,
@@ -22,9 +28,9 @@ impl RoundManager {
     }
 
     /* This retrieves the round with the highest probability of finding an item*/
-    pub fn most_eff(&self) -> Option<&SavedRound> {
-        if self.rounds.len() > 0 {
-            let mut best_round = self.rounds.get(0).unwrap();
+    pub fn most_eff(&self) -> Option<&SavedRound<P>> {
+        if !self.rounds.is_empty() {
+            let mut best_round = self.rounds.first().unwrap();
 
             for r in self.rounds[1..].iter() {
                 if r.probability() > best_round.probability() {
@@ -38,18 +44,18 @@ impl RoundManager {
 
     /* We should use a round if it has a higher chance of finding a pair
     than just eliminating possibilities*/
-    pub fn should_use_round(&mut self, possibilities: &HashMap<Player, HashSet<Player>>) -> bool {
+    pub fn should_use_round(&mut self, possibilities: &HashMap<P, HashSet<P>>) -> bool {
         let best_round = match self.most_eff() {
             None => return false,
             Some(round) => { round },
         };
 
         // find the player key with the least number of poss pairs left
-        let mut best_player: Option<(&Player, usize)> = None;
+        let mut best_player: Option<(&P, usize)> = None;
         for (player, poss_pairs) in possibilities.iter() {
             match best_player {
                 Some(best) => {
-                    if poss_pairs.len() < best.1 {
+                    if (poss_pairs.len(), player) < (best.1, best.0) {
                         best_player = Some((player, poss_pairs.len()))
                     }
                 }
@@ -80,12 +86,12 @@ impl RoundManager {
         }
     }
 
-    pub fn add_round(&mut self, guesses: Vec<ContestantPair>, num_correct: usize) {
+    pub fn add_round(&mut self, guesses: Vec<ContestantPair<P>>, num_correct: usize) {
         self.last_round_id += 1;
         self.rounds.push(SavedRound::new(guesses, num_correct, self.last_round_id));
     }
 
-    pub fn best_guess(&mut self) -> Option<ContestantPair> {
+    pub fn best_guess(&mut self) -> Option<ContestantPair<P>> {
         match self.most_eff() {
             Some(round) => {
                 let mut player = round.pick_from_round(1);
@@ -96,30 +102,35 @@ impl RoundManager {
         };
     }
 
-    pub fn latest(&self) -> Option<&SavedRound> {
+    pub fn latest(&self) -> Option<&SavedRound<P>> {
         return self.rounds.last();
     }
-    pub fn latest_mut(&mut self) -> Option<&mut SavedRound> {
+    pub fn latest_mut(&mut self) -> Option<&mut SavedRound<P>> {
         return self.rounds.last_mut();
     }
-    pub fn contains(&self, round: &SavedRound) -> bool {
+    pub fn contains(&self, round: &SavedRound<P>) -> bool {
         return self.rounds.contains(round);
     }
 
-    pub fn perfect_match_found(&mut self, pair: &ContestantPair) {
+    pub fn perfect_match_found(&mut self, pair: &ContestantPair<P>) {
         // Go through each round, see if it contains the pair. If it does,
-        // eliminate guess and decrease number of perfect match
+        // eliminate guess and decrease number of perfect match. Either way
+        // every other pair with one of the two players is now wrong
         for round in self.rounds.iter_mut() {
-
-            if round.num_correct > 0 {
-                round.num_correct -= 1;
+            if round.considers(pair) {
+                if round.num_correct == 0 {
+                    // The round ruled the pair out, so one of the two observations was misread
+                    error!("Saved round #{} has no beams left for {}", round.round_id, pair);
+                } else {
+                    round.num_correct -= 1;
+                }
             }
             round.eliminate_player(pair.get_a());
             round.eliminate_player(pair.get_b());
         }
     }
 
-    pub fn eliminate_guesses(&mut self, guesses: Vec<ContestantPair>) {
+    pub fn eliminate_guesses(&mut self, guesses: Vec<ContestantPair<P>>) {
 
         for round in self.rounds.iter_mut() {
             round.eliminate_guesses(&guesses);
@@ -137,8 +148,12 @@ impl RoundManager {
     /* Returns how many rounds were pruned */
     pub fn prune_rounds(&mut self) -> usize {
         let num_saved = self.rounds.len();
-        // eliminate any rounds that are not pruned
-        self.rounds.retain(|round| round.num_consideration() > 0 && round.num_correct != 0);
+        // Rounds with no beams left have nothing more to say. Beams owed by fewer pairs than
+        // remain are a contradiction, and dropping the round is the only safe thing left to do
+        for round in self.rounds.iter().filter(|round| round.num_correct > round.num_consideration()) {
+            error!("Saved round #{} owes {} beams but only {} pairs remain", round.round_id, round.num_correct, round.num_consideration());
+        }
+        self.rounds.retain(|round| round.num_consideration() > 0 && round.num_correct != 0 && round.num_correct <= round.num_consideration());
         debug!("{} rounds pruned", num_saved - self.rounds.len());
         return num_saved - self.rounds.len();
     }
@@ -152,6 +167,7 @@ mod tests {
     use std::collections::HashSet;
     use std::collections::HashMap;
     use crate::utils::gen_contestants;
+    use crate::utils::get_matches;
 
     #[test]
     fn test_most_eff_round() {
@@ -191,6 +207,22 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_perfect_match_found() {
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut round_manager = RoundManager::new();
+        round_manager.add_round(get_matches(&truth, 2, 2), 2);
+        round_manager.add_round(get_matches(&truth, 1, 3), 1);
+
+        // Only the first round sat the pair, the second only loses the pairs its players were in
+        round_manager.perfect_match_found(&truth[1]);
+        assert_eq!(round_manager.rounds[0].num_correct, 1);
+        assert_eq!(round_manager.rounds[0].num_consideration(), 3);
+        assert_eq!(round_manager.rounds[1].num_correct, 1);
+        assert_eq!(round_manager.rounds[1].num_consideration(), 2);
+    }
+
     #[test]
     fn test_prune_rounds() {
         let c = gen_contestants(10);
@@ -205,9 +237,18 @@ mod tests {
 
         let guess = contestants_to_pairs(&c);
         round_manager.add_round(guess.clone(), 0);
-        let mut latest = round_manager.rounds.last_mut().unwrap();
+        let latest = round_manager.rounds.last_mut().unwrap();
         latest.eliminate_guesses(&guess);
         assert_eq!(round_manager.prune_rounds(), 1);
         assert_eq!(round_manager.rounds.len(), 0);
+
+        // Two beams owed by the one pair left is a contradiction, which would otherwise stop the
+        // deductions from every saved round
+        round_manager.add_round(guess.clone(), 2);
+        round_manager.add_round(guess.clone(), 1);
+        round_manager.rounds[0].eliminate_guesses(&guess[1..]);
+        assert_eq!(round_manager.prune_rounds(), 1);
+        assert_eq!(round_manager.rounds.len(), 1);
+        assert_eq!(round_manager.rounds[0].num_correct, 1);
     }
 }
\ No newline at end of file
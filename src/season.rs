@@ -0,0 +1,435 @@
+/* Aired seasons written down as plain text, so they can be replayed through a strategy.
+
+   A season file is read line by line. Blank lines and anything after a '#' are ignored.
+
+       name: Example season
+       cast: Alice, Bob, Carol, Dave
+
+       week 1
+       ceremony: Alice+Bob, Carol+Dave = 0
+       booth: Alice+Carol = no match
+
+       week 2
+       booth: Alice+Dave = match
+
+   - `name:` is optional.
+   - `cast:` lists everyone once, separated by commas. Contestants get ids in the order listed.
+   - Seasons where only the two sides can be matched give two `side:` lines instead of `cast:`.
+     Pairs within a side are then ruled out from the start.
+   - `week N` starts the next week. Weeks are numbered from 1 and can't be skipped.
+   - `ceremony:` lists the seated couples, partners joined by '+', then '=' and the beams.
+   - `booth:` gives one couple, then '=' and either `match` or `no match`.
+   A week may hold any number of booths and at most one ceremony, in the order they aired.
+   Results that contradict earlier ones are rejected with the line they are on.
+
+   Not done yet: none of the aired seasons (US 1 to 9 or the international ones) is in the seasons
+   directory, so report has only been run on a recorded game, in bruteforce's test_season_fixture.
+   They can be added in this format once they are transcribed from a source that can be cited. */
+
+use crate::bruteforce::BruteForce;
+use crate::contestant::{ContestantPair, Player};
+use crate::gamestrategy::{Feedback, GameStrategy};
+use crate::history::{History, Observation};
+use crate::solutionspace::SolutionSpace;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// Exact counts are skipped when the space could hold more matchings than a 16 contestant cast
+const EXACT_CAST_LIMIT: usize = 16;
+const EXACT_SIDE_LIMIT: usize = 10;
+
+#[derive(Debug, PartialEq)]
+pub struct SeasonError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SeasonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SeasonError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Season {
+    pub name: Option<String>,
+    pub contestants: Vec<Player>,
+    pub sides: Option<(Vec<Player>, Vec<Player>)>,
+    pub weeks: Vec<Vec<Observation>>,
+}
+
+impl Season {
+    pub fn parse(text: &str) -> Result<Season, SeasonError> {
+        let mut name = None;
+        let mut sides: Vec<Vec<Player>> = vec![];
+        let mut contestants: Vec<Player> = vec![];
+        let mut by_name: HashMap<String, Player> = HashMap::new();
+        let mut weeks: Vec<Vec<Observation>> = vec![];
+        let mut consistency: Option<Consistency> = None;
+
+        for (i, raw) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let error = |message: String| SeasonError { line: line_number, message };
+            let line = raw.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(week) = line.strip_prefix("week ") {
+                let number: usize = week.trim().parse().map_err(|_| error(format!("'{}' is not a week number", week.trim())))?;
+                if number != weeks.len() + 1 {
+                    return Err(error(format!("expected week {}, found week {}", weeks.len() + 1, number)));
+                }
+                if consistency.is_none() {
+                    consistency = Some(Consistency::new(&contestants, &sides));
+                }
+                weeks.push(vec![]);
+                continue;
+            }
+            let (key, value) = line.split_once(':').ok_or_else(|| error(format!("could not read '{}'", line)))?;
+            let value = value.trim();
+            match key.trim() {
+                "name" => name = Some(value.to_string()),
+                "cast" | "side" => {
+                    if !weeks.is_empty() {
+                        return Err(error(String::from("the cast has to come before the first week")));
+                    }
+                    if key.trim() == "cast" && !contestants.is_empty() {
+                        return Err(error(String::from("the cast was already given")));
+                    }
+                    let mut side = vec![];
+                    for cast_name in value.split(',').map(|n| n.trim()).filter(|n| !n.is_empty()) {
+                        if by_name.contains_key(cast_name) {
+                            return Err(error(format!("{} is listed twice", cast_name)));
+                        }
+                        let player = Player { id: contestants.len() as i32, name: cast_name.to_string() };
+                        by_name.insert(cast_name.to_string(), player.clone());
+                        contestants.push(player.clone());
+                        side.push(player);
+                    }
+                    if key.trim() == "side" {
+                        sides.push(side);
+                    }
+                }
+                "ceremony" | "booth" => {
+                    let week = weeks.last_mut().ok_or_else(|| error(String::from("results have to be inside a week")))?;
+                    let (couples, result) = value.split_once('=').ok_or_else(|| error(String::from("missing '=' and the result")))?;
+                    let mut pairs = vec![];
+                    for couple in couples.split(',').map(|c| c.trim()).filter(|c| !c.is_empty()) {
+                        let (a, b) = couple.split_once('+').ok_or_else(|| error(format!("'{}' is not a couple", couple)))?;
+                        let player = |n: &str| by_name.get(n.trim()).cloned().ok_or_else(|| error(format!("{} isn't in the cast", n.trim())));
+                        pairs.push(ContestantPair::new(player(a)?, player(b)?));
+                    }
+                    let observation = if key.trim() == "booth" {
+                        if pairs.len() != 1 {
+                            return Err(error(String::from("a booth holds exactly one couple")));
+                        }
+                        let is_match = match result.trim() {
+                            "match" => true,
+                            "no match" => false,
+                            other => return Err(error(format!("'{}' should be 'match' or 'no match'", other))),
+                        };
+                        Observation::Booth { pair: pairs.pop().unwrap(), is_match }
+                    } else {
+                        if week.iter().any(|o| matches!(o, Observation::Ceremony { .. })) {
+                            return Err(error(String::from("a week has at most one ceremony")));
+                        }
+                        let mut seated: Vec<&Player> = pairs.iter().flat_map(|p| [p.get_a(), p.get_b()]).collect();
+                        seated.sort_by_key(|p| p.id);
+                        seated.dedup();
+                        if seated.len() != pairs.len() * 2 {
+                            return Err(error(String::from("somebody is seated twice")));
+                        }
+                        let beams: usize = result.trim().parse().map_err(|_| error(format!("'{}' is not a beam count", result.trim())))?;
+                        if beams > pairs.len() {
+                            return Err(error(format!("{} beams from {} couples", beams, pairs.len())));
+                        }
+                        Observation::Ceremony { pairs, beams }
+                    };
+                    if let Some(consistency) = consistency.as_mut() {
+                        consistency.check(&observation).map_err(error)?;
+                    }
+                    week.push(observation);
+                }
+                other => return Err(error(format!("unknown entry '{}'", other))),
+            }
+        }
+
+        let sides = match sides.len() {
+            0 => None,
+            2 if sides[0].len() + sides[1].len() == contestants.len() => Some((sides[0].clone(), sides[1].clone())),
+            2 => return Err(SeasonError { line: 0, message: String::from("a season gives either a cast or two sides, not both") }),
+            n => return Err(SeasonError { line: 0, message: format!("expected two sides, found {}", n) }),
+        };
+        if contestants.is_empty() {
+            return Err(SeasonError { line: 0, message: String::from("the season has no cast") });
+        }
+        return Ok(Season { name, contestants, sides, weeks });
+    }
+
+    /* Couples who can't be a match because they are on the same side */
+    pub fn same_side_pairs(&self) -> Vec<ContestantPair> {
+        let mut pairs = vec![];
+        if let Some((first, second)) = &self.sides {
+            for side in [first, second] {
+                for (i, a) in side.iter().enumerate() {
+                    for b in side[i + 1..].iter() {
+                        pairs.push(ContestantPair::new(a.clone(), b.clone()));
+                    }
+                }
+            }
+        }
+        return pairs;
+    }
+
+    /* Everything that aired, in order, with the same side couples excluded up front */
+    pub fn history(&self) -> History {
+        let mut history = History::new(self.contestants.clone()).with_excluded(self.same_side_pairs());
+        for observation in self.weeks.iter().flatten() {
+            history.record(observation.clone());
+        }
+        return history;
+    }
+
+    /* Whether counting every consistent matching is quick enough to do once a week */
+    fn is_exactly_countable(&self) -> bool {
+        return match &self.sides {
+            Some((first, second)) => first.len().max(second.len()) <= EXACT_SIDE_LIMIT,
+            None => self.contestants.len() <= EXACT_CAST_LIMIT,
+        };
+    }
+}
+
+/* Catches results that contradict what aired before them, which would otherwise trip up a strategy
+   replaying the season. Booths are always checked against each other and against the ceremonies.
+   Seasons small enough to count exactly are also checked for a matching that fits everything */
+struct Consistency {
+    partner: HashMap<Player, Player>,
+    ruled_out: HashSet<ContestantPair>,
+    space: Option<SolutionSpace>,
+}
+
+impl Consistency {
+    fn new(contestants: &[Player], sides: &[Vec<Player>]) -> Consistency {
+        let season = Season {
+            name: None,
+            contestants: contestants.to_vec(),
+            sides: if sides.len() == 2 { Some((sides[0].clone(), sides[1].clone())) } else { None },
+            weeks: vec![],
+        };
+        let history = season.history();
+        let space = if season.is_exactly_countable() { Some(SolutionSpace::from_history(&history)) } else { None };
+        return Consistency { partner: HashMap::new(), ruled_out: history.excluded.into_iter().collect(), space };
+    }
+
+    fn is_confirmed(&self, pair: &ContestantPair) -> bool {
+        return self.partner.get(pair.get_a()) == Some(pair.get_b());
+    }
+
+    /* Whether the pair could still be a match going by the booths */
+    fn is_open(&self, pair: &ContestantPair) -> bool {
+        let free = |player: &Player, other: &Player| self.partner.get(player).is_none_or(|partner| partner == other);
+        return !self.ruled_out.contains(pair) && free(pair.get_a(), pair.get_b()) && free(pair.get_b(), pair.get_a());
+    }
+
+    fn check(&mut self, observation: &Observation) -> Result<(), String> {
+        match observation {
+            Observation::Booth { pair, is_match: true } => {
+                if !self.is_open(pair) {
+                    return Err(format!("{} can't be a match any more", pair));
+                }
+                self.partner.insert(pair.get_a().clone(), pair.get_b().clone());
+                self.partner.insert(pair.get_b().clone(), pair.get_a().clone());
+            }
+            Observation::Booth { pair, is_match: false } => {
+                if self.is_confirmed(pair) {
+                    return Err(format!("{} was already confirmed", pair));
+                }
+                self.ruled_out.insert(pair.clone());
+            }
+            Observation::Ceremony { pairs, beams } => {
+                let confirmed = pairs.iter().filter(|pair| self.is_confirmed(pair)).count();
+                let open = pairs.iter().filter(|pair| self.is_open(pair)).count();
+                if *beams < confirmed {
+                    return Err(format!("{} beams with {} confirmed matches seated", beams, confirmed));
+                }
+                if *beams > open {
+                    return Err(format!("{} beams when only {} seated couples could be matches", beams, open));
+                }
+            }
+        }
+        if let Some(space) = self.space.as_mut() {
+            space.observe(observation);
+            if !space.has_matching() {
+                return Err(String::from("no matching fits this and everything before it"));
+            }
+        }
+        return Ok(());
+    }
+}
+
+/* Tells the strategy what the cast learned in one week, without it choosing anything */
+pub fn replay_week(strategy: &mut dyn GameStrategy, week: &[Observation]) {
+    for observation in week.iter() {
+        match observation {
+            Observation::Ceremony { pairs, beams } => strategy.ceremony_feedback(*beams, pairs.clone()),
+            Observation::Booth { pair, is_match: true } => strategy.observe_booth(Feedback::Correct(pair.clone())),
+            Observation::Booth { pair, is_match: false } => strategy.observe_booth(Feedback::Wrong(pair.clone())),
+        }
+    }
+}
+
+/* The whole season, with same side couples excluded before the first week */
+pub fn replay(season: &Season, strategy: &mut dyn GameStrategy) {
+    strategy.exclude_pairs(&season.same_side_pairs());
+    for week in season.weeks.iter() {
+        replay_week(strategy, week);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeekReport {
+    pub week: usize,
+    pub beams: Option<usize>,
+    // None when the cast is too large to count exactly
+    pub matchings_left: Option<u64>,
+    pub solver_solved: bool,
+}
+
+/* When the answer was settled, when BruteForce knew it, and when the cast got every beam */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeasonReport {
+    pub weeks: Vec<WeekReport>,
+    pub determined_week: Option<usize>,
+    pub solver_week: Option<usize>,
+    pub cast_week: Option<usize>,
+}
+
+pub fn report(season: &Season) -> SeasonReport {
+    let mut space = SolutionSpace::new(&season.contestants);
+    for pair in season.same_side_pairs() {
+        space.rule_out(&pair);
+    }
+    let mut strategy = BruteForce::initialize(season.contestants.iter().collect());
+    strategy.exclude_pairs(&season.same_side_pairs());
+
+    let mut weeks = vec![];
+    for (i, week) in season.weeks.iter().enumerate() {
+        for observation in week.iter() {
+            space.observe(observation);
+        }
+        replay_week(&mut strategy, week);
+        let beams = week.iter().find_map(|observation| match observation {
+            Observation::Ceremony { beams, .. } => Some(*beams),
+            Observation::Booth { .. } => None,
+        });
+        weeks.push(WeekReport {
+            week: i + 1,
+            beams,
+            matchings_left: if season.is_exactly_countable() { Some(space.count()) } else { None },
+            solver_solved: strategy.is_solved(),
+        });
+    }
+
+    let everyone = season.contestants.len() / 2;
+    return SeasonReport {
+        determined_week: weeks.iter().find(|w| w.matchings_left == Some(1)).map(|w| w.week),
+        solver_week: weeks.iter().find(|w| w.solver_solved).map(|w| w.week),
+        cast_week: weeks.iter().find(|w| w.beams == Some(everyone)).map(|w| w.week),
+        weeks,
+    };
+}
+
+impl fmt::Display for SeasonReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "week  beams  matchings left  solver solved")?;
+        for week in self.weeks.iter() {
+            let beams = week.beams.map(|b| b.to_string()).unwrap_or_else(|| String::from("-"));
+            let left = week.matchings_left.map(|c| c.to_string()).unwrap_or_else(|| String::from("?"));
+            writeln!(f, "{:>4}  {:>5}  {:>14}  {}", week.week, beams, left, week.solver_solved)?;
+        }
+        let week = |w: Option<usize>| w.map(|w| format!("week {}", w)).unwrap_or_else(|| String::from("never"));
+        writeln!(f, "Determined by the observations: {}", week(self.determined_week))?;
+        writeln!(f, "Solved by BruteForce: {}", week(self.solver_week))?;
+        write!(f, "Every beam lit by the cast: {}", week(self.cast_week))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bruteforce::BruteForce;
+    use crate::gamestrategy::GameStrategy;
+    use crate::history::Observation;
+    use crate::season::{replay, report, Season};
+    use crate::solutionspace::SolutionSpace;
+
+    const SEASON: &str = "
+        # Made up, small enough to check by hand
+        name: Test season
+        side: Adam, Ben, Carl
+        side: Dana, Erin, Fay
+
+        week 1
+        ceremony: Adam+Dana, Ben+Erin, Carl+Fay = 1
+        booth: Adam+Dana = no match
+
+        week 2
+        ceremony: Adam+Erin, Ben+Fay, Carl+Dana = 1
+        booth: Ben+Dana = match  # leaves Adam+Erin and Carl+Fay
+
+        week 3
+        ceremony: Adam+Erin, Ben+Dana, Carl+Fay = 3
+    ";
+
+    #[test]
+    fn test_parse_season() {
+        let season = Season::parse(SEASON).unwrap();
+        assert_eq!(season.name, Some(String::from("Test season")));
+        assert_eq!(season.contestants.len(), 6);
+        assert_eq!(season.contestants[3].name, "Dana");
+        assert_eq!(season.same_side_pairs().len(), 6);
+        assert_eq!(season.weeks.len(), 3);
+        assert_eq!(season.history().observations.len(), 5);
+        assert!(matches!(season.weeks[0][0], Observation::Ceremony { beams: 1, .. }));
+        assert!(matches!(season.weeks[1][1], Observation::Booth { is_match: true, .. }));
+
+        let error = |text: &str| Season::parse(text).unwrap_err();
+        assert_eq!(error("cast: A, B\nweek 2").line, 2);
+        assert_eq!(error("cast: A, B\nweek 1\nbooth: A+C = match").line, 3);
+        assert_eq!(error("cast: A, B\nweek 1\nbooth: A+B = maybe").line, 3);
+        assert_eq!(error("cast: A, B\nweek 1\nceremony: A+B = 2").line, 3);
+        assert_eq!(error("cast: A, B\nweek 1\nceremony: A+B, B+A = 1").line, 3);
+        assert_eq!(error("cast: A, A").line, 1);
+        assert_eq!(error("booth: A+B = match").line, 1);
+        assert_eq!(error("cast: A, B\nside: C\nside: D").line, 0);
+
+        // Results that contradict what aired before them
+        let cast = "cast: A, B, C, D\nweek 1\n";
+        assert_eq!(error(&format!("{}booth: A+B = match\nbooth: A+C = match", cast)).line, 4);
+        assert_eq!(error(&format!("{}booth: A+B = match\nbooth: B+A = no match", cast)).line, 4);
+        assert_eq!(error(&format!("{}booth: A+B = match\nceremony: A+B, C+D = 0", cast)).line, 4);
+        assert_eq!(error(&format!("{}booth: A+B = no match\nceremony: A+B, C+D = 2", cast)).line, 4);
+        assert_eq!(error(&format!("{}ceremony: A+B, C+D = 1", cast)).line, 3);
+        assert_eq!(error("side: A, B\nside: C, D\nweek 1\nbooth: A+B = match").line, 4);
+    }
+
+    #[test]
+    fn test_replay_and_report() {
+        let season = Season::parse(SEASON).unwrap();
+        let mut strategy = BruteForce::initialize(season.contestants.iter().collect());
+        replay(&season, &mut strategy);
+        assert!(strategy.is_solved());
+
+        let report = report(&season);
+        assert_eq!(report.weeks.len(), 3);
+        assert_eq!(report.weeks[0].matchings_left, Some(2));
+        assert_eq!(report.determined_week, Some(2));
+        assert_eq!(report.cast_week, Some(3));
+        assert!(report.solver_week.unwrap() <= 3);
+
+        assert_eq!(SolutionSpace::from_history(&season.history()).count(), 1);
+        assert!(report.to_string().contains("Determined by the observations: week 2"));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::contestant::ContestantPairs;
+use crate::gamemaster::GameMaster;
+use crate::gamestrategy::{BoothRules, GameStrategy};
+use crate::memory;
+use crate::registry::{StrategyConfig, StrategyRegistry};
+use crate::stats::{GameStats, Method, StatsCollector};
+use log::info;
+
+/* Rounds it took to finish a game */
+pub fn game(num_players: i32, timeout_rounds: usize, booths: &BoothRules, registry: &StrategyRegistry, config: &StrategyConfig, collector: &mut StatsCollector) -> Result<u32, Box<dyn std::error::Error>> {
+    let game = play(num_players, timeout_rounds, booths, registry, config, collector)?;
+    return Ok(game.get_iterations() as u32);
+}
+
+/* Plays a single game to the end and hands back the game master, transcript included. What
+   happened along the way goes to the collector rather than stdout */
+pub fn play(num_players: i32, timeout_rounds: usize, booths: &BoothRules, registry: &StrategyRegistry, config: &StrategyConfig, collector: &mut StatsCollector) -> Result<GameMaster, Box<dyn std::error::Error>> {
+    return play_seeded(num_players, timeout_rounds, booths, registry, config, rand::random(), collector);
+}
+
+/* In debug builds, stops the game as soon as the strategy decides a pair against the hidden matching
+   or stops considering one of the hidden matches, proof or not */
+fn audit(game: &GameMaster, strategy: &dyn GameStrategy) {
+    if cfg!(debug_assertions) {
+        if let Some(unsound) = game.audit(strategy.proofs()) {
+            panic!("{}, which the hidden matching contradicts", unsound);
+        }
+        if let Some(pair) = game.discarded(strategy) {
+            panic!("{} is a hidden match but the strategy no longer considers it", pair);
+        }
+    }
+}
+
+/* Like play with everything random drawn from the seed, so a game can be played again. The seed
+   picks the hidden matching and the house's choices, and reaches the strategy through its rules */
+pub fn play_seeded(num_players: i32, timeout_rounds: usize, booths: &BoothRules, registry: &StrategyRegistry, config: &StrategyConfig, seed: u64, collector: &mut StatsCollector) -> Result<GameMaster, Box<dyn std::error::Error>> {
+    let baseline = memory::reset_peak();
+    let mut game = GameMaster::initialize_game(num_players, timeout_rounds as i32).with_booth_rules(booths.clone()).with_seed(seed);
+    let mut stats = GameStats::new(&config.to_string(), num_players as usize);
+
+    let mut strategy = registry.build(config, game.contestants())?;
+    strategy.game_start(&game.contestants(), &game.rules());
+    info!("-------------------------Correct pairing-------------------------\n{}", ContestantPairs(&game.matches));
+    while game.in_progress() {
+        
+        info!("\\/\\/\\/\\/\\/\\/\\/\\/ROUND {}\\/\\/\\/\\/\\/\\/\\/\\/", game.get_iterations());
+        let seating = game.seating_constraints();
+        let guess = stats.timings.time(Method::Ceremony, || strategy.ceremony_pairs_within(&seating));
+
+        let num_correct = game.ceremony(&guess)?;
+        if !game.in_progress() {
+            break;
+        }
+
+        stats.timings.time(Method::CeremonyFeedback, || strategy.ceremony_feedback(num_correct, guess));
+        audit(&game, strategy.as_ref());
+        info!("Num correct: {}", game.num_matched);
+
+        if let Some(voted) = game.voted_booths() {
+            for booth_result in game.booth_week(voted) {
+                stats.count_booth(&booth_result);
+                stats.timings.time(Method::BoothFeedback, || strategy.observe_booth(booth_result));
+            }
+            audit(&game, strategy.as_ref());
+            continue;
+        }
+        let booth_guesses = stats.timings.time(Method::Booth, || strategy.send_to_booths(game.booths_this_week()));
+        if let Some(explanation) = strategy.explain_last_decision() {
+            info!("{}", explanation);
+        }
+        for booth_result in game.booth_week(booth_guesses) {
+            stats.count_booth(&booth_result);
+            stats.timings.time(Method::BoothFeedback, || strategy.booth_feedback(booth_result));
+        }
+        audit(&game, strategy.as_ref());
+    }
+    strategy.game_end(&game.outcome().expect("The game should be over"));
+    stats.finish(&game, strategy.stats());
+    stats.peak_memory = memory::peak().saturating_sub(baseline);
+    collector.record(stats);
+    return Ok(game);
+}
@@ -0,0 +1,447 @@
+use crate::contestant::{Contestant, ContestantPair, Player};
+use crate::history::{History, Observation};
+use std::collections::HashMap;
+
+// Each player's possible partners are kept as a bitmask over player indices
+type Mask = u128;
+const MAX_CONTESTANTS: usize = 128;
+
+/* A ceremony as seen from each seat: who the player sat with, and how many beams lit up */
+#[derive(Debug, Clone)]
+struct CeremonyConstraint {
+    seat_partner: Vec<Option<usize>>,
+    beams: usize,
+}
+
+/* Every perfect matching of the cast that agrees with the observations so far. Unlike
+   BruteForce's possibilities this is exact, so it is only practical for show sized casts
+   (the unconstrained 16 contestant game has about two million matchings). */
+#[derive(Debug, Clone)]
+pub struct SolutionSpace<P: Contestant = Player> {
+    players: Vec<P>,
+    index: HashMap<P, usize>,
+    allowed: Vec<Mask>,
+    ceremonies: Vec<CeremonyConstraint>,
+}
+
+impl<P: Contestant> SolutionSpace<P> {
+    pub fn new(contestants: &[P]) -> SolutionSpace<P> {
+        if contestants.len() > MAX_CONTESTANTS {
+            panic!("Solution spaces support at most {} contestants, got {}", MAX_CONTESTANTS, contestants.len());
+        }
+        let mut players = contestants.to_vec();
+        players.sort();
+
+        let index = players.iter().enumerate().map(|(i, p)| (p.clone(), i)).collect();
+        let everyone: Mask = if players.len() == MAX_CONTESTANTS { Mask::MAX } else { (1 << players.len()) - 1 };
+        let allowed = (0..players.len()).map(|i| everyone & !(1 << i)).collect();
+
+        return SolutionSpace { players, index, allowed, ceremonies: vec![] };
+    }
+
+    pub fn from_history(history: &History<P>) -> SolutionSpace<P> {
+        let mut space = SolutionSpace::new(&history.contestants);
+        for pair in history.excluded.iter() {
+            space.rule_out(pair);
+        }
+        for observation in history.observations.iter() {
+            space.observe(observation);
+        }
+        return space;
+    }
+
+    pub fn players(&self) -> &[P] {
+        return &self.players;
+    }
+
+    pub fn index_of(&self, player: &P) -> usize {
+        return *self.index.get(player).unwrap_or_else(|| panic!("{} is not a part of the game!", player));
+    }
+
+    pub fn pair_indices(&self, pair: &ContestantPair<P>) -> (usize, usize) {
+        return (self.index_of(pair.get_a()), self.index_of(pair.get_b()));
+    }
+
+    pub fn observe(&mut self, observation: &Observation<P>) {
+        match observation {
+            Observation::Booth { pair, is_match: true } => self.confirm(pair),
+            Observation::Booth { pair, is_match: false } => self.rule_out(pair),
+            Observation::Ceremony { pairs, beams } => {
+                let mut seat_partner = vec![None; self.players.len()];
+                for pair in pairs.iter() {
+                    let (a, b) = self.pair_indices(pair);
+                    seat_partner[a] = Some(b);
+                    seat_partner[b] = Some(a);
+                }
+                self.ceremonies.push(CeremonyConstraint { seat_partner, beams: *beams });
+            }
+        }
+    }
+
+    pub fn confirm(&mut self, pair: &ContestantPair<P>) {
+        let (a, b) = self.pair_indices(pair);
+        for (i, allowed) in self.allowed.iter_mut().enumerate() {
+            if i != a && i != b {
+                *allowed &= !(1 << a) & !(1 << b);
+            }
+        }
+        self.allowed[a] &= 1 << b;
+        self.allowed[b] &= 1 << a;
+    }
+
+    pub fn rule_out(&mut self, pair: &ContestantPair<P>) {
+        let (a, b) = self.pair_indices(pair);
+        self.allowed[a] &= !(1 << b);
+        self.allowed[b] &= !(1 << a);
+    }
+
+    /* Whether the observations alone (ignoring ceremonies) still allow the pair */
+    pub fn is_allowed(&self, a: usize, b: usize) -> bool {
+        return self.allowed[a] & (1 << b) != 0;
+    }
+
+    pub fn summarize(&self) -> SpaceSummary<P> {
+        return self.partition(1, |_| 0).pop().unwrap();
+    }
+
+    pub fn count(&self) -> u64 {
+        let mut count = 0;
+        self.for_each_matching(|_| count += 1);
+        return count;
+    }
+
+    /* Splits the matchings into buckets by the key function (given each player's partner index)
+       and summarizes every bucket, all in a single pass over the space */
+    pub fn partition<F: FnMut(&[usize]) -> usize>(&self, num_buckets: usize, mut key: F) -> Vec<SpaceSummary<P>> {
+        let n = self.players.len();
+        let mut summaries: Vec<SpaceSummary<P>> = (0..num_buckets)
+            .map(|_| SpaceSummary { players: self.players.clone(), count: 0, pair_counts: vec![vec![0; n]; n] })
+            .collect();
+
+        self.for_each_matching(|partner| {
+            let summary = &mut summaries[key(partner)];
+            summary.count += 1;
+            for (i, &j) in partner.iter().enumerate() {
+                summary.pair_counts[i][j] += 1;
+            }
+        });
+        return summaries;
+    }
+
+    /* Calls visit with each consistent matching, given as the partner index of every player */
+    pub fn for_each_matching<F: FnMut(&[usize])>(&self, visit: F) {
+        self.search(visit, false);
+    }
+
+    /* Whether any matching is consistent, stopping at the first one found */
+    pub fn has_matching(&self) -> bool {
+        let mut found = false;
+        self.search(|_| found = true, true);
+        return found;
+    }
+
+    fn search<F: FnMut(&[usize])>(&self, visit: F, first_only: bool) {
+        let n = self.players.len();
+        if n % 2 == 1 {
+            return;
+        }
+
+        // Ceremony pairs that were ruled out some other way can never light up, so drop them
+        let ceremonies: Vec<CeremonyConstraint> = self
+            .ceremonies
+            .iter()
+            .map(|ceremony| CeremonyConstraint {
+                seat_partner: (0..n)
+                    .map(|i| ceremony.seat_partner[i].filter(|&j| self.is_allowed(i, j)))
+                    .collect(),
+                beams: ceremony.beams,
+            })
+            .collect();
+        let open: Vec<usize> = ceremonies
+            .iter()
+            .map(|ceremony| ceremony.seat_partner.iter().filter(|p| p.is_some()).count() / 2)
+            .collect();
+        if ceremonies.iter().zip(open.iter()).any(|(ceremony, open)| ceremony.beams > *open) {
+            return;
+        }
+
+        let mut search = Search {
+            allowed: &self.allowed,
+            hits: vec![0; ceremonies.len()],
+            open,
+            ceremonies,
+            partner: vec![usize::MAX; n],
+            unmatched: if n == MAX_CONTESTANTS { Mask::MAX } else { (1 << n) - 1 },
+            visit,
+            first_only,
+            done: false,
+        };
+        search.run();
+    }
+}
+
+/* Depth first search over the matchings. Ceremony beam counts are checked as pairs are added:
+   hits can't go over the beams, and hits plus still possible pairs can't fall under them. */
+struct Search<'a, F: FnMut(&[usize])> {
+    allowed: &'a [Mask],
+    ceremonies: Vec<CeremonyConstraint>,
+    hits: Vec<usize>,
+    open: Vec<usize>,
+    partner: Vec<usize>,
+    unmatched: Mask,
+    visit: F,
+    // Stop the whole search once a matching has been visited
+    first_only: bool,
+    done: bool,
+}
+
+impl<'a, F: FnMut(&[usize])> Search<'a, F> {
+    fn run(&mut self) {
+        if self.unmatched == 0 {
+            (self.visit)(&self.partner);
+            self.done = self.first_only;
+            return;
+        }
+
+        // Branch on the player with the fewest partners left
+        let mut player = 0;
+        let mut fewest = u32::MAX;
+        let mut remaining = self.unmatched;
+        while remaining != 0 {
+            let i = remaining.trailing_zeros() as usize;
+            remaining &= remaining - 1;
+            let options = (self.allowed[i] & self.unmatched).count_ones();
+            if options == 0 {
+                return;
+            }
+            if options < fewest {
+                fewest = options;
+                player = i;
+            }
+        }
+
+        let mut options = self.allowed[player] & self.unmatched;
+        while options != 0 {
+            let other = options.trailing_zeros() as usize;
+            options &= options - 1;
+
+            let consistent = self.pair_up(player, other);
+            if consistent {
+                self.run();
+            }
+            self.split_up(player, other);
+            if self.done {
+                return;
+            }
+        }
+    }
+
+    fn pair_up(&mut self, a: usize, b: usize) -> bool {
+        self.update_ceremonies(a, b, true);
+        self.unmatched &= !(1 << a) & !(1 << b);
+        self.partner[a] = b;
+        self.partner[b] = a;
+
+        return self
+            .ceremonies
+            .iter()
+            .enumerate()
+            .all(|(c, ceremony)| self.hits[c] <= ceremony.beams && self.hits[c] + self.open[c] >= ceremony.beams);
+    }
+
+    fn split_up(&mut self, a: usize, b: usize) {
+        self.partner[a] = usize::MAX;
+        self.partner[b] = usize::MAX;
+        self.unmatched |= (1 << a) | (1 << b);
+        self.update_ceremonies(a, b, false);
+    }
+
+    /* Must see the unmatched set from before the pair was added, both when adding and undoing */
+    fn update_ceremonies(&mut self, a: usize, b: usize, adding: bool) {
+        for (c, ceremony) in self.ceremonies.iter().enumerate() {
+            let (hit, closed) = if ceremony.seat_partner[a] == Some(b) {
+                (1, 1)
+            } else {
+                let still_open = |seat: Option<usize>| match seat {
+                    Some(k) => self.unmatched & (1 << k) != 0,
+                    None => false,
+                };
+                (0, still_open(ceremony.seat_partner[a]) as usize + still_open(ceremony.seat_partner[b]) as usize)
+            };
+            if adding {
+                self.hits[c] += hit;
+                self.open[c] -= closed;
+            } else {
+                self.hits[c] -= hit;
+                self.open[c] += closed;
+            }
+        }
+    }
+}
+
+/* How many matchings are left and how often each pair shows up in them */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceSummary<P: Contestant = Player> {
+    players: Vec<P>,
+    pub count: u64,
+    pair_counts: Vec<Vec<u64>>,
+}
+
+impl<P: Contestant> SpaceSummary<P> {
+    pub fn players(&self) -> &[P] {
+        return &self.players;
+    }
+
+    fn index_of(&self, player: &P) -> usize {
+        return self
+            .players
+            .iter()
+            .position(|p| p == player)
+            .unwrap_or_else(|| panic!("{} is not a part of the game!", player));
+    }
+
+    pub fn pair_count(&self, pair: &ContestantPair<P>) -> u64 {
+        return self.pair_counts[self.index_of(pair.get_a())][self.index_of(pair.get_b())];
+    }
+
+    pub fn probability(&self, pair: &ContestantPair<P>) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        return self.pair_count(pair) as f64 / self.count as f64;
+    }
+
+    /* Probability of every pair, indexed in the same order as players() */
+    pub fn probability_matrix(&self) -> Vec<Vec<f64>> {
+        return self
+            .pair_counts
+            .iter()
+            .map(|row| row.iter().map(|&c| if self.count == 0 { 0.0 } else { c as f64 / self.count as f64 }).collect())
+            .collect();
+    }
+
+    /* Pairs that are in every remaining matching */
+    pub fn forced_pairs(&self) -> Vec<ContestantPair<P>> {
+        return self.pairs_where(|count| self.count > 0 && count == self.count);
+    }
+
+    /* Pairs that are in none of the remaining matchings */
+    pub fn impossible_pairs(&self) -> Vec<ContestantPair<P>> {
+        return self.pairs_where(|count| count == 0);
+    }
+
+    fn pairs_where<F: Fn(u64) -> bool>(&self, keep: F) -> Vec<ContestantPair<P>> {
+        let mut pairs = vec![];
+        for i in 0..self.players.len() {
+            for j in i + 1..self.players.len() {
+                if keep(self.pair_counts[i][j]) {
+                    pairs.push(ContestantPair::new(self.players[i].clone(), self.players[j].clone()));
+                }
+            }
+        }
+        return pairs;
+    }
+
+    /* Bits of uncertainty left, assuming every remaining matching is equally likely */
+    pub fn entropy(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        return (self.count as f64).log2();
+    }
+
+    pub fn is_solved(&self) -> bool {
+        return self.count == 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::contestant::ContestantPair;
+    use crate::history::{History, Observation};
+    use crate::solutionspace::SolutionSpace;
+    use crate::utils::{contestants_to_pairs, gen_contestants, get_matches};
+
+    #[test]
+    fn test_unconstrained_count() {
+        // (n - 1)!! perfect matchings
+        assert_eq!(SolutionSpace::new(&gen_contestants(4)).count(), 3);
+        assert_eq!(SolutionSpace::new(&gen_contestants(6)).count(), 15);
+        assert_eq!(SolutionSpace::new(&gen_contestants(10)).count(), 945);
+        assert_eq!(SolutionSpace::new(&gen_contestants(3)).count(), 0);
+    }
+
+    #[test]
+    fn test_booth_observations() {
+        let c = gen_contestants(6);
+        let mut space = SolutionSpace::new(&c);
+
+        space.observe(&Observation::Booth { pair: ContestantPair::new(c[0].clone(), c[1].clone()), is_match: false });
+        assert_eq!(space.count(), 12);
+
+        space.observe(&Observation::Booth { pair: ContestantPair::new(c[0].clone(), c[2].clone()), is_match: true });
+        let summary = space.summarize();
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.probability(&ContestantPair::new(c[2].clone(), c[0].clone())), 1.0);
+        assert!(summary.forced_pairs().contains(&ContestantPair::new(c[0].clone(), c[2].clone())));
+        assert!(summary.impossible_pairs().contains(&ContestantPair::new(c[1].clone(), c[2].clone())));
+    }
+
+    #[test]
+    fn test_ceremony_observations() {
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut history = History::new(c.clone());
+
+        // Zero beams rules out all 4 pairs: 105 - matchings using any of them
+        history.record(Observation::Ceremony { pairs: get_matches(&truth, 0, 4), beams: 0 });
+        let summary = SolutionSpace::from_history(&history).summarize();
+        for pair in get_matches(&truth, 0, 4).iter() {
+            assert_eq!(summary.probability(pair), 0.0);
+        }
+
+        // A full house leaves only that matching
+        history.record(Observation::Ceremony { pairs: truth.clone(), beams: 4 });
+        let summary = SolutionSpace::from_history(&history).summarize();
+        assert_eq!(summary.count, 1);
+        assert!(summary.is_solved());
+        assert_eq!(summary.forced_pairs().len(), 4);
+        assert!(SolutionSpace::from_history(&history).has_matching());
+
+        // And pairs excluded from the start take that one away too
+        let history = history.with_excluded(vec![truth[0].clone()]);
+        assert!(!SolutionSpace::from_history(&history).has_matching());
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        // Every matching counted must actually have the right number of beams
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let ceremony = get_matches(&truth, 2, 2);
+        let mut space = SolutionSpace::new(&c);
+        space.observe(&Observation::Ceremony { pairs: ceremony.clone(), beams: 2 });
+
+        let mut count = 0;
+        space.for_each_matching(|partner| {
+            let beams = ceremony
+                .iter()
+                .filter(|pair| {
+                    let (a, b) = space.pair_indices(pair);
+                    partner[a] == b
+                })
+                .count();
+            assert_eq!(beams, 2);
+            count += 1;
+        });
+
+        // All 105 matchings split by how many of the ceremony's 4 pairs they share
+        let mut by_beams = [0u64; 5];
+        SolutionSpace::new(&c).for_each_matching(|partner| {
+            let beams = ceremony.iter().filter(|pair| partner[space.pair_indices(pair).0] == space.pair_indices(pair).1).count();
+            by_beams[beams] += 1;
+        });
+        assert_eq!(count, by_beams[2]);
+        assert_eq!(by_beams.iter().sum::<u64>(), 105);
+    }
+}
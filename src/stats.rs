@@ -0,0 +1,652 @@
+use crate::gamemaster::GameMaster;
+use crate::gamestrategy::{Feedback, GameOutcome};
+use crate::history::Observation;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/* The strategy calls a game loop makes, for timing */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Ceremony,
+    CeremonyFeedback,
+    Booth,
+    BoothFeedback,
+}
+
+/* Wall-clock time spent in each strategy method over a game */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodTimings {
+    pub ceremony: Duration,
+    pub ceremony_feedback: Duration,
+    pub booth: Duration,
+    pub booth_feedback: Duration,
+    // Decisions made, to tell what a single one costs. Feedback calls follow from these
+    pub ceremony_calls: usize,
+    pub booth_calls: usize,
+}
+
+impl MethodTimings {
+    /* Runs the call and adds how long it took to the method's total */
+    pub fn time<T, F: FnOnce() -> T>(&mut self, method: Method, call: F) -> T {
+        let start = Instant::now();
+        let result = call();
+        let elapsed = start.elapsed();
+        match method {
+            Method::Ceremony => {
+                self.ceremony += elapsed;
+                self.ceremony_calls += 1;
+            }
+            Method::CeremonyFeedback => self.ceremony_feedback += elapsed,
+            Method::Booth => {
+                self.booth += elapsed;
+                self.booth_calls += 1;
+            }
+            Method::BoothFeedback => self.booth_feedback += elapsed,
+        }
+        return result;
+    }
+
+    /* Mean time of one ceremony or booth decision, if any were made. Feedback isn't a decision */
+    pub fn per_decision(&self, method: Method) -> Option<Duration> {
+        let (total, calls) = match method {
+            Method::Ceremony => (self.ceremony, self.ceremony_calls),
+            Method::Booth => (self.booth, self.booth_calls),
+            Method::CeremonyFeedback | Method::BoothFeedback => return None,
+        };
+        if calls == 0 {
+            return None;
+        }
+        return Some(total / calls as u32);
+    }
+
+    pub fn total(&self) -> Duration {
+        return self.ceremony + self.ceremony_feedback + self.booth + self.booth_feedback;
+    }
+}
+
+/* Everything recorded about a single game */
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameStats {
+    // Picked the hidden matching, when the game was seeded
+    pub seed: Option<u64>,
+    pub strategy: String,
+    pub num_contestants: usize,
+    pub won: bool,
+    pub rounds: usize,
+    pub ceremonies: usize,
+    pub zero_beam_ceremonies: usize,
+    // Booths sent, hidden results included, and how many of those were revealed as matches
+    pub booths: usize,
+    pub correct_booths: usize,
+    pub timings: MethodTimings,
+    // Most heap the game had allocated at once beyond what was already in use when it began. The
+    // count covers the whole process, so it is only right when games aren't played concurrently
+    pub peak_memory: usize,
+    // Whatever the strategy reports about itself at the end, like how often it used its saved rounds
+    pub strategy_stats: Vec<(String, f64)>,
+}
+
+impl GameStats {
+    pub fn new(strategy: &str, num_contestants: usize) -> GameStats {
+        return GameStats {
+            seed: None,
+            strategy: strategy.to_string(),
+            num_contestants,
+            won: false,
+            rounds: 0,
+            ceremonies: 0,
+            zero_beam_ceremonies: 0,
+            booths: 0,
+            correct_booths: 0,
+            timings: MethodTimings::default(),
+            peak_memory: 0,
+            strategy_stats: vec![],
+        };
+    }
+
+    pub fn count_booth(&mut self, feedback: &Feedback) {
+        self.booths += 1;
+        if let Feedback::Correct(_) = feedback {
+            self.correct_booths += 1;
+        }
+    }
+
+    /* Fills in the seed, the outcome and the ceremonies from the finished game */
+    pub fn finish(&mut self, game: &GameMaster, strategy_stats: Vec<(String, f64)>) {
+        self.seed = game.seed();
+        (self.won, self.rounds) = match game.outcome().expect("The game should be over") {
+            GameOutcome::Won { iterations } => (true, iterations),
+            GameOutcome::TimedOut { iterations } => (false, iterations),
+        };
+        for observation in game.history().observations.iter() {
+            if let Observation::Ceremony { beams, .. } = observation {
+                self.ceremonies += 1;
+                if *beams == 0 {
+                    self.zero_beam_ceremonies += 1;
+                }
+            }
+        }
+        self.strategy_stats = strategy_stats;
+    }
+
+    pub fn correct_booth_rate(&self) -> f64 {
+        if self.booths == 0 {
+            return 0.0;
+        }
+        return self.correct_booths as f64 / self.booths as f64;
+    }
+
+    pub fn strategy_stat(&self, name: &str) -> Option<f64> {
+        return self.strategy_stats.iter().find(|(stat, _)| stat == name).map(|(_, value)| *value);
+    }
+}
+
+/* Columns every per game CSV starts with. Strategy stats follow */
+const RECORD_COLUMNS: [&str; 17] = [
+    "seed",
+    "strategy",
+    "num_contestants",
+    "won",
+    "rounds",
+    "ceremonies",
+    "zero_beam_ceremonies",
+    "booths",
+    "correct_booths",
+    "correct_booth_rate",
+    "ceremony_ms",
+    "ceremony_feedback_ms",
+    "booth_ms",
+    "booth_feedback_ms",
+    "ceremony_calls",
+    "booth_calls",
+    "peak_memory_bytes",
+];
+
+/* A game as written to disk, with times in milliseconds so other tools can read them */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub seed: Option<u64>,
+    pub strategy: String,
+    pub num_contestants: usize,
+    pub won: bool,
+    pub rounds: usize,
+    pub ceremonies: usize,
+    pub zero_beam_ceremonies: usize,
+    pub booths: usize,
+    pub correct_booths: usize,
+    pub ceremony_ms: f64,
+    pub ceremony_feedback_ms: f64,
+    pub booth_ms: f64,
+    pub booth_feedback_ms: f64,
+    // Missing from records written before they were tracked
+    #[serde(default)]
+    pub ceremony_calls: usize,
+    #[serde(default)]
+    pub booth_calls: usize,
+    #[serde(default)]
+    pub peak_memory_bytes: usize,
+    pub strategy_stats: BTreeMap<String, f64>,
+}
+
+impl From<&GameStats> for GameRecord {
+    fn from(game: &GameStats) -> GameRecord {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        return GameRecord {
+            seed: game.seed,
+            strategy: game.strategy.clone(),
+            num_contestants: game.num_contestants,
+            won: game.won,
+            rounds: game.rounds,
+            ceremonies: game.ceremonies,
+            zero_beam_ceremonies: game.zero_beam_ceremonies,
+            booths: game.booths,
+            correct_booths: game.correct_booths,
+            ceremony_ms: ms(game.timings.ceremony),
+            ceremony_feedback_ms: ms(game.timings.ceremony_feedback),
+            booth_ms: ms(game.timings.booth),
+            booth_feedback_ms: ms(game.timings.booth_feedback),
+            ceremony_calls: game.timings.ceremony_calls,
+            booth_calls: game.timings.booth_calls,
+            peak_memory_bytes: game.peak_memory,
+            strategy_stats: game.strategy_stats.iter().cloned().collect(),
+        };
+    }
+}
+
+impl From<GameRecord> for GameStats {
+    fn from(record: GameRecord) -> GameStats {
+        let duration = |ms: f64| Duration::from_secs_f64(ms.max(0.0) / 1000.0);
+        return GameStats {
+            seed: record.seed,
+            strategy: record.strategy,
+            num_contestants: record.num_contestants,
+            won: record.won,
+            rounds: record.rounds,
+            ceremonies: record.ceremonies,
+            zero_beam_ceremonies: record.zero_beam_ceremonies,
+            booths: record.booths,
+            correct_booths: record.correct_booths,
+            timings: MethodTimings {
+                ceremony: duration(record.ceremony_ms),
+                ceremony_feedback: duration(record.ceremony_feedback_ms),
+                booth: duration(record.booth_ms),
+                booth_feedback: duration(record.booth_feedback_ms),
+                ceremony_calls: record.ceremony_calls,
+                booth_calls: record.booth_calls,
+            },
+            peak_memory: record.peak_memory_bytes,
+            strategy_stats: record.strategy_stats.into_iter().collect(),
+        };
+    }
+}
+
+/* A record that couldn't be read back, with the line it is on */
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+/* Averages over every game one strategy played */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub strategy: String,
+    pub games: usize,
+    pub wins: usize,
+    pub mean_rounds: f64,
+    pub max_rounds: usize,
+    pub mean_booths: f64,
+    pub correct_booth_rate: f64,
+    pub mean_zero_beam_ceremonies: f64,
+    pub mean_timings: MethodTimings,
+    // Means of the strategy's own stats, over the games that reported them
+    pub strategy_stats: Vec<(String, f64)>,
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}: won {} of {} games", self.strategy, self.wins, self.games)?;
+        writeln!(f, "  rounds: {:.2} on average, {} at most", self.mean_rounds, self.max_rounds)?;
+        writeln!(f, "  booths: {:.2} a game, {:.1}% matches", self.mean_booths, 100.0 * self.correct_booth_rate)?;
+        writeln!(f, "  ceremonies without a beam: {:.2} a game", self.mean_zero_beam_ceremonies)?;
+        write!(
+            f,
+            "  time a game: {:?} ceremony, {:?} ceremony feedback, {:?} booth, {:?} booth feedback",
+            self.mean_timings.ceremony, self.mean_timings.ceremony_feedback, self.mean_timings.booth, self.mean_timings.booth_feedback
+        )?;
+        for (name, value) in self.strategy_stats.iter() {
+            write!(f, "\n  {}: {:.2}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/* Collects the stats of many games so a simulation can report on them at the end instead of
+   printing after every game */
+#[derive(Debug, Clone, Default)]
+pub struct StatsCollector {
+    pub games: Vec<GameStats>,
+}
+
+impl StatsCollector {
+    pub fn new() -> StatsCollector {
+        return StatsCollector { games: vec![] };
+    }
+
+    pub fn record(&mut self, stats: GameStats) {
+        self.games.push(stats);
+    }
+
+    /* Every strategy stat any game reported, in a fixed order */
+    fn strategy_stat_names(&self) -> Vec<String> {
+        let names: BTreeSet<&String> = self.games.iter().flat_map(|game| game.strategy_stats.iter().map(|(name, _)| name)).collect();
+        return names.into_iter().cloned().collect();
+    }
+
+    /* One aggregate per strategy, in the order they first played */
+    pub fn aggregate(&self) -> Vec<Aggregate> {
+        let mut strategies: Vec<&str> = vec![];
+        for game in self.games.iter() {
+            if !strategies.contains(&game.strategy.as_str()) {
+                strategies.push(&game.strategy);
+            }
+        }
+        let names = self.strategy_stat_names();
+        return strategies
+            .into_iter()
+            .map(|strategy| {
+                let games: Vec<&GameStats> = self.games.iter().filter(|game| game.strategy == strategy).collect();
+                let count = games.len();
+                let mean = |value: &dyn Fn(&GameStats) -> f64| games.iter().map(|&game| value(game)).sum::<f64>() / count as f64;
+                let mean_time = |value: &dyn Fn(&MethodTimings) -> Duration| games.iter().map(|game| value(&game.timings)).sum::<Duration>() / count as u32;
+                let booths: usize = games.iter().map(|game| game.booths).sum();
+                let correct: usize = games.iter().map(|game| game.correct_booths).sum();
+                let strategy_stats = names
+                    .iter()
+                    .filter_map(|name| {
+                        let values: Vec<f64> = games.iter().filter_map(|game| game.strategy_stat(name)).collect();
+                        if values.is_empty() {
+                            return None;
+                        }
+                        Some((name.clone(), values.iter().sum::<f64>() / values.len() as f64))
+                    })
+                    .collect();
+                Aggregate {
+                    strategy: strategy.to_string(),
+                    games: count,
+                    wins: games.iter().filter(|game| game.won).count(),
+                    mean_rounds: mean(&|game| game.rounds as f64),
+                    max_rounds: games.iter().map(|game| game.rounds).max().unwrap_or(0),
+                    mean_booths: mean(&|game| game.booths as f64),
+                    correct_booth_rate: if booths == 0 { 0.0 } else { correct as f64 / booths as f64 },
+                    mean_zero_beam_ceremonies: mean(&|game| game.zero_beam_ceremonies as f64),
+                    mean_timings: MethodTimings {
+                        ceremony: mean_time(&|timings| timings.ceremony),
+                        ceremony_feedback: mean_time(&|timings| timings.ceremony_feedback),
+                        booth: mean_time(&|timings| timings.booth),
+                        booth_feedback: mean_time(&|timings| timings.booth_feedback),
+                        ceremony_calls: games.iter().map(|game| game.timings.ceremony_calls).sum::<usize>() / count,
+                        booth_calls: games.iter().map(|game| game.timings.booth_calls).sum::<usize>() / count,
+                    },
+                    strategy_stats,
+                }
+            })
+            .collect();
+    }
+
+    /* One row per game. Strategy stats get a column each, left empty for games that didn't report them */
+    pub fn to_csv(&self) -> String {
+        let names = self.strategy_stat_names();
+        let mut header: Vec<&str> = RECORD_COLUMNS.to_vec();
+        header.extend(names.iter().map(|name| name.as_str()));
+        let mut csv = header.join(",") + "\n";
+        for game in self.games.iter() {
+            let record = GameRecord::from(game);
+            let mut row = vec![
+                record.seed.map(|seed| seed.to_string()).unwrap_or_default(),
+                csv_field(&record.strategy),
+                record.num_contestants.to_string(),
+                record.won.to_string(),
+                record.rounds.to_string(),
+                record.ceremonies.to_string(),
+                record.zero_beam_ceremonies.to_string(),
+                record.booths.to_string(),
+                record.correct_booths.to_string(),
+                format!("{:.4}", game.correct_booth_rate()),
+                format!("{:.3}", record.ceremony_ms),
+                format!("{:.3}", record.ceremony_feedback_ms),
+                format!("{:.3}", record.booth_ms),
+                format!("{:.3}", record.booth_feedback_ms),
+                record.ceremony_calls.to_string(),
+                record.booth_calls.to_string(),
+                record.peak_memory_bytes.to_string(),
+            ];
+            row.extend(names.iter().map(|name| record.strategy_stats.get(name).map(|value| value.to_string()).unwrap_or_default()));
+            csv += &(row.join(",") + "\n");
+        }
+        return csv;
+    }
+
+    /* Reads back what to_csv wrote. Columns are found by name, and any past the fixed ones are strategy stats */
+    pub fn from_csv(text: &str) -> Result<StatsCollector, RecordError> {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let header = match lines.next() {
+            Some((_, header)) => split_csv_line(header),
+            None => return Ok(StatsCollector::new()),
+        };
+        let mut collector = StatsCollector::new();
+        for (i, line) in lines {
+            let error = |message: String| RecordError { line: i + 1, message };
+            let fields = split_csv_line(line);
+            if fields.len() != header.len() {
+                return Err(error(format!("expected {} fields, found {}", header.len(), fields.len())));
+            }
+            let field = |column: &str| -> Result<&str, RecordError> {
+                let position = header.iter().position(|name| name == column).ok_or_else(|| error(format!("no '{}' column", column)))?;
+                return Ok(fields[position].as_str());
+            };
+            fn parsed<T: std::str::FromStr>(value: &str, column: &str, line: usize) -> Result<T, RecordError> {
+                return value.parse().map_err(|_| RecordError { line, message: format!("'{}' is not a valid {}", value, column) });
+            }
+            let number = |column: &str| -> Result<usize, RecordError> { parsed(field(column)?, column, i + 1) };
+            let millis = |column: &str| -> Result<f64, RecordError> { parsed(field(column)?, column, i + 1) };
+            // Columns added after the first records were written count as zero when absent
+            let counted = |column: &str| -> Result<usize, RecordError> {
+                if !header.iter().any(|name| name == column) {
+                    return Ok(0);
+                }
+                return number(column);
+            };
+            let seed = field("seed")?;
+            let mut record = GameRecord {
+                seed: if seed.is_empty() { None } else { Some(parsed(seed, "seed", i + 1)?) },
+                strategy: field("strategy")?.to_string(),
+                num_contestants: number("num_contestants")?,
+                won: parsed(field("won")?, "won", i + 1)?,
+                rounds: number("rounds")?,
+                ceremonies: number("ceremonies")?,
+                zero_beam_ceremonies: number("zero_beam_ceremonies")?,
+                booths: number("booths")?,
+                correct_booths: number("correct_booths")?,
+                ceremony_ms: millis("ceremony_ms")?,
+                ceremony_feedback_ms: millis("ceremony_feedback_ms")?,
+                booth_ms: millis("booth_ms")?,
+                booth_feedback_ms: millis("booth_feedback_ms")?,
+                ceremony_calls: counted("ceremony_calls")?,
+                booth_calls: counted("booth_calls")?,
+                peak_memory_bytes: counted("peak_memory_bytes")?,
+                strategy_stats: BTreeMap::new(),
+            };
+            for (name, value) in header.iter().zip(fields.iter()) {
+                if !RECORD_COLUMNS.contains(&name.as_str()) && !value.is_empty() {
+                    record.strategy_stats.insert(name.clone(), parsed(value, name, i + 1)?);
+                }
+            }
+            collector.record(GameStats::from(record));
+        }
+        return Ok(collector);
+    }
+
+    /* One JSON object per game, per line */
+    pub fn to_jsonl(&self) -> String {
+        return self
+            .games
+            .iter()
+            .map(|game| serde_json::to_string(&GameRecord::from(game)).expect("Records only hold plain data") + "\n")
+            .collect();
+    }
+
+    pub fn from_jsonl(text: &str) -> Result<StatsCollector, RecordError> {
+        let mut collector = StatsCollector::new();
+        for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let record: GameRecord = serde_json::from_str(line).map_err(|error| RecordError { line: i + 1, message: error.to_string() })?;
+            collector.record(GameStats::from(record));
+        }
+        return Ok(collector);
+    }
+
+    /* Reads records written by write_records, as CSV or JSON Lines going by the extension */
+    pub fn load(location: &str) -> Result<StatsCollector, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(location)?;
+        if location.ends_with(".jsonl") {
+            return Ok(StatsCollector::from_jsonl(&text)?);
+        }
+        return Ok(StatsCollector::from_csv(&text)?);
+    }
+
+    /* One row per strategy with the aggregates */
+    pub fn summary_csv(&self) -> String {
+        let mut csv = String::from("strategy,games,wins,mean_rounds,max_rounds,mean_booths,correct_booth_rate,mean_zero_beam_ceremonies,mean_ms\n");
+        for aggregate in self.aggregate() {
+            csv += &format!(
+                "{},{},{},{:.4},{},{:.4},{:.4},{:.4},{:.3}\n",
+                csv_field(&aggregate.strategy),
+                aggregate.games,
+                aggregate.wins,
+                aggregate.mean_rounds,
+                aggregate.max_rounds,
+                aggregate.mean_booths,
+                aggregate.correct_booth_rate,
+                aggregate.mean_zero_beam_ceremonies,
+                aggregate.mean_timings.total().as_secs_f64() * 1000.0
+            );
+        }
+        return csv;
+    }
+
+    /* Writes the per game records as `<stem>.csv` and `<stem>.jsonl`, with the summary in `<stem>_summary.csv` */
+    pub fn write_records(&self, stem: &str) -> std::io::Result<()> {
+        if let Some(folder) = std::path::Path::new(stem).parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+        std::fs::write(format!("{}.csv", stem), self.to_csv())?;
+        std::fs::write(format!("{}.jsonl", stem), self.to_jsonl())?;
+        std::fs::write(format!("{}_summary.csv", stem), self.summary_csv())?;
+        return Ok(());
+    }
+}
+
+/* Splits a CSV row written by csv_field, keeping quoted commas */
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                fields.last_mut().unwrap().push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    return fields;
+}
+
+/* Strategy configs can hold commas, which would split the column */
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+    return value.to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::contestant::{ContestantPair, Player};
+    use crate::gamemaster::GameMaster;
+    use crate::gamestrategy::Feedback;
+    use crate::stats::{GameStats, Method, RecordError, StatsCollector};
+
+    #[test]
+    fn test_game_stats() {
+        let mut game = GameMaster::initialize_game(6, 10);
+        let truth = game.matches.clone();
+        let mut stats = GameStats::new("bruteforce", 6);
+
+        let pair = |a: &Player, b: &Player| ContestantPair::new(a.clone(), b.clone());
+        let wrong = vec![pair(truth[0].get_a(), truth[1].get_a()), pair(truth[1].get_b(), truth[2].get_a()), pair(truth[2].get_b(), truth[0].get_b())];
+        let beams = stats.timings.time(Method::Ceremony, || game.ceremony(&wrong));
+        assert_eq!(beams, Ok(0));
+        for feedback in game.booth_week(vec![truth[0].clone()]) {
+            stats.count_booth(&feedback);
+        }
+        stats.count_booth(&Feedback::Wrong(pair(truth[0].get_a(), truth[1].get_a())));
+        game.ceremony(&truth).unwrap();
+        stats.finish(&game, vec![(String::from("rounds_used"), 2.0)]);
+
+        assert!(stats.won);
+        assert_eq!((stats.rounds, stats.ceremonies, stats.zero_beam_ceremonies), (1, 2, 1));
+        assert_eq!((stats.booths, stats.correct_booths), (2, 1));
+        assert_eq!(stats.correct_booth_rate(), 0.5);
+        assert!(stats.timings.ceremony > std::time::Duration::ZERO);
+        assert_eq!(stats.timings.ceremony_calls, 1);
+        assert_eq!(stats.timings.per_decision(Method::Ceremony), Some(stats.timings.ceremony));
+        assert_eq!(stats.timings.per_decision(Method::Booth), None);
+        assert_eq!(stats.strategy_stat("rounds_used"), Some(2.0));
+    }
+
+    #[test]
+    fn test_aggregate_and_csv() {
+        let mut collector = StatsCollector::new();
+        for (strategy, rounds, booths) in [("bruteforce", 4, 4), ("bruteforce", 6, 6), ("bruteforce:deduction=false,sound=true", 9, 9)] {
+            let mut stats = GameStats::new(strategy, 8);
+            stats.won = true;
+            stats.rounds = rounds;
+            stats.booths = booths;
+            stats.correct_booths = 2;
+            if strategy == "bruteforce" {
+                stats.strategy_stats = vec![(String::from("rounds_used"), rounds as f64 / 2.0)];
+            }
+            collector.record(stats);
+        }
+
+        let aggregates = collector.aggregate();
+        assert_eq!(aggregates.len(), 2);
+        assert_eq!((aggregates[0].games, aggregates[0].mean_rounds, aggregates[0].max_rounds), (2, 5.0, 6));
+        assert_eq!(aggregates[0].correct_booth_rate, 0.4);
+        assert_eq!(aggregates[0].strategy_stats, vec![(String::from("rounds_used"), 2.5)]);
+        assert!(aggregates[1].strategy_stats.is_empty());
+
+        let csv = collector.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("seed,strategy,num_contestants,won,rounds,") && lines[0].ends_with(",rounds_used"));
+        assert!(lines[1].starts_with(",bruteforce,8,true,4,") && lines[1].ends_with(",2"));
+        assert!(lines[3].starts_with(",\"bruteforce:deduction=false,sound=true\",8,true,9,") && lines[3].ends_with(','));
+
+        let summary = collector.summary_csv();
+        assert_eq!(summary.lines().count(), 3);
+        assert!(summary.lines().nth(1).unwrap().starts_with("bruteforce,2,2,5.0000,6,5.0000,0.4000,0.0000,"));
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        let mut collector = StatsCollector::new();
+        let mut stats = GameStats::new("bruteforce:opening_book=a,round_optimization=false", 16);
+        stats.seed = Some(42);
+        stats.won = true;
+        stats.rounds = 7;
+        stats.booths = 7;
+        stats.correct_booths = 3;
+        stats.timings.booth = std::time::Duration::from_micros(1500);
+        stats.timings.booth_calls = 3;
+        stats.peak_memory = 4096;
+        stats.strategy_stats = vec![(String::from("rounds_used"), 2.0)];
+        collector.record(stats);
+        collector.record(GameStats::new("random", 16));
+
+        for restored in [StatsCollector::from_csv(&collector.to_csv()).unwrap(), StatsCollector::from_jsonl(&collector.to_jsonl()).unwrap()] {
+            assert_eq!(restored.games.len(), 2);
+            assert_eq!(restored.games[0].strategy, collector.games[0].strategy);
+            assert_eq!(restored.games[0].seed, Some(42));
+            assert_eq!((restored.games[0].rounds, restored.games[0].correct_booths), (7, 3));
+            assert_eq!(restored.games[0].timings.booth, std::time::Duration::from_micros(1500));
+            assert_eq!((restored.games[0].timings.booth_calls, restored.games[0].peak_memory), (3, 4096));
+            assert_eq!(restored.games[0].strategy_stats, vec![(String::from("rounds_used"), 2.0)]);
+            assert_eq!(restored.games[1].seed, None);
+            assert!(restored.games[1].strategy_stats.is_empty());
+        }
+
+        let broken = collector.to_csv().replace(",7,", ",seven,");
+        assert_eq!(StatsCollector::from_csv(&broken).err().map(|error: RecordError| error.line), Some(2));
+        assert!(StatsCollector::from_jsonl("{").is_err());
+
+        // Records from before calls and memory were tracked still load
+        let old = "seed,strategy,num_contestants,won,rounds,ceremonies,zero_beam_ceremonies,booths,correct_booths,correct_booth_rate,ceremony_ms,ceremony_feedback_ms,booth_ms,booth_feedback_ms\n,random,16,false,500,500,3,500,20,0.0400,1.0,1.0,1.0,1.0\n";
+        let restored = StatsCollector::from_csv(old).unwrap();
+        assert_eq!((restored.games[0].rounds, restored.games[0].timings.booth_calls, restored.games[0].peak_memory), (500, 0, 0));
+    }
+}
@@ -1,9 +1,9 @@
 use std::collections::HashSet;
 use std::collections::HashMap;
-use crate::contestant::Player;
+use crate::contestant::{Contestant, Player};
 use crate::contestant::ContestantPair;
 
-pub fn pairs_to_contestants(pairs: &Vec<ContestantPair>) -> Vec<Player> {
+pub fn pairs_to_contestants<P: Contestant>(pairs: &[ContestantPair<P>]) -> Vec<P> {
     let mut players = Vec::new();
     for p in pairs.iter() {
         players.push(p.get_a().clone());
@@ -12,7 +12,7 @@ pub fn pairs_to_contestants(pairs: &Vec<ContestantPair>) -> Vec<Player> {
     return players;
 }
 
-pub fn contestants_to_pairs(contestants: &Vec<Player>) -> Vec<ContestantPair> {
+pub fn contestants_to_pairs<P: Contestant>(contestants: &[P]) -> Vec<ContestantPair<P>> {
     let mut pairs = Vec::new();
 
     for i in (0..contestants.len() - 1).step_by(2) {
@@ -22,7 +22,7 @@ pub fn contestants_to_pairs(contestants: &Vec<Player>) -> Vec<ContestantPair> {
 }
 
 
-pub fn get_matches(perf_matches: &Vec<ContestantPair>, num_perfect: usize, num_unperfect: usize) -> Vec<ContestantPair> {
+pub fn get_matches(perf_matches: &[ContestantPair], num_perfect: usize, num_unperfect: usize) -> Vec<ContestantPair> {
     if num_perfect + num_unperfect > perf_matches.len() {
         panic!("{} matches can't be made from {} contestants", perf_matches.len(), num_perfect + num_unperfect);
     }
@@ -37,7 +37,7 @@ pub fn get_matches(perf_matches: &Vec<ContestantPair>, num_perfect: usize, num_u
     }
 
     // do not include the already perfectly matched
-    let to_unmatch = pairs_to_contestants(&perf_matches[num_perfect..].to_vec());
+    let to_unmatch = pairs_to_contestants(&perf_matches[num_perfect..]);
 
     // Work forwards and backwards and pair those two contestants together
     for i in 0..to_unmatch.len() / 2{
@@ -57,14 +57,22 @@ pub fn gen_contestants(num: usize) -> Vec<Player> {
     players
 }
 
-pub fn pretty_string_poss(possibilities: &HashMap<Player, HashSet<Player>>) -> String {
+/* Bits of information gained from a yes/no answer that is yes with probability p */
+pub fn binary_entropy(p: f64) -> f64 {
+    if p <= 0.0 || p >= 1.0 {
+        return 0.0;
+    }
+    return -p * p.log2() - (1.0 - p) * (1.0 - p).log2();
+}
+
+pub fn pretty_string_poss<P: Contestant>(possibilities: &HashMap<P, HashSet<P>>) -> String {
     let mut total_str = String::new();
     for (player, player_poss) in possibilities.iter() {
         let mut poss_string = String::new();
         for poss in player_poss.iter() {
-            poss_string.push_str((poss.id.to_string() + " ,").as_str());
+            poss_string.push_str((poss.to_string() + " ,").as_str());
         }
-        total_str.push_str(format!("{}: {{{}}}\n", player.id, poss_string).as_str());
+        total_str.push_str(format!("{}: {{{}}}\n", player, poss_string).as_str());
     }
     return total_str;
 }
@@ -72,5 +80,13 @@ pub fn pretty_string_poss(possibilities: &HashMap<Player, HashSet<Player>>) -> S
 
 #[cfg(test)]
 mod tests {
-    
+    use crate::utils::binary_entropy;
+
+    #[test]
+    fn test_binary_entropy() {
+        assert_eq!(binary_entropy(0.5), 1.0);
+        assert_eq!(binary_entropy(0.0), 0.0);
+        assert_eq!(binary_entropy(1.0), 0.0);
+        assert!((binary_entropy(0.25) - binary_entropy(0.75)).abs() < 1e-12);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+use crate::contestant::{ContestantPair, Player};
+use crate::history::{History, Observation};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+/* How the house picks who goes to the truth booth when the strategy doesn't get a say */
+#[derive(Debug, Clone, PartialEq)]
+pub enum VotePolicy {
+    // Any couple the booth hasn't already ruled on
+    Random,
+    // The couple that sat together in ceremonies with the most beams
+    MostBeams,
+    // Couples in love: a real match with the given chance, a random couple otherwise
+    Couples { accuracy: f64 },
+}
+
+impl VotePolicy {
+    /* Up to count couples for this week's booths, sharing nobody. Couples the booth already
+    ruled on aren't voted for again */
+    pub fn vote<R: Rng>(&self, history: &History, matches: &[ContestantPair], count: usize, rng: &mut R) -> Vec<ContestantPair> {
+        let mut candidates = untested_pairs(history);
+        candidates.shuffle(rng);
+        match self {
+            VotePolicy::Random => (),
+            VotePolicy::MostBeams => {
+                let beams = beams_together(history);
+                let together = |pair: &ContestantPair| beams.get(pair).cloned().unwrap_or(0);
+                candidates.sort_by_key(|pair| std::cmp::Reverse(together(pair)));
+            }
+            VotePolicy::Couples { accuracy } => {
+                let in_love: Vec<bool> = candidates.iter().map(|_| rng.gen::<f64>() < *accuracy).collect();
+                let mut ranked: Vec<(bool, ContestantPair)> =
+                    candidates.into_iter().zip(in_love).map(|(pair, love)| (love && matches.contains(&pair), pair)).collect();
+                ranked.sort_by_key(|(love, _)| !*love);
+                candidates = ranked.into_iter().map(|(_, pair)| pair).collect();
+            }
+        }
+
+        let mut votes: Vec<ContestantPair> = vec![];
+        let mut seated: HashSet<&Player> = HashSet::new();
+        for pair in candidates.iter() {
+            if votes.len() == count {
+                break;
+            }
+            if !seated.contains(pair.get_a()) && !seated.contains(pair.get_b()) {
+                seated.insert(pair.get_a());
+                seated.insert(pair.get_b());
+                votes.push(pair.clone());
+            }
+        }
+        return votes;
+    }
+}
+
+fn untested_pairs(history: &History) -> Vec<ContestantPair> {
+    let tested: HashSet<&ContestantPair> = history
+        .observations
+        .iter()
+        .filter_map(|observation| match observation {
+            Observation::Booth { pair, .. } => Some(pair),
+            Observation::Ceremony { .. } => None,
+        })
+        .collect();
+    let players = &history.contestants;
+    let mut pairs = vec![];
+    for (i, a) in players.iter().enumerate() {
+        for b in players[i + 1..].iter() {
+            let pair = ContestantPair::new(a.clone(), b.clone());
+            if !tested.contains(&pair) {
+                pairs.push(pair);
+            }
+        }
+    }
+    return pairs;
+}
+
+/* Total beams of every ceremony each couple sat together in */
+fn beams_together(history: &History) -> HashMap<ContestantPair, usize> {
+    let mut beams: HashMap<ContestantPair, usize> = HashMap::new();
+    for observation in history.observations.iter() {
+        if let Observation::Ceremony { pairs, beams: lit } = observation {
+            for pair in pairs.iter() {
+                *beams.entry(pair.clone()).or_insert(0) += lit;
+            }
+        }
+    }
+    return beams;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::{History, Observation};
+    use crate::utils::{contestants_to_pairs, gen_contestants, get_matches, pairs_to_contestants};
+    use crate::voting::VotePolicy;
+    use rand::thread_rng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_vote_policies() {
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut history = History::new(c.clone());
+        history.record(Observation::Booth { pair: truth[0].clone(), is_match: true });
+        let mut rng = thread_rng();
+
+        // Nobody is voted in twice, and the booth's couples aren't sent back
+        let votes = VotePolicy::Random.vote(&history, &truth, 3, &mut rng);
+        assert_eq!(votes.len(), 3);
+        assert!(!votes.contains(&truth[0]));
+        assert_eq!(pairs_to_contestants(&votes).into_iter().collect::<HashSet<_>>().len(), 6);
+
+        let seated = get_matches(&truth, 1, 3);
+        history.record(Observation::Ceremony { pairs: seated.clone(), beams: 2 });
+        let votes = VotePolicy::MostBeams.vote(&history, &truth, 1, &mut rng);
+        assert!(seated.contains(&votes[0]));
+
+        let votes = VotePolicy::Couples { accuracy: 1.0 }.vote(&history, &truth, 2, &mut rng);
+        assert!(votes.iter().all(|vote| truth.contains(vote)));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::contestant::{Contestant, ContestantPair, Player};
+use crate::history::{History, Observation};
+use crate::solutionspace::{SolutionSpace, SpaceSummary};
+
+/* Something the house could do this week */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action<P: Contestant = Player> {
+    Booth(ContestantPair<P>),
+    Ceremony(Vec<ContestantPair<P>>),
+}
+
+impl<P: Contestant> Action<P> {
+    /* A booth is either a match (1) or not (0), a ceremony can light up anywhere from 0 to every pair */
+    pub fn num_outcomes(&self) -> usize {
+        return match self {
+            Action::Booth(_) => 2,
+            Action::Ceremony(pairs) => pairs.len() + 1,
+        };
+    }
+
+    pub fn observation(&self, outcome: usize) -> Observation<P> {
+        return match self {
+            Action::Booth(pair) => Observation::Booth { pair: pair.clone(), is_match: outcome == 1 },
+            Action::Ceremony(pairs) => Observation::Ceremony { pairs: pairs.clone(), beams: outcome },
+        };
+    }
+
+    /* Which outcome the action would have if the partners were the truth */
+    fn outcome(&self, space: &SolutionSpace<P>, partner: &[usize]) -> usize {
+        let is_match = |pair: &ContestantPair<P>| {
+            let (a, b) = space.pair_indices(pair);
+            partner[a] == b
+        };
+        return match self {
+            Action::Booth(pair) => is_match(pair) as usize,
+            Action::Ceremony(pairs) => pairs.iter().filter(|pair| is_match(pair)).count(),
+        };
+    }
+}
+
+/* One outcome of an action and the solution space it would leave behind */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario<P: Contestant = Player> {
+    pub observation: Observation<P>,
+    pub probability: f64,
+    pub summary: SpaceSummary<P>,
+}
+
+/* Every outcome of an action, including the impossible ones (with probability 0) */
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhatIf<P: Contestant = Player> {
+    pub before: u64,
+    pub scenarios: Vec<Scenario<P>>,
+}
+
+impl<P: Contestant> WhatIf<P> {
+    /* Bits the action is expected to reveal, i.e. the entropy of its outcome */
+    pub fn expected_information(&self) -> f64 {
+        return self
+            .scenarios
+            .iter()
+            .filter(|s| s.probability > 0.0)
+            .map(|s| -s.probability * s.probability.log2())
+            .sum();
+    }
+
+    /* The number of matchings expected to be left once the outcome is known */
+    pub fn expected_remaining(&self) -> f64 {
+        return self.scenarios.iter().map(|s| s.probability * s.summary.count as f64).sum();
+    }
+
+    pub fn possible_scenarios(&self) -> Vec<&Scenario<P>> {
+        return self.scenarios.iter().filter(|s| s.summary.count > 0).collect();
+    }
+}
+
+/* The solution space that a single hypothetical observation would leave. The history is not changed. */
+pub fn what_if<P: Contestant>(history: &History<P>, observation: &Observation<P>) -> SpaceSummary<P> {
+    return SolutionSpace::from_history(&history.with(observation.clone())).summarize();
+}
+
+/* Works out every outcome of the action in a single pass over the current solution space */
+pub fn analyze_action<P: Contestant>(history: &History<P>, action: &Action<P>) -> WhatIf<P> {
+    let space = SolutionSpace::from_history(history);
+    return analyze_action_in(&space, action);
+}
+
+pub fn analyze_action_in<P: Contestant>(space: &SolutionSpace<P>, action: &Action<P>) -> WhatIf<P> {
+    let summaries = space.partition(action.num_outcomes(), |partner| action.outcome(space, partner));
+    let before: u64 = summaries.iter().map(|s| s.count).sum();
+
+    let scenarios = summaries
+        .into_iter()
+        .enumerate()
+        .map(|(outcome, summary)| Scenario {
+            observation: action.observation(outcome),
+            probability: if before == 0 { 0.0 } else { summary.count as f64 / before as f64 },
+            summary,
+        })
+        .collect();
+    return WhatIf { before, scenarios };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::contestant::ContestantPair;
+    use crate::history::{History, Observation};
+    use crate::utils::{contestants_to_pairs, gen_contestants, get_matches};
+    use crate::whatif::{analyze_action, what_if, Action};
+
+    #[test]
+    fn test_booth_what_if() {
+        let c = gen_contestants(6);
+        let history = History::new(c.clone());
+        let pair = ContestantPair::new(c[0].clone(), c[1].clone());
+
+        let analysis = analyze_action(&history, &Action::Booth(pair.clone()));
+        assert_eq!(analysis.before, 15);
+        assert_eq!(analysis.scenarios.len(), 2);
+        assert_eq!(analysis.scenarios[0].summary.count, 12);
+        assert_eq!(analysis.scenarios[1].summary.count, 3);
+        assert_eq!(analysis.scenarios[1].probability, 0.2);
+        assert_eq!(analysis.scenarios[1].summary.forced_pairs(), vec![pair.clone()]);
+
+        // The single outcome version agrees, and neither touches the history
+        let no_match = Observation::Booth { pair, is_match: false };
+        assert_eq!(what_if(&history, &no_match), analysis.scenarios[0].summary);
+        assert!(history.observations.is_empty());
+    }
+
+    #[test]
+    fn test_ceremony_what_if() {
+        let c = gen_contestants(8);
+        let truth = contestants_to_pairs(&c);
+        let mut history = History::new(c.clone());
+        history.record(Observation::Booth { pair: truth[0].clone(), is_match: true });
+
+        let ceremony = get_matches(&truth, 1, 3);
+        let analysis = analyze_action(&history, &Action::Ceremony(ceremony.clone()));
+        assert_eq!(analysis.scenarios.len(), 5);
+        assert_eq!(analysis.before, 15);
+
+        // The confirmed pair always lights up, so there is never a zero
+        assert_eq!(analysis.scenarios[0].summary.count, 0);
+        assert_eq!(analysis.possible_scenarios().len(), analysis.scenarios.iter().filter(|s| s.probability > 0.0).count());
+        let total: u64 = analysis.scenarios.iter().map(|s| s.summary.count).sum();
+        assert_eq!(total, 15);
+        assert!(analysis.expected_information() > 0.0);
+        assert!(analysis.expected_remaining() < 15.0);
+    }
+}